@@ -0,0 +1,10 @@
+UPDATE beatmapsets SET deleted = FALSE WHERE deleted IS NULL;
+ALTER TABLE beatmapsets ALTER COLUMN deleted SET NOT NULL;
+ALTER TABLE beatmapsets ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+
+UPDATE beatmaps SET deleted = FALSE WHERE deleted IS NULL;
+ALTER TABLE beatmaps ALTER COLUMN deleted SET NOT NULL;
+ALTER TABLE beatmaps ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS idx_beatmapsets_updated_at_live
+    ON beatmapsets(updated_at ASC) WHERE NOT deleted;
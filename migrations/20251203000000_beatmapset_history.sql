@@ -0,0 +1,23 @@
+CREATE TABLE IF NOT EXISTS beatmapset_history (
+    id BIGSERIAL PRIMARY KEY,
+    beatmapset_id BIGINT NOT NULL REFERENCES beatmapsets(id) ON DELETE CASCADE,
+
+    changes TEXT[] NOT NULL,
+
+    previous_status VARCHAR(20),
+    status VARCHAR(20) NOT NULL,
+    previous_ranked_date TIMESTAMPTZ,
+    ranked_date TIMESTAMPTZ,
+    previous_last_updated TIMESTAMPTZ,
+    last_updated TIMESTAMPTZ,
+
+    beatmaps JSONB NOT NULL DEFAULT '[]',
+    beatmap_changes JSONB NOT NULL DEFAULT '[]',
+
+    recorded_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_beatmapset_history_beatmapset_id
+    ON beatmapset_history(beatmapset_id, id DESC);
+CREATE INDEX IF NOT EXISTS idx_beatmapset_history_recorded_at
+    ON beatmapset_history(recorded_at DESC);
@@ -0,0 +1,10 @@
+-- Beatmapsets queued for a forced refetch through the admin API.
+-- No foreign key: queueing an id we have never seen is how single sets get imported.
+CREATE TABLE IF NOT EXISTS resync_queue (
+    beatmapset_id BIGINT PRIMARY KEY,
+    requested_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_resync_queue_requested_at ON resync_queue (requested_at);
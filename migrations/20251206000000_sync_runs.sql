@@ -0,0 +1,19 @@
+-- One row per crawler worker cycle that fetched something, failed, or moved its cursor.
+CREATE TABLE IF NOT EXISTS sync_runs (
+    id BIGSERIAL PRIMARY KEY,
+    worker_id VARCHAR(50) NOT NULL,
+    started_at TIMESTAMPTZ NOT NULL,
+    finished_at TIMESTAMPTZ NOT NULL,
+    fetched INTEGER NOT NULL DEFAULT 0,
+    inserted INTEGER NOT NULL DEFAULT 0,
+    updated INTEGER NOT NULL DEFAULT 0,
+    unchanged INTEGER NOT NULL DEFAULT 0,
+    deleted INTEGER NOT NULL DEFAULT 0,
+    errors INTEGER NOT NULL DEFAULT 0,
+    cursor_before TEXT,
+    cursor_after TEXT,
+    error TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_sync_runs_worker ON sync_runs (worker_id, id DESC);
+CREATE INDEX IF NOT EXISTS idx_sync_runs_finished_at ON sync_runs (finished_at);
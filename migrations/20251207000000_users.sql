@@ -0,0 +1,26 @@
+-- Mapper profiles fetched from the osu! users endpoint.
+CREATE TABLE IF NOT EXISTS users (
+    id BIGINT PRIMARY KEY,
+    username VARCHAR(255) NOT NULL,
+    previous_usernames TEXT[] NOT NULL DEFAULT '{}',
+    country_code VARCHAR(2),
+    country_name VARCHAR(100),
+    avatar_url TEXT,
+
+    ranked_beatmapset_count INTEGER NOT NULL DEFAULT 0,
+    loved_beatmapset_count INTEGER NOT NULL DEFAULT 0,
+    pending_beatmapset_count INTEGER NOT NULL DEFAULT 0,
+    graveyard_beatmapset_count INTEGER NOT NULL DEFAULT 0,
+    guest_beatmapset_count INTEGER NOT NULL DEFAULT 0,
+
+    -- FALSE when osu! answers 404 (restricted or deleted account).
+    available BOOLEAN NOT NULL DEFAULT TRUE,
+
+    fetched_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_users_username_trgm ON users USING GIN (username gin_trgm_ops);
+CREATE INDEX IF NOT EXISTS idx_users_fetched_at ON users (fetched_at);
+CREATE INDEX IF NOT EXISTS idx_beatmapsets_creator_id ON beatmapsets (creator_id);
@@ -0,0 +1,26 @@
+CREATE TABLE IF NOT EXISTS beatmap_packs (
+    tag VARCHAR(32) PRIMARY KEY,
+    name TEXT NOT NULL,
+    author TEXT,
+    pack_type VARCHAR(20) NOT NULL,
+    date TIMESTAMPTZ,
+    url TEXT,
+    ruleset_id INTEGER,
+    no_diff_reduction BOOLEAN NOT NULL DEFAULT FALSE,
+    -- NULL until the pack's set list has been fetched.
+    sets_synced_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_beatmap_packs_type_date ON beatmap_packs (pack_type, date DESC, tag DESC);
+CREATE INDEX IF NOT EXISTS idx_beatmap_packs_date ON beatmap_packs (date DESC, tag DESC);
+
+-- No foreign key to beatmapsets: packs may list sets the crawler hasn't stored yet.
+CREATE TABLE IF NOT EXISTS beatmap_pack_sets (
+    pack_tag VARCHAR(32) NOT NULL REFERENCES beatmap_packs(tag) ON DELETE CASCADE,
+    beatmapset_id BIGINT NOT NULL,
+    PRIMARY KEY (pack_tag, beatmapset_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_beatmap_pack_sets_beatmapset ON beatmap_pack_sets (beatmapset_id);
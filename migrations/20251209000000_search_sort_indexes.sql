@@ -0,0 +1,16 @@
+-- Keyset pagination for /v2/search orders by these expressions with the set id
+-- as tie-breaker, in either direction.
+CREATE INDEX IF NOT EXISTS idx_beatmapsets_sort_ranked
+    ON beatmapsets ((COALESCE(ranked_date, 'epoch'::TIMESTAMPTZ)), id);
+CREATE INDEX IF NOT EXISTS idx_beatmapsets_sort_updated
+    ON beatmapsets ((COALESCE(last_updated, 'epoch'::TIMESTAMPTZ)), id);
+CREATE INDEX IF NOT EXISTS idx_beatmapsets_sort_plays
+    ON beatmapsets ((COALESCE(play_count, 0)::BIGINT), id);
+CREATE INDEX IF NOT EXISTS idx_beatmapsets_sort_favourites
+    ON beatmapsets ((COALESCE(favourite_count, 0)::BIGINT), id);
+CREATE INDEX IF NOT EXISTS idx_beatmapsets_sort_rating
+    ON beatmapsets ((COALESCE(rating, 0)), id);
+CREATE INDEX IF NOT EXISTS idx_beatmapsets_sort_title
+    ON beatmapsets ((title::TEXT), id);
+CREATE INDEX IF NOT EXISTS idx_beatmapsets_sort_artist
+    ON beatmapsets ((artist::TEXT), id);
@@ -0,0 +1,26 @@
+-- Weighted full-text vector for keyword search: title and artist (A), source
+-- and creator (B), tags (C). The `simple` config neither stems nor drops stop
+-- words, which suits song titles and keeps unspaced CJK text as whole tokens
+-- that prefix queries can still match.
+ALTER TABLE beatmapsets ADD COLUMN IF NOT EXISTS search_vector TSVECTOR
+    GENERATED ALWAYS AS (
+        setweight(to_tsvector('simple'::REGCONFIG,
+            COALESCE(title, '') || ' ' || COALESCE(title_unicode, '') || ' ' ||
+            COALESCE(artist, '') || ' ' || COALESCE(artist_unicode, '')), 'A') ||
+        setweight(to_tsvector('simple'::REGCONFIG,
+            COALESCE(source, '') || ' ' || COALESCE(creator, '')), 'B') ||
+        setweight(to_tsvector('simple'::REGCONFIG, COALESCE(tags, '')), 'C')
+    ) STORED;
+
+CREATE INDEX IF NOT EXISTS idx_beatmapsets_search_vector
+    ON beatmapsets USING gin (search_vector);
+
+-- Typo-tolerant matching on the fields the init migration didn't cover.
+CREATE INDEX IF NOT EXISTS idx_beatmapsets_title_unicode_trgm
+    ON beatmapsets USING gin (title_unicode gin_trgm_ops);
+
+CREATE INDEX IF NOT EXISTS idx_beatmapsets_artist_unicode_trgm
+    ON beatmapsets USING gin (artist_unicode gin_trgm_ops);
+
+CREATE INDEX IF NOT EXISTS idx_beatmapsets_source_trgm
+    ON beatmapsets USING gin (source gin_trgm_ops);
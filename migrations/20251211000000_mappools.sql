@@ -0,0 +1,25 @@
+CREATE TABLE IF NOT EXISTS mappools (
+    id BIGSERIAL PRIMARY KEY,
+    tournament TEXT NOT NULL,
+    round TEXT NOT NULL,
+    name TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (tournament, round, name)
+);
+
+-- No foreign key to beatmaps: a pool may be drafted before its maps are stored.
+CREATE TABLE IF NOT EXISTS mappool_slots (
+    mappool_id BIGINT NOT NULL REFERENCES mappools(id) ON DELETE CASCADE,
+    slot VARCHAR(16) NOT NULL,
+    position INTEGER NOT NULL,
+    beatmap_id BIGINT NOT NULL,
+    -- Legacy mod bitmask the slot is played with.
+    mods INTEGER NOT NULL DEFAULT 0,
+    -- Star rating under `mods` as reported by osu!. NULL when the mods don't
+    -- change it or it couldn't be fetched.
+    star_rating DOUBLE PRECISION,
+    PRIMARY KEY (mappool_id, slot)
+);
+
+CREATE INDEX IF NOT EXISTS idx_mappool_slots_beatmap ON mappool_slots (beatmap_id);
@@ -0,0 +1,20 @@
+-- Difficulty attributes precomputed from cached .osu files for common mod
+-- combinations, so search can filter on modded star ratings.
+CREATE TABLE IF NOT EXISTS beatmap_difficulty_attributes (
+    beatmap_id BIGINT NOT NULL REFERENCES beatmaps(id) ON DELETE CASCADE,
+    -- Legacy mod bitmask.
+    mods INTEGER NOT NULL,
+    -- Checksum of the .osu the values were calculated from; rows that no longer
+    -- match the beatmap are stale and get recalculated.
+    checksum VARCHAR(32),
+    star_rating DOUBLE PRECISION NOT NULL,
+    -- osu! standard only.
+    aim DOUBLE PRECISION,
+    speed DOUBLE PRECISION,
+    max_combo INTEGER NOT NULL,
+    computed_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (beatmap_id, mods)
+);
+
+CREATE INDEX IF NOT EXISTS idx_beatmap_difficulty_attributes_mods_stars
+    ON beatmap_difficulty_attributes (mods, star_rating);
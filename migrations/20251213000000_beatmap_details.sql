@@ -0,0 +1,32 @@
+-- Values parsed from each difficulty's .osu file that the osu! API doesn't
+-- return. Times are in milliseconds.
+CREATE TABLE IF NOT EXISTS beatmap_details (
+    beatmap_id BIGINT PRIMARY KEY REFERENCES beatmaps(id) ON DELETE CASCADE,
+    -- Checksum of the parsed .osu; rows that no longer match the beatmap are
+    -- stale and get parsed again.
+    checksum VARCHAR(32),
+    audio_filename TEXT,
+    audio_lead_in INTEGER NOT NULL DEFAULT 0,
+    preview_time INTEGER,
+    -- osu!mania only.
+    keys INTEGER,
+    bpm_min DOUBLE PRECISION,
+    bpm_max DOUBLE PRECISION,
+    bpm_main DOUBLE PRECISION,
+    slider_multiplier DOUBLE PRECISION NOT NULL,
+    slider_tick_rate DOUBLE PRECISION NOT NULL,
+    circles INTEGER NOT NULL DEFAULT 0,
+    sliders INTEGER NOT NULL DEFAULT 0,
+    spinners INTEGER NOT NULL DEFAULT 0,
+    holds INTEGER NOT NULL DEFAULT 0,
+    first_object_time INTEGER,
+    last_object_time INTEGER,
+    -- Time from the first object to the end of the last one, minus breaks.
+    drain_time INTEGER,
+    breaks JSONB NOT NULL DEFAULT '[]',
+    -- Objects per slice of the map, first to last object.
+    density JSONB NOT NULL DEFAULT '[]',
+    parsed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_beatmap_details_keys ON beatmap_details (keys) WHERE keys IS NOT NULL;
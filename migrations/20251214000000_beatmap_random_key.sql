@@ -0,0 +1,7 @@
+-- Random picks seek from a random point in this column instead of sorting the
+-- whole table by random().
+ALTER TABLE beatmaps ADD COLUMN IF NOT EXISTS random_key DOUBLE PRECISION NOT NULL DEFAULT random();
+CREATE INDEX IF NOT EXISTS idx_beatmaps_random_key ON beatmaps (random_key);
+
+-- Similar maps are searched in a star rating window within one mode.
+CREATE INDEX IF NOT EXISTS idx_beatmaps_mode_difficulty ON beatmaps (mode_int, difficulty_rating);
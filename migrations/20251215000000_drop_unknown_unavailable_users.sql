@@ -0,0 +1,5 @@
+-- Unavailable rows were once written for any id looked up at /v2/users/{id};
+-- keep only those of mappers we have sets from.
+DELETE FROM users u
+WHERE NOT u.available
+  AND NOT EXISTS (SELECT 1 FROM beatmapsets s WHERE s.creator_id = u.id);
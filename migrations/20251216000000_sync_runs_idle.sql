@@ -0,0 +1,4 @@
+-- Idle cycles are recorded too. Consecutive idle cycles of a worker share one
+-- row, whose `runs` counts them and whose `finished_at` is the end of the last.
+ALTER TABLE sync_runs ADD COLUMN IF NOT EXISTS idle BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE sync_runs ADD COLUMN IF NOT EXISTS runs INTEGER NOT NULL DEFAULT 1;
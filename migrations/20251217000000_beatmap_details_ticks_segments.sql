@@ -0,0 +1,7 @@
+-- Ticks of each slider and the parts of the map between breaks. Details parsed
+-- before these existed are dropped so they're parsed again.
+ALTER TABLE beatmap_details ADD COLUMN IF NOT EXISTS slider_ticks JSONB NOT NULL DEFAULT '[]';
+ALTER TABLE beatmap_details ADD COLUMN IF NOT EXISTS drain_segments JSONB NOT NULL DEFAULT '[]';
+
+DELETE FROM beatmap_details WHERE sliders > 0 AND slider_ticks = '[]';
+DELETE FROM beatmap_details WHERE drain_time > 0 AND drain_segments = '[]';
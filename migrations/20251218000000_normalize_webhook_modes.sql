@@ -0,0 +1,13 @@
+-- Webhook modes are matched against osu!'s mode names; rewrite numbers and
+-- aliases stored before they were normalized.
+UPDATE webhooks SET modes = ARRAY(
+    SELECT DISTINCT CASE lower(trim(m))
+        WHEN '0' THEN 'osu' WHEN 'std' THEN 'osu' WHEN 'standard' THEN 'osu'
+        WHEN '1' THEN 'taiko'
+        WHEN '2' THEN 'fruits' WHEN 'catch' THEN 'fruits' WHEN 'ctb' THEN 'fruits'
+        WHEN '3' THEN 'mania'
+        ELSE lower(trim(m))
+    END
+    FROM unnest(modes) AS m
+)
+WHERE modes <> '{}';
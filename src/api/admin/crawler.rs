@@ -0,0 +1,147 @@
+use crate::{
+    AppState,
+    crawler::{registry::WorkerStatus, scheduler::RESYNC_MAX_ATTEMPTS, sync},
+    db::queries,
+    error::{AppError, Result},
+};
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use serde::{Deserialize, Serialize};
+
+/// Largest number of ids a single resync request may queue.
+const MAX_RESYNC_IDS: i64 = 100_000;
+
+#[derive(Serialize)]
+pub struct CrawlerStatus {
+    enabled: bool,
+    workers: Vec<WorkerStatus>,
+    resync_queue: ResyncQueueStatus,
+}
+
+#[derive(Serialize)]
+pub struct ResyncQueueStatus {
+    pending: i64,
+    failed: i64,
+}
+
+#[derive(Deserialize)]
+pub struct ResyncRequest {
+    #[serde(default)]
+    ids: Vec<i64>,
+    /// Inclusive `[from, to]` id ranges.
+    #[serde(default)]
+    ranges: Vec<(i64, i64)>,
+}
+
+#[derive(Serialize)]
+pub struct ResyncResponse {
+    queued: u64,
+}
+
+pub async fn crawler_status(State(state): State<AppState>) -> Result<Json<CrawlerStatus>> {
+    let (pending, failed) = queries::count_resync_queue(&state.db, RESYNC_MAX_ATTEMPTS).await?;
+
+    Ok(Json(CrawlerStatus {
+        enabled: state.config.crawler.enabled,
+        workers: state.crawler.list(),
+        resync_queue: ResyncQueueStatus { pending, failed },
+    }))
+}
+
+pub async fn pause_worker(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<WorkerStatus>> {
+    set_paused(&state, &id, true)
+}
+
+pub async fn resume_worker(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<WorkerStatus>> {
+    set_paused(&state, &id, false)
+}
+
+fn set_paused(state: &AppState, id: &str, paused: bool) -> Result<Json<WorkerStatus>> {
+    if !state.crawler.set_paused(id, paused) {
+        return Err(AppError::NotFound(format!("Worker {} not found", id)));
+    }
+    tracing::info!(
+        "Crawler worker {} {}",
+        id,
+        if paused { "paused" } else { "resumed" }
+    );
+
+    state
+        .crawler
+        .get(id)
+        .map(Json)
+        .ok_or_else(|| AppError::NotFound(format!("Worker {} not found", id)))
+}
+
+pub async fn reset_cursor(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode> {
+    let removed = sync::reset_cursor(&state.db, &id)
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+
+    if !removed && !state.crawler.contains(&id) {
+        return Err(AppError::NotFound(format!("Cursor {} not found", id)));
+    }
+
+    state.crawler.set_cursor(&id, None);
+    tracing::info!("Crawler cursor {} reset", id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn enqueue_resync(
+    State(state): State<AppState>,
+    Json(body): Json<ResyncRequest>,
+) -> Result<(StatusCode, Json<ResyncResponse>)> {
+    if body.ids.is_empty() && body.ranges.is_empty() {
+        return Err(AppError::BadRequest(
+            "ids or ranges must be given".to_string(),
+        ));
+    }
+
+    let too_many = || {
+        AppError::BadRequest(format!(
+            "at most {} ids can be queued at once",
+            MAX_RESYNC_IDS
+        ))
+    };
+    let mut total = body.ids.len() as i64;
+    for &(from, to) in &body.ranges {
+        if from <= 0 || to < from {
+            return Err(AppError::BadRequest(format!(
+                "invalid range [{}, {}]",
+                from, to
+            )));
+        }
+        total = to
+            .checked_sub(from)
+            .and_then(|n| n.checked_add(1))
+            .and_then(|n| total.checked_add(n))
+            .filter(|&n| n <= MAX_RESYNC_IDS)
+            .ok_or_else(too_many)?;
+    }
+    if total > MAX_RESYNC_IDS {
+        return Err(too_many());
+    }
+
+    let mut queued = 0;
+    if !body.ids.is_empty() {
+        queued += queries::enqueue_resync(&state.db, &body.ids).await?;
+    }
+    for &(from, to) in &body.ranges {
+        queued += queries::enqueue_resync_range(&state.db, from, to).await?;
+    }
+
+    tracing::info!("Queued {} beatmapsets for resync", queued);
+    Ok((StatusCode::ACCEPTED, Json(ResyncResponse { queued })))
+}
@@ -0,0 +1,214 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use serde::Deserialize;
+use std::collections::HashSet;
+
+use crate::{
+    AppState,
+    api::v2::mappools::{MappoolV2, load_mappool},
+    crawler,
+    db::{models::MappoolSlot, queries},
+    error::{AppError, Result},
+    mods::Mods,
+};
+
+/// Keeps the whole pool downloadable through one `/d/bulk` URL.
+const MAX_SLOTS: usize = 50;
+
+#[derive(Deserialize)]
+pub struct SaveMappool {
+    tournament: String,
+    round: String,
+    name: String,
+    slots: Vec<SaveMappoolSlot>,
+}
+
+#[derive(Deserialize)]
+pub struct SaveMappoolSlot {
+    slot: String,
+    beatmap_id: i64,
+    /// Acronyms like `HDHR`; taken from the slot name when omitted.
+    #[serde(default)]
+    mods: Option<String>,
+}
+
+pub async fn create_mappool(
+    State(state): State<AppState>,
+    Json(body): Json<SaveMappool>,
+) -> Result<(StatusCode, Json<MappoolV2>)> {
+    let id = save(&state, None, body).await?;
+    let pool = load_mappool(&state, id).await?;
+    pool.map(|p| (StatusCode::CREATED, Json(p)))
+        .ok_or_else(|| AppError::Internal("mappool vanished after saving".to_string()))
+}
+
+/// Replaces a pool's details and all of its slots.
+pub async fn update_mappool(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Json(body): Json<SaveMappool>,
+) -> Result<Json<MappoolV2>> {
+    let id = save(&state, Some(id), body).await?;
+    let pool = load_mappool(&state, id).await?;
+    pool.map(Json)
+        .ok_or_else(|| AppError::Internal("mappool vanished after saving".to_string()))
+}
+
+pub async fn delete_mappool(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<StatusCode> {
+    if queries::delete_mappool(&state.db, id).await? {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(AppError::NotFound(format!("Mappool {} not found", id)))
+    }
+}
+
+async fn save(state: &AppState, id: Option<i64>, body: SaveMappool) -> Result<i64> {
+    for (field, value) in [
+        ("tournament", &body.tournament),
+        ("round", &body.round),
+        ("name", &body.name),
+    ] {
+        if value.trim().is_empty() {
+            return Err(AppError::BadRequest(format!("{} must not be empty", field)));
+        }
+    }
+    if body.slots.len() > MAX_SLOTS {
+        return Err(AppError::BadRequest(format!(
+            "a mappool has at most {} slots",
+            MAX_SLOTS
+        )));
+    }
+
+    let mut names = HashSet::new();
+    let mut slots = Vec::with_capacity(body.slots.len());
+    for s in body.slots {
+        let slot = s.slot.trim().to_uppercase();
+        if slot.is_empty() || slot.len() > 16 {
+            return Err(AppError::BadRequest(format!(
+                "invalid slot name '{}'",
+                s.slot
+            )));
+        }
+        if !names.insert(slot.clone()) {
+            return Err(AppError::BadRequest(format!("duplicate slot '{}'", slot)));
+        }
+
+        let mods = match s.mods.as_deref() {
+            Some(m) => Mods::parse(m)
+                .ok_or_else(|| AppError::BadRequest(format!("invalid mods '{}'", m)))?,
+            None => Mods::from_slot(&slot).ok_or_else(|| {
+                AppError::BadRequest(format!(
+                    "can't tell the mods of slot '{}', set mods explicitly",
+                    slot
+                ))
+            })?,
+        };
+
+        slots.push(MappoolSlot {
+            slot,
+            beatmap_id: s.beatmap_id,
+            mods: mods.bits(),
+            star_rating: None,
+        });
+    }
+
+    fetch_missing_beatmaps(state, &slots).await?;
+
+    for slot in &mut slots {
+        let mods = Mods::from_bits(slot.mods);
+        if !mods.changes_star_rating() {
+            continue;
+        }
+        match state
+            .osu_client
+            .get_beatmap_star_rating(slot.beatmap_id, slot.mods)
+            .await
+        {
+            Ok(rating) => slot.star_rating = rating,
+            Err(e) => tracing::warn!(
+                "Failed to fetch {} star rating of beatmap {}: {}",
+                mods.acronyms(),
+                slot.beatmap_id,
+                e
+            ),
+        }
+    }
+
+    let saved = queries::save_mappool(
+        &state.db,
+        id,
+        body.tournament.trim(),
+        body.round.trim(),
+        body.name.trim(),
+        &slots,
+    )
+    .await;
+
+    match saved {
+        Ok(Some(id)) => Ok(id),
+        Ok(None) => Err(AppError::NotFound(format!(
+            "Mappool {} not found",
+            id.unwrap_or_default()
+        ))),
+        Err(AppError::Database(e))
+            if e.as_database_error()
+                .is_some_and(|d| d.is_unique_violation()) =>
+        {
+            Err(AppError::BadRequest(format!(
+                "{} / {} already has a mappool named '{}'",
+                body.tournament.trim(),
+                body.round.trim(),
+                body.name.trim()
+            )))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Stores the sets of slot beatmaps we don't know yet so the sheet is complete.
+async fn fetch_missing_beatmaps(state: &AppState, slots: &[MappoolSlot]) -> Result<()> {
+    let ids: Vec<i64> = slots.iter().map(|s| s.beatmap_id).collect();
+    let known: HashSet<i64> = queries::get_beatmap_set_ids(&state.db, &ids)
+        .await?
+        .into_iter()
+        .map(|(id, _)| id)
+        .collect();
+    let unknown: Vec<i64> = ids.into_iter().filter(|id| !known.contains(id)).collect();
+    if unknown.is_empty() {
+        return Ok(());
+    }
+
+    tracing::info!(
+        "{} beatmaps not found locally → fetching from osu! API",
+        unknown.len()
+    );
+    let maps = match state.osu_client.get_beatmaps(&unknown).await {
+        Ok(maps) => maps,
+        Err(e) => {
+            tracing::warn!("Failed to fetch beatmaps from API: {}", e);
+            return Ok(());
+        }
+    };
+
+    let mut fetched = HashSet::new();
+    for map in maps {
+        if fetched.insert(map.beatmapset_id)
+            && let Err(e) =
+                crawler::sync::resync_beatmapset(&state.db, &state.osu_client, map.beatmapset_id)
+                    .await
+        {
+            tracing::warn!(
+                "Failed to fetch beatmapset {} from API: {}",
+                map.beatmapset_id,
+                e
+            );
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,4 @@
+pub mod crawler;
+pub mod mappools;
+pub mod routes;
+pub mod webhooks;
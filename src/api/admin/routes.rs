@@ -0,0 +1,31 @@
+use axum::{
+    Router,
+    routing::{delete, get, post, put},
+};
+
+use crate::AppState;
+
+use super::{crawler, mappools, webhooks};
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/crawler", get(crawler::crawler_status))
+        .route("/crawler/workers/{id}/pause", post(crawler::pause_worker))
+        .route("/crawler/workers/{id}/resume", post(crawler::resume_worker))
+        .route("/crawler/cursors/{id}", delete(crawler::reset_cursor))
+        .route("/crawler/resync", post(crawler::enqueue_resync))
+        .route("/mappools", post(mappools::create_mappool))
+        .route(
+            "/mappools/{id}",
+            put(mappools::update_mappool).delete(mappools::delete_mappool),
+        )
+        .route(
+            "/webhooks",
+            get(webhooks::list_webhooks).post(webhooks::create_webhook),
+        )
+        .route("/webhooks/{id}", delete(webhooks::delete_webhook))
+        .route(
+            "/webhooks/{id}/deliveries",
+            get(webhooks::list_webhook_deliveries),
+        )
+}
@@ -0,0 +1,107 @@
+use crate::{
+    AppState,
+    config::{default_webhook_events, default_webhook_format},
+    db::{
+        models::{Webhook, WebhookDelivery},
+        queries,
+    },
+    error::{AppError, Result},
+    webhooks::{FORMATS, TRIGGERS, normalize_modes},
+};
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct CreateWebhook {
+    url: String,
+    #[serde(default)]
+    secret: Option<String>,
+    #[serde(default = "default_webhook_events")]
+    events: Vec<String>,
+    #[serde(default)]
+    modes: Vec<String>,
+    #[serde(default = "default_webhook_format")]
+    format: String,
+}
+
+#[derive(Deserialize)]
+pub struct DeliveryParams {
+    #[serde(default = "default_limit")]
+    limit: i64,
+}
+
+fn default_limit() -> i64 {
+    50
+}
+
+pub async fn list_webhooks(State(state): State<AppState>) -> Result<Json<Vec<Webhook>>> {
+    Ok(Json(queries::list_webhooks(&state.db).await?))
+}
+
+pub async fn create_webhook(
+    State(state): State<AppState>,
+    Json(body): Json<CreateWebhook>,
+) -> Result<(StatusCode, Json<Webhook>)> {
+    if !body.url.starts_with("http://") && !body.url.starts_with("https://") {
+        return Err(AppError::BadRequest(
+            "url must be an http(s) URL".to_string(),
+        ));
+    }
+    if body.events.is_empty() {
+        return Err(AppError::BadRequest("events must not be empty".to_string()));
+    }
+    if let Some(e) = body.events.iter().find(|e| !TRIGGERS.contains(&e.as_str())) {
+        return Err(AppError::BadRequest(format!(
+            "unknown event '{}', expected one of {}",
+            e,
+            TRIGGERS.join(", ")
+        )));
+    }
+    if !FORMATS.contains(&body.format.as_str()) {
+        return Err(AppError::BadRequest(format!(
+            "unknown format '{}', expected one of {}",
+            body.format,
+            FORMATS.join(", ")
+        )));
+    }
+
+    let modes = normalize_modes(&body.modes).map_err(AppError::BadRequest)?;
+
+    let webhook = queries::insert_webhook(
+        &state.db,
+        &body.url,
+        body.secret.as_deref().filter(|s| !s.is_empty()),
+        &body.events,
+        &modes,
+        &body.format,
+    )
+    .await?;
+
+    Ok((StatusCode::CREATED, Json(webhook)))
+}
+
+pub async fn delete_webhook(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<StatusCode> {
+    if queries::delete_webhook(&state.db, id).await? {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(AppError::NotFound(format!("Webhook {} not found", id)))
+    }
+}
+
+pub async fn list_webhook_deliveries(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Query(params): Query<DeliveryParams>,
+) -> Result<Json<Vec<WebhookDelivery>>> {
+    let limit = params.limit.clamp(1, 200);
+    Ok(Json(
+        queries::get_webhook_deliveries(&state.db, id, limit).await?,
+    ))
+}
@@ -0,0 +1,59 @@
+use axum::{
+    Json,
+    extract::{Query, State},
+};
+use serde::Deserialize;
+
+use crate::{
+    AppState,
+    api::v2::mapping::{BeatmapsetV2, map_set_v2},
+    db::search::SearchFilter,
+    error::Result,
+};
+
+use super::search::{parse_mode, parse_sort, search_sets};
+
+#[derive(Deserialize)]
+pub struct CatboySearchParams {
+    #[serde(alias = "q", default)]
+    query: String,
+    #[serde(default = "default_limit")]
+    limit: i64,
+    #[serde(default)]
+    offset: i64,
+    /// A status name or number, or a comma separated list of them.
+    #[serde(default)]
+    status: Option<String>,
+    #[serde(default)]
+    mode: Option<String>,
+    #[serde(default)]
+    sort: Option<String>,
+}
+
+fn default_limit() -> i64 {
+    50
+}
+
+/// catboy.best's `/api/v2/search`. Sets are shaped like osu!'s API v2 and
+/// its `/s`, `/b` and `/md5` lookups are served by our v2 handlers.
+pub async fn search(
+    State(state): State<AppState>,
+    Query(params): Query<CatboySearchParams>,
+) -> Result<Json<Vec<BeatmapsetV2>>> {
+    let mut filter = SearchFilter::parse(&params.query)?;
+    if let Some(status) = params.status.as_deref() {
+        filter.add_status_list(status)?;
+    }
+    filter.mode = parse_mode(params.mode.as_deref().unwrap_or_default())?;
+    let sort = params.sort.as_deref().map(parse_sort).transpose()?;
+
+    let sets = search_sets(
+        &state,
+        &filter,
+        sort,
+        params.limit.clamp(1, 100),
+        params.offset,
+    )
+    .await?;
+    Ok(Json(sets.into_iter().map(map_set_v2).collect()))
+}
@@ -0,0 +1,206 @@
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    AppState,
+    crawler::{self, client::BeatmapLookup},
+    db::{
+        models::{Beatmap, Beatmapset, RankStatus},
+        queries,
+        search::SearchFilter,
+    },
+    error::Result,
+};
+
+use super::search::{parse_mode, search_sets};
+
+/// A set in the cheesegull shape that Chimu serves.
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ChimuSet {
+    set_id: i64,
+    children_beatmaps: Vec<ChimuMap>,
+    ranked_status: i32,
+    approved_date: Option<DateTime<Utc>>,
+    last_update: Option<DateTime<Utc>>,
+    last_checked: DateTime<Utc>,
+    artist: String,
+    title: String,
+    creator: String,
+    source: String,
+    tags: String,
+    has_video: bool,
+    genre: i32,
+    language: i32,
+    favourites: i32,
+    disabled: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ChimuMap {
+    beatmap_id: i64,
+    parent_set_id: i64,
+    diff_name: String,
+    #[serde(rename = "FileMD5")]
+    file_md5: String,
+    mode: i32,
+    #[serde(rename = "BPM")]
+    bpm: f64,
+    #[serde(rename = "AR")]
+    ar: f64,
+    #[serde(rename = "OD")]
+    od: f64,
+    #[serde(rename = "CS")]
+    cs: f64,
+    #[serde(rename = "HP")]
+    hp: f64,
+    total_length: i32,
+    hit_length: i32,
+    playcount: i32,
+    passcount: i32,
+    max_combo: i32,
+    difficulty_rating: f64,
+}
+
+fn map_chimu_map(map: &Beatmap) -> ChimuMap {
+    ChimuMap {
+        beatmap_id: map.id,
+        parent_set_id: map.beatmapset_id,
+        diff_name: map.version.clone(),
+        file_md5: map.checksum.clone().unwrap_or_default(),
+        mode: map.mode_int,
+        bpm: map.bpm.unwrap_or(0.0),
+        ar: map.ar.unwrap_or(0.0),
+        od: map.accuracy.unwrap_or(0.0),
+        cs: map.cs.unwrap_or(0.0),
+        hp: map.drain.unwrap_or(0.0),
+        total_length: map.total_length.unwrap_or(0),
+        hit_length: map.hit_length.unwrap_or(0),
+        playcount: 0,
+        passcount: 0,
+        max_combo: map.max_combo.unwrap_or(0),
+        difficulty_rating: map.difficulty_rating.unwrap_or(0.0),
+    }
+}
+
+fn map_chimu_set(set: Beatmapset) -> ChimuSet {
+    ChimuSet {
+        set_id: set.id,
+        children_beatmaps: set.beatmaps.iter().flatten().map(map_chimu_map).collect(),
+        ranked_status: RankStatus::parse(&set.status).map_or(0, |s| s.as_int()),
+        approved_date: set.ranked_date,
+        last_update: set.last_updated,
+        last_checked: set.updated_at,
+        artist: set.artist,
+        title: set.title,
+        creator: set.creator,
+        source: set.source.unwrap_or_default(),
+        tags: set.tags.unwrap_or_default(),
+        has_video: set.video,
+        genre: set.genre_id.unwrap_or(0),
+        language: set.language_id.unwrap_or(0),
+        favourites: set.favourite_count,
+        disabled: set.availability_download_disabled,
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ChimuSearchParams {
+    #[serde(default)]
+    query: String,
+    #[serde(default = "default_amount")]
+    amount: i64,
+    #[serde(default)]
+    offset: i64,
+    /// A status number or name, or a comma separated list of them.
+    #[serde(default)]
+    status: Option<String>,
+    #[serde(default)]
+    mode: Option<String>,
+}
+
+fn default_amount() -> i64 {
+    50
+}
+
+pub async fn get_set(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<Option<ChimuSet>>> {
+    let set = load_set(&state, id).await?;
+    Ok(Json(set.map(map_chimu_set)))
+}
+
+pub async fn get_map(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<Option<ChimuMap>>> {
+    let mut set_id = queries::get_beatmap_set_id(&state.db, id).await?;
+
+    if set_id.is_none() {
+        let lookup = BeatmapLookup {
+            id: Some(id),
+            ..Default::default()
+        };
+        match state.osu_client.lookup_beatmap(&lookup).await {
+            Ok(map) => set_id = map.map(|m| m.beatmapset_id),
+            Err(e) => tracing::warn!("Failed to look up beatmap {} from API: {}", id, e),
+        }
+    }
+
+    let Some(set_id) = set_id else {
+        return Ok(Json(None));
+    };
+    let map = load_set(&state, set_id)
+        .await?
+        .and_then(|set| set.beatmaps)
+        .and_then(|maps| maps.into_iter().find(|m| m.id == id));
+    Ok(Json(map.as_ref().map(map_chimu_map)))
+}
+
+pub async fn search(
+    State(state): State<AppState>,
+    Query(params): Query<ChimuSearchParams>,
+) -> Result<Json<Vec<ChimuSet>>> {
+    let mut filter = SearchFilter::parse(&params.query)?;
+    if let Some(status) = params.status.as_deref() {
+        filter.add_status_list(status)?;
+    }
+    filter.mode = parse_mode(params.mode.as_deref().unwrap_or_default())?;
+
+    let sets = search_sets(
+        &state,
+        &filter,
+        None,
+        params.amount.clamp(1, 100),
+        params.offset,
+    )
+    .await?;
+    Ok(Json(sets.into_iter().map(map_chimu_set).collect()))
+}
+
+/// A live set with its live beatmaps, fetched from osu! when we don't have it.
+async fn load_set(state: &AppState, id: i64) -> Result<Option<Beatmapset>> {
+    let mut set = queries::get_beatmapset(&state.db, id).await?;
+
+    if set.is_none() {
+        tracing::info!(
+            "Beatmapset {} not found locally → fetching from osu! API",
+            id
+        );
+        if let Err(e) = crawler::sync::resync_beatmapset(&state.db, &state.osu_client, id).await {
+            tracing::warn!("Failed to fetch beatmapset {} from API: {}", id, e);
+        }
+        set = queries::get_beatmapset(&state.db, id).await?;
+    }
+
+    Ok(set.filter(|s| !s.deleted).map(|mut s| {
+        s.retain_live_beatmaps();
+        s
+    }))
+}
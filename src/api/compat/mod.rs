@@ -0,0 +1,5 @@
+pub mod catboy;
+pub mod chimu;
+pub mod nerinyan;
+pub mod routes;
+pub mod search;
@@ -0,0 +1,126 @@
+use axum::{
+    Json,
+    extract::{Query, State},
+};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{
+    AppState,
+    api::v2::mapping::{BeatmapsetV2, map_set_v2},
+    db::search::SearchFilter,
+    error::Result,
+};
+
+use super::search::{parse_mode, parse_sort, search_sets};
+
+/// Same cap as our own search, whatever `ps` asks for.
+const MAX_PAGE_SIZE: i64 = 100;
+
+/// Nerinyan's search, from the query string of `GET /search` or the JSON body
+/// of `POST /search`. `maxCombo` and `option` are accepted and ignored.
+#[derive(Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub struct NerinyanSearch {
+    #[serde(alias = "q")]
+    query: String,
+    /// `all`, a status name or number, or a comma separated list of them.
+    #[serde(alias = "s")]
+    ranked: Option<String>,
+    /// Mode number as a string or number; empty for all.
+    m: Option<Value>,
+    /// `false` hides NSFW sets.
+    nsfw: Option<bool>,
+    /// `video`, `storyboard` or both joined with `.`.
+    #[serde(alias = "e")]
+    extra: Option<String>,
+    sort: Option<String>,
+    #[serde(alias = "p")]
+    page: i64,
+    ps: Option<i64>,
+    total_length: Option<Range>,
+    difficulty_rating: Option<Range>,
+    accuracy: Option<Range>,
+    ar: Option<Range>,
+    cs: Option<Range>,
+    drain: Option<Range>,
+    bpm: Option<Range>,
+}
+
+/// A bound of 0 means unbounded, as in Nerinyan's web UI.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct Range {
+    min: f64,
+    max: f64,
+}
+
+impl NerinyanSearch {
+    fn to_filter(&self) -> Result<SearchFilter> {
+        // Ranges become query-language filters, e.g. `stars>=5 stars<=6`.
+        let mut q = self.query.clone();
+        let ranges = [
+            ("length", &self.total_length),
+            ("stars", &self.difficulty_rating),
+            ("od", &self.accuracy),
+            ("ar", &self.ar),
+            ("cs", &self.cs),
+            ("hp", &self.drain),
+            ("bpm", &self.bpm),
+        ];
+        for (key, range) in ranges {
+            let Some(range) = range else { continue };
+            if range.min > 0.0 {
+                q.push_str(&format!(" {}>={}", key, range.min));
+            }
+            if range.max > 0.0 {
+                q.push_str(&format!(" {}<={}", key, range.max));
+            }
+        }
+
+        let mut filter = SearchFilter::parse(&q)?;
+        if let Some(ranked) = self.ranked.as_deref() {
+            filter.add_status_list(ranked)?;
+        }
+        filter.mode = match &self.m {
+            Some(Value::String(m)) => parse_mode(m)?,
+            Some(Value::Number(m)) => parse_mode(&m.to_string())?,
+            _ => None,
+        };
+        filter.exclude_nsfw = self.nsfw == Some(false);
+        if let Some(extra) = self.extra.as_deref() {
+            filter.set_extra(extra)?;
+        }
+        Ok(filter)
+    }
+
+    async fn run(self, state: &AppState) -> Result<Vec<BeatmapsetV2>> {
+        let filter = self.to_filter()?;
+        let sort = self.sort.as_deref().map(parse_sort).transpose()?;
+        let page_size = self.ps.unwrap_or(50).clamp(1, MAX_PAGE_SIZE);
+
+        let sets = search_sets(
+            state,
+            &filter,
+            sort,
+            page_size,
+            self.page.max(0).saturating_mul(page_size),
+        )
+        .await?;
+        Ok(sets.into_iter().map(map_set_v2).collect())
+    }
+}
+
+pub async fn search_get(
+    State(state): State<AppState>,
+    Query(params): Query<NerinyanSearch>,
+) -> Result<Json<Vec<BeatmapsetV2>>> {
+    params.run(&state).await.map(Json)
+}
+
+pub async fn search_post(
+    State(state): State<AppState>,
+    Json(body): Json<NerinyanSearch>,
+) -> Result<Json<Vec<BeatmapsetV2>>> {
+    body.run(&state).await.map(Json)
+}
@@ -0,0 +1,40 @@
+use axum::{
+    Router,
+    routing::{get, post},
+};
+
+use crate::{AppState, api::v2, config::CompatConfig};
+
+use super::{catboy, chimu, nerinyan};
+
+/// The compat route groups enabled in `config`.
+pub fn router(config: &CompatConfig) -> Router<AppState> {
+    let mut router = Router::new();
+
+    if config.nerinyan {
+        router = router.route(
+            "/search",
+            get(nerinyan::search_get).merge(post(nerinyan::search_post)),
+        );
+    }
+
+    if config.catboy {
+        router = router
+            .route("/api/v2/s/{id}", get(v2::beatmapset::get_beatmapset_v2))
+            .route("/api/v2/b/{id}", get(v2::beatmaps::get_beatmap_v2))
+            .route(
+                "/api/v2/md5/{md5}",
+                get(v2::beatmaps::get_beatmap_by_md5_v2),
+            )
+            .route("/api/v2/search", get(catboy::search));
+    }
+
+    if config.chimu {
+        router = router
+            .route("/api/v1/set/{id}", get(chimu::get_set))
+            .route("/api/v1/map/{id}", get(chimu::get_map))
+            .route("/api/v1/search", get(chimu::search));
+    }
+
+    router
+}
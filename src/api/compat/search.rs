@@ -0,0 +1,61 @@
+use crate::{
+    AppState,
+    db::{
+        models::Beatmapset,
+        queries,
+        search::{SearchFilter, SearchSort},
+    },
+    error::{AppError, Result},
+};
+
+/// `{field}_{asc|desc}` in our spelling or the column-style names other
+/// mirrors use (`play_count_desc`, `last_updated_desc`, ...).
+pub fn parse_sort(value: &str) -> Result<SearchSort> {
+    let (name, dir) = match value.rsplit_once('_') {
+        Some((name, dir @ ("asc" | "desc"))) => (name, dir),
+        _ => (value, "desc"),
+    };
+    let name = match name {
+        "play_count" | "playcount" => "plays",
+        "favourite_count" | "favorites" | "favourites_count" => "favourites",
+        "last_updated" | "last_update" => "updated",
+        "ranked_date" | "approved_date" => "ranked",
+        "difficulty_rating" | "difficultyrating" => "difficulty",
+        other => other,
+    };
+    SearchSort::parse(&format!("{}_{}", name, dir))
+        .map_err(|_| AppError::BadRequest(format!("unknown sort '{}'", value)))
+}
+
+/// `0`-`3`, a ruleset name, or empty/`-1` for all modes.
+pub fn parse_mode(value: &str) -> Result<Option<i32>> {
+    match value {
+        "" | "-1" | "all" => Ok(None),
+        "0" | "osu" => Ok(Some(0)),
+        "1" | "taiko" => Ok(Some(1)),
+        "2" | "fruits" | "catch" => Ok(Some(2)),
+        "3" | "mania" => Ok(Some(3)),
+        other => Err(AppError::BadRequest(format!("unknown mode '{}'", other))),
+    }
+}
+
+/// One page of live sets with their live beatmaps.
+pub async fn search_sets(
+    state: &AppState,
+    filter: &SearchFilter,
+    sort: Option<SearchSort>,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<Beatmapset>> {
+    let sort = SearchSort::resolve(sort, filter);
+    let page =
+        queries::search_beatmapsets(&state.db, filter, &sort, None, limit, offset.max(0)).await?;
+    Ok(page
+        .sets
+        .into_iter()
+        .map(|mut set| {
+            set.retain_live_beatmaps();
+            set
+        })
+        .collect())
+}
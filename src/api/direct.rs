@@ -0,0 +1,236 @@
+use axum::{
+    extract::{Query, State},
+    http::header,
+    response::IntoResponse,
+};
+use serde::Deserialize;
+
+use crate::{
+    AppState,
+    crawler::{self, client::BeatmapLookup},
+    db::{
+        models::{Beatmapset, RankStatus},
+        queries,
+        search::{SearchFilter, SearchSort},
+    },
+    error::{AppError, Result},
+};
+
+/// osu!direct always asks for pages of 100.
+const PAGE_SIZE: i64 = 100;
+
+#[derive(Deserialize)]
+pub struct DirectSearchParams {
+    #[serde(default)]
+    q: String,
+    /// osu!direct's status dropdown, see [`status_category`].
+    #[serde(default = "default_status")]
+    r: i32,
+    /// `-1` for all modes.
+    #[serde(default = "default_mode")]
+    m: i32,
+    #[serde(default)]
+    p: i64,
+}
+
+fn default_status() -> i32 {
+    4
+}
+
+fn default_mode() -> i32 {
+    -1
+}
+
+#[derive(Deserialize)]
+pub struct DirectSetParams {
+    #[serde(default)]
+    s: Option<i64>,
+    #[serde(default)]
+    b: Option<i64>,
+    #[serde(default)]
+    c: Option<String>,
+}
+
+/// Maps osu!direct's `r` codes to the website's status categories.
+fn status_category(r: i32) -> Option<&'static str> {
+    match r {
+        0 | 7 => Some("ranked"),
+        2 => Some("pending"),
+        3 => Some("qualified"),
+        5 => Some("graveyard"),
+        8 => Some("loved"),
+        _ => None,
+    }
+}
+
+/// osu!direct's `/web/osu-search.php`, for bancho-style private servers: a count
+/// line, then one line per set with its difficulties. A full page reports 101
+/// so the client offers the next one.
+pub async fn osu_search(
+    State(state): State<AppState>,
+    Query(params): Query<DirectSearchParams>,
+) -> Result<impl IntoResponse> {
+    // The client sends these in place of keywords for its sort buttons.
+    let (query, sort) = match params.q.as_str() {
+        "Newest" => ("", Some(SearchSort::RANKED_DESC)),
+        "Top Rated" => ("", Some(SearchSort::parse("rating_desc")?)),
+        "Most Played" => ("", Some(SearchSort::parse("plays_desc")?)),
+        q => (q, None),
+    };
+
+    let mut filter = SearchFilter::parse(query)?;
+    if let Some(category) = status_category(params.r) {
+        filter.add_status_category(category)?;
+    }
+    match params.m {
+        -1 => {}
+        m @ 0..=3 => filter.mode = Some(m),
+        m => return Err(AppError::BadRequest(format!("unknown mode {}", m))),
+    }
+    let sort = SearchSort::resolve(sort, &filter);
+
+    let page = queries::search_beatmapsets(
+        &state.db,
+        &filter,
+        &sort,
+        None,
+        PAGE_SIZE,
+        params.p.max(0).saturating_mul(PAGE_SIZE),
+    )
+    .await?;
+
+    let count = page.sets.len() as i64;
+    let mut lines = vec![if count == PAGE_SIZE {
+        (PAGE_SIZE + 1).to_string()
+    } else {
+        count.to_string()
+    }];
+    for mut set in page.sets {
+        set.retain_live_beatmaps();
+        lines.push(format!("{}|{}", set_line(&set), diffs_field(&set)));
+    }
+
+    Ok(text(lines.join("\n")))
+}
+
+/// `/web/osu-search-set.php`: the set line of one set looked up by set id,
+/// beatmap id or .osu checksum. Empty when nothing matches.
+pub async fn osu_search_set(
+    State(state): State<AppState>,
+    Query(params): Query<DirectSetParams>,
+) -> Result<impl IntoResponse> {
+    let Some(set_id) = resolve_set_id(&state, &params).await? else {
+        return Ok(text(String::new()));
+    };
+
+    let mut set = queries::get_beatmapset(&state.db, set_id).await?;
+    if set.is_none() {
+        tracing::info!(
+            "Beatmapset {} not found locally → fetching from osu! API",
+            set_id
+        );
+        if let Err(e) = crawler::sync::resync_beatmapset(&state.db, &state.osu_client, set_id).await
+        {
+            tracing::warn!("Failed to fetch beatmapset {} from API: {}", set_id, e);
+        }
+        set = queries::get_beatmapset(&state.db, set_id).await?;
+    }
+
+    Ok(text(
+        set.filter(|s| !s.deleted)
+            .map(|s| set_line(&s))
+            .unwrap_or_default(),
+    ))
+}
+
+async fn resolve_set_id(state: &AppState, params: &DirectSetParams) -> Result<Option<i64>> {
+    if let Some(id) = params.s {
+        return Ok(Some(id));
+    }
+
+    let local = if let Some(id) = params.b {
+        queries::get_beatmap_set_id(&state.db, id).await?
+    } else if let Some(checksum) = params.c.as_deref() {
+        queries::get_beatmap_ids_by_checksum(&state.db, checksum)
+            .await?
+            .map(|(_, set_id)| set_id)
+    } else {
+        return Err(AppError::BadRequest(
+            "one of s, b or c is required".to_string(),
+        ));
+    };
+    if local.is_some() {
+        return Ok(local);
+    }
+
+    let lookup = BeatmapLookup {
+        checksum: params.c.as_deref(),
+        id: params.b,
+        ..Default::default()
+    };
+    match state.osu_client.lookup_beatmap(&lookup).await {
+        Ok(map) => Ok(map.map(|m| m.beatmapset_id)),
+        Err(e) => {
+            tracing::warn!("Failed to look up beatmap {:?} from API: {}", lookup, e);
+            Ok(None)
+        }
+    }
+}
+
+/// `filename|artist|title|creator|status|rating|last update|set id|thread id|
+/// video|storyboard|size|size without video`.
+fn set_line(set: &Beatmapset) -> String {
+    let status = RankStatus::parse(&set.status).map_or(0, |s| s.as_int());
+    let last_update = set
+        .last_updated
+        .map(|d| d.format("%Y-%m-%dT%H:%M:%SZ").to_string())
+        .unwrap_or_default();
+    format!(
+        "{}.osz|{}|{}|{}|{}|{:.1}|{}|{}|0|{}|{}|0|0",
+        set.id,
+        field(&set.artist),
+        field(&set.title),
+        field(&set.creator),
+        status,
+        set.rating.unwrap_or(0.0),
+        last_update,
+        set.id,
+        set.video as u8,
+        set.storyboard as u8,
+    )
+}
+
+/// Comma separated `[4.20⭐] Insane {cs: 4 / od: 8 / ar: 9 / hp: 6}@0`,
+/// easiest first.
+fn diffs_field(set: &Beatmapset) -> String {
+    let mut maps: Vec<_> = set.beatmaps.iter().flatten().collect();
+    maps.sort_by(|a, b| {
+        a.difficulty_rating
+            .unwrap_or(0.0)
+            .total_cmp(&b.difficulty_rating.unwrap_or(0.0))
+    });
+    maps.iter()
+        .map(|b| {
+            format!(
+                "[{:.2}⭐] {} {{cs: {} / od: {} / ar: {} / hp: {}}}@{}",
+                b.difficulty_rating.unwrap_or(0.0),
+                field(&b.version).replace(',', " "),
+                b.cs.unwrap_or(0.0),
+                b.accuracy.unwrap_or(0.0),
+                b.ar.unwrap_or(0.0),
+                b.drain.unwrap_or(0.0),
+                b.mode_int,
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Keeps user text from breaking the line format.
+fn field(value: &str) -> String {
+    value.replace(['|', '\n', '\r'], " ")
+}
+
+fn text(body: String) -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, "text/plain; charset=utf-8")], body)
+}
@@ -1,230 +1,1219 @@
 use axum::{Json, response::IntoResponse};
-use serde_json::json;
+use serde_json::{Map, Value, json};
 
 pub async fn openapi_json() -> impl IntoResponse {
     const VERSION: &str = env!("CARGO_PKG_VERSION");
 
-    Json(json!({
-        "openapi": "3.1.1",
+    let save_mappool = json!({
+        "type": "object",
+        "required": ["tournament", "round", "name", "slots"],
+        "properties": {
+            "tournament": { "type": "string" },
+            "round": { "type": "string" },
+            "name": { "type": "string" },
+            "slots": {
+                "type": "array",
+                "maxItems": 50,
+                "items": {
+                    "type": "object",
+                    "required": ["slot", "beatmap_id"],
+                    "properties": {
+                        "slot": { "type": "string", "description": "e.g. NM1, HD2, DT1, TB" },
+                        "beatmap_id": { "type": "integer" },
+                        "mods": { "type": "string", "description": "e.g. HDHR; overrides the slot name" }
+                    }
+                }
+            }
+        }
+    });
 
-        "info": {
-            "title": "osu-mirror-rs Documentation",
-            "version": VERSION,
-            "description": "This is the API documentation for the osu-mirror-rs an osu! beatmap mirror.",
+    let system = json!({
+        "/health": {
+            "get": {
+                "tags": ["System"],
+                "summary": "Health check",
+                "responses": {
+                    "200": { "description": "Service is alive" }
+                }
+            }
         },
-
-        "tags": [
-            {
-                "name": "System",
-                "description": "Server health and system endpoints"
-            },
-            {
-                "name": "osu!v1 api",
-                "description": "osu!api v1 compatible endpoints"
-            },
-            {
-                "name": "osu!v2 api",
-                "description": "osu!api v2 compatible endpoints"
-            },
-        ],
-
-        "paths": {
-            "/health": {
-                "get": {
-                    "tags": ["System"],
-                    "summary": "Health check",
-                    "responses": {
-                        "200": { "description": "Service is alive" }
+        "/status": {
+            "get": {
+                "tags": ["System"],
+                "summary": "Server status",
+                "responses": {
+                    "200": {
+                        "description": "Status information",
+                        "content": {
+                            "application/json": {
+                                "schema": { "type": "object" }
+                            }
+                        }
                     }
                 }
-            },
-            "/status": {
-                "get": {
-                    "tags": ["System"],
-                    "summary": "Server status",
-                    "responses": {
-                        "200": {
-                            "description": "Status information",
-                            "content": {
-                                "application/json": {
-                                    "schema": { "type": "object" }
+            }
+        }
+    });
+
+    let v1 = json!({
+        "/v1/search": {
+            "get": {
+                "tags": ["osu!v1 api"],
+                "summary": "Search beatmapsets",
+                "parameters": [
+                    {
+                        "name": "q",
+                        "in": "query",
+                        "required": false,
+                        "schema": { "type": "string" },
+                        "description": "Search query (artist, title, creator, md5, etc)"
+                    },
+                    {
+                        "name": "status",
+                        "in": "query",
+                        "required": false,
+                        "schema": { "type": "string" },
+                        "description": "Single status: a name, its one-letter alias or -2 to 4. Unknown values are a 400."
+                    }
+                ],
+                "responses": {
+                    "200": {
+                        "description": "Search results",
+                        "content": {
+                            "application/json": {
+                                "schema": {
+                                    "type": "array",
+                                    "items": { "type": "object" }
                                 }
                             }
                         }
                     }
                 }
-            },
+            }
+        },
 
-            "/v1/search": {
-                "get": {
-                    "tags": ["osu!v1 api"],
-                    "summary": "Search beatmapsets",
-                    "parameters": [
-                        {
-                            "name": "q",
-                            "in": "query",
-                            "required": false,
-                            "schema": { "type": "string" },
-                            "description": "Search query (artist, title, creator, md5, etc)"
-                        }
-                    ],
-                    "responses": {
-                        "200": {
-                            "description": "Search results",
-                            "content": {
-                                "application/json": {
-                                    "schema": {
-                                        "type": "array",
-                                        "items": { "type": "object" }
-                                    }
-                                }
+
+        "/api/get_beatmaps": {
+            "get": {
+                "tags": ["osu!v1 api"],
+                "summary": "osu! API v1 get_beatmaps",
+                "description": "Drop-in for osu!'s /api/get_beatmaps. Results are ordered by ranked date, oldest first. Converts (m with a=1) report the requested mode but keep the osu! difficulty values.",
+                "parameters": [
+                    { "name": "k", "in": "query", "required": false, "schema": { "type": "string" }, "description": "API key; only checked when legacy_api.keys is configured" },
+                    { "name": "s", "in": "query", "required": false, "schema": { "type": "integer" }, "description": "Beatmapset id" },
+                    { "name": "b", "in": "query", "required": false, "schema": { "type": "integer" }, "description": "Beatmap id" },
+                    { "name": "u", "in": "query", "required": false, "schema": { "type": "string" }, "description": "Mapper user id or username" },
+                    { "name": "type", "in": "query", "required": false, "schema": { "type": "string", "enum": ["id", "string"] }, "description": "How to read u; guessed when omitted" },
+                    { "name": "m", "in": "query", "required": false, "schema": { "type": "integer", "enum": [0, 1, 2, 3] } },
+                    { "name": "a", "in": "query", "required": false, "schema": { "type": "integer", "enum": [0, 1] }, "description": "Include converts for m" },
+                    { "name": "h", "in": "query", "required": false, "schema": { "type": "string" }, "description": ".osu file md5" },
+                    { "name": "since", "in": "query", "required": false, "schema": { "type": "string" }, "description": "Only sets ranked after this UTC date (YYYY-MM-DD or YYYY-MM-DD HH:MM:SS)" },
+                    { "name": "limit", "in": "query", "required": false, "schema": { "type": "integer", "default": 500, "maximum": 500 } }
+                ],
+                "responses": {
+                    "200": {
+                        "description": "Beatmaps in the v1 format, every field a string",
+                        "content": {
+                            "application/json": {
+                                "schema": { "type": "array", "items": { "type": "object" } }
                             }
                         }
-                    }
+                    },
+                    "400": { "description": "Invalid parameter" },
+                    "401": { "description": "k missing or not configured" }
                 }
-            },
+            }
+        },
 
+        "/v1/beatmapsets/{id}": {
+            "get": {
+                "tags": ["osu!v1 api"],
+                "summary": "Get beatmapset",
+                "parameters": [
+                    {
+                        "name": "id",
+                        "in": "path",
+                        "required": true,
+                        "schema": { "type": "integer" }
+                    }
+                ],
+                "responses": {
+                    "200": {
+                        "description": "Beatmapset found",
+                        "content": {
+                            "application/json": {
+                                "schema": { "type": "array", "items": { "type": "object" } }
+                            }
+                        }
+                    },
+                    "404": { "description": "Not found" }
+                }
+            }
+        },
 
-            "/v1/beatmapsets/{id}": {
-                "get": {
-                    "tags": ["osu!v1 api"],
-                    "summary": "Get beatmapset",
-                    "parameters": [
-                        {
-                            "name": "id",
-                            "in": "path",
-                            "required": true,
-                            "schema": { "type": "integer" }
-                        }
-                    ],
-                    "responses": {
-                        "200": {
-                            "description": "Beatmapset found",
-                            "content": {
-                                "application/json": {
-                                    "schema": { "type": "array", "items": { "type": "object" } }
-                                }
+        "/v1/beatmaps/{id}": {
+            "get": {
+                "tags": ["osu!v1 api"],
+                "summary": "Get beatmap",
+                "parameters": [
+                    {
+                        "name": "id",
+                        "in": "path",
+                        "required": true,
+                        "schema": { "type": "integer" }
+                    }
+                ],
+                "responses": {
+                    "200": {
+                        "description": "Beatmap found",
+                        "content": {
+                            "application/json": {
+                                "schema": { "type": "array", "items": { "type": "object" } }
                             }
-                        },
-                        "404": { "description": "Not found" }
+                        }
+                    },
+                    "404": { "description": "Not found" }
+                }
+            }
+        },
+
+        "/v1/beatmaps/md5/{md5}": {
+            "get": {
+                "tags": ["osu!v1 api"],
+                "summary": "Get beatmap by MD5 hash",
+                "parameters": [
+                    {
+                        "name": "md5",
+                        "in": "path",
+                        "required": true,
+                        "schema": { "type": "string" }
                     }
+                ],
+                "responses": {
+                    "200": {
+                        "description": "Beatmap found",
+                        "content": {
+                            "application/json": { "schema": { "type": "array", "items": { "type": "object" } } }
+                        }
+                    },
+                    "404": { "description": "Not found" }
                 }
-            },
+            }
+        }
+    });
 
-            "/v1/beatmaps/{id}": {
-                "get": {
-                    "tags": ["osu!v1 api"],
-                    "summary": "Get beatmap",
-                    "parameters": [
-                        {
-                            "name": "id",
-                            "in": "path",
-                            "required": true,
-                            "schema": { "type": "integer" }
-                        }
-                    ],
-                    "responses": {
-                        "200": {
-                            "description": "Beatmap found",
-                            "content": {
-                                "application/json": {
-                                    "schema": { "type": "array", "items": { "type": "object" } }
-                                }
+    let v2_sets = json!({
+        "/v2/beatmapsets/{id}": {
+            "get": {
+                "tags": ["osu!v2 api"],
+                "summary": "Get beatmapset",
+                "parameters": [
+                    {
+                        "name": "id",
+                        "in": "path",
+                        "required": true,
+                        "schema": { "type": "integer" }
+                    }
+                ],
+                "responses": {
+                    "200": {
+                        "description": "Beatmapset found",
+                        "content": {
+                            "application/json": {
+                                "schema": { "type": "array", "items": { "type": "object" } }
                             }
-                        },
-                        "404": { "description": "Not found" }
+                        }
+                    },
+                    "404": { "description": "Not found" }
+                }
+            }
+        },
+
+        "/v2/beatmapsets/{id}/history": {
+            "get": {
+                "tags": ["osu!v2 api"],
+                "summary": "Get beatmapset change history",
+                "description": "Snapshots recorded whenever a tracked field changes (status, ranked date, checksums, difficulty values, deletion).",
+                "parameters": [
+                    {
+                        "name": "id",
+                        "in": "path",
+                        "required": true,
+                        "schema": { "type": "integer" }
+                    },
+                    {
+                        "name": "limit",
+                        "in": "query",
+                        "required": false,
+                        "schema": { "type": "integer" },
+                        "description": "Max entries (1-100, default 50)"
+                    },
+                    {
+                        "name": "before",
+                        "in": "query",
+                        "required": false,
+                        "schema": { "type": "integer" },
+                        "description": "Only return entries older than this history id"
+                    }
+                ],
+                "responses": {
+                    "200": {
+                        "description": "History entries, newest first",
+                        "content": {
+                            "application/json": {
+                                "schema": { "type": "array", "items": { "type": "object" } }
+                            }
+                        }
                     }
                 }
-            },
+            }
+        },
+
+        "/v2/users/{id}": {
+            "get": {
+                "tags": ["osu!v2 api"],
+                "summary": "Mapper profile",
+                "description": "Username, previous usernames, country, avatar and beatmapset counts. Fetched from osu! when the mirror doesn't know the user yet.",
+                "parameters": [
+                    { "name": "id", "in": "path", "required": true, "schema": { "type": "integer" } }
+                ],
+                "responses": {
+                    "200": { "description": "User, or null when osu! doesn't know it either" }
+                }
+            }
+        },
+        "/v2/users/{id}/beatmapsets": {
+            "get": {
+                "tags": ["osu!v2 api"],
+                "summary": "Beatmapsets mapped by a user",
+                "parameters": [
+                    { "name": "id", "in": "path", "required": true, "schema": { "type": "integer" } },
+                    { "name": "status", "in": "query", "required": false, "schema": { "type": "string" }, "description": "graveyard, wip, pending, ranked, approved, qualified or loved; also the one-letter aliases and -2 to 4. Unknown values are a 400." },
+                    { "name": "limit", "in": "query", "required": false, "schema": { "type": "integer", "default": 50, "maximum": 100 } },
+                    { "name": "offset", "in": "query", "required": false, "schema": { "type": "integer", "default": 0 } },
+                    { "name": "include_deleted", "in": "query", "required": false, "schema": { "type": "boolean", "default": false } }
+                ],
+                "responses": {
+                    "200": { "description": "Beatmapsets, newest first" }
+                }
+            }
+        },
+        "/v2/search": {
+            "get": {
+                "tags": ["osu!v2 api"],
+                "summary": "Search beatmapsets",
+                "description": "q takes the osu! website query language: keywords plus ar, cs, od, hp, stars, bpm, length, keys (comparisons with = : < <= > >=, ranges like stars=5-6), status=r|a|q|l|p|w|g, artist, title, creator, source, tag, difficulty (quote for an exact match), and created, updated, ranked dates (2020, 2020-05 or 2020-05-17). Difficulty filters must all hold for the same difficulty. Keywords are prefix-matched against title, artist (romanised and unicode), source, creator and tags, with typo tolerance on title and artist; relevance ranks title and artist matches highest.",
+                "parameters": [
+                    { "name": "q", "in": "query", "required": false, "schema": { "type": "string" }, "description": "e.g. ar>9 stars=5-6 status=r camellia" },
+                    { "name": "m", "in": "query", "required": false, "schema": { "type": "integer", "enum": [0, 1, 2, 3] }, "description": "Mode" },
+                    { "name": "s", "in": "query", "required": false, "schema": { "type": "string", "enum": ["any", "leaderboard", "ranked", "qualified", "loved", "pending", "wip", "graveyard"] }, "description": "Status category" },
+                    { "name": "status", "in": "query", "required": false, "schema": { "type": "string" }, "description": "Single status: a name, its one-letter alias (r, a, q, l, p, w, g) or -2 to 4. ranked includes approved; unknown values are a 400." },
+                    { "name": "g", "in": "query", "required": false, "schema": { "type": "integer" }, "description": "Genre id" },
+                    { "name": "l", "in": "query", "required": false, "schema": { "type": "integer" }, "description": "Language id" },
+                    { "name": "nsfw", "in": "query", "required": false, "schema": { "type": "boolean" }, "description": "false hides explicit sets" },
+                    { "name": "e", "in": "query", "required": false, "schema": { "type": "string" }, "description": "Required extras: video, storyboard or video.storyboard" },
+                    { "name": "mods", "in": "query", "required": false, "schema": { "type": "string", "enum": ["NM", "HR", "DT", "HRDT", "EZ", "HT"] }, "description": "stars filters on the star rating under these mods (HD and NC are accepted and don't matter). Only cached ranked and loved difficulties have modded ratings." },
+                    { "name": "sort", "in": "query", "required": false, "schema": { "type": "string" }, "description": "title, artist, difficulty, ranked, rating, plays, favourites, updated or relevance, suffixed _asc or _desc. Defaults to relevance_desc with keywords and ranked_desc without." },
+                    { "name": "cursor_string", "in": "query", "required": false, "schema": { "type": "string" }, "description": "cursor_string of the previous page; takes precedence over offset" },
+                    { "name": "limit", "in": "query", "required": false, "schema": { "type": "integer", "default": 50, "maximum": 100 } },
+                    { "name": "offset", "in": "query", "required": false, "schema": { "type": "integer", "default": 0 } },
+                    { "name": "include_deleted", "in": "query", "required": false, "schema": { "type": "boolean", "default": false } }
+                ],
+                "responses": {
+                    "200": { "description": "Search results in the shape of osu!'s /beatmapsets/search; cursor_string is null on the last page" },
+                    "400": { "description": "Invalid filter, sort, mods or cursor_string" }
+                }
+            }
+        }
+    });
+
+    let v2_beatmaps = json!({
+        "/v2/beatmaps": {
+            "get": {
+                "tags": ["osu!v2 api"],
+                "summary": "Beatmaps by id",
+                "description": "Batch lookup in the shape of osu!'s endpoint. Unknown ids are fetched from osu!; ids nobody knows are left out.",
+                "parameters": [
+                    { "name": "ids[]", "in": "query", "required": true, "schema": { "type": "array", "items": { "type": "integer" }, "maxItems": 50 }, "style": "form", "explode": true },
+                    { "name": "include_deleted", "in": "query", "required": false, "schema": { "type": "boolean", "default": false } }
+                ],
+                "responses": {
+                    "200": { "description": "{ beatmaps: [...] }, each with its beatmapset embedded" },
+                    "400": { "description": "More than 50 ids, or an id that isn't an integer" }
+                }
+            }
+        },
+        "/v2/beatmaps/lookup": {
+            "get": {
+                "tags": ["osu!v2 api"],
+                "summary": "Look up a beatmap",
+                "description": "By the first of checksum, filename and id that is given. Fetched from osu! on a local miss.",
+                "parameters": [
+                    { "name": "checksum", "in": "query", "required": false, "schema": { "type": "string" }, "description": "MD5 of the .osu file" },
+                    { "name": "filename", "in": "query", "required": false, "schema": { "type": "string" }, "description": "Artist - Title (Creator) [Version].osu" },
+                    { "name": "id", "in": "query", "required": false, "schema": { "type": "integer" } },
+                    { "name": "include_deleted", "in": "query", "required": false, "schema": { "type": "boolean", "default": false } }
+                ],
+                "responses": {
+                    "200": { "description": "Beatmap with its beatmapset, or null" },
+                    "400": { "description": "None of checksum, filename or id given" }
+                }
+            }
+        },
+        "/v2/beatmaps/random": {
+            "get": {
+                "tags": ["osu!v2 api"],
+                "summary": "Random beatmaps",
+                "description": "Random difficulties matching the /v2/search filters, e.g. q=stars=5-6 length<180&s=ranked&m=0. Difficulty filters apply to the picked difficulty itself.",
+                "parameters": [
+                    { "name": "count", "in": "query", "required": false, "schema": { "type": "integer", "default": 1, "maximum": 50 } },
+                    { "name": "m", "in": "query", "required": false, "schema": { "type": "integer", "enum": [0, 1, 2, 3] }, "description": "Mode" },
+                    { "name": "q", "in": "query", "required": false, "schema": { "type": "string" }, "description": "Search filters, e.g. stars=5-6 length<180" },
+                    { "name": "s", "in": "query", "required": false, "schema": { "type": "string" }, "description": "Status category, as on /v2/search" },
+                    { "name": "status", "in": "query", "required": false, "schema": { "type": "string" }, "description": "Single status, as on /v2/search" },
+                    { "name": "g", "in": "query", "required": false, "schema": { "type": "integer" }, "description": "Genre id" },
+                    { "name": "l", "in": "query", "required": false, "schema": { "type": "integer" }, "description": "Language id" },
+                    { "name": "nsfw", "in": "query", "required": false, "schema": { "type": "boolean" }, "description": "false hides explicit sets" },
+                    { "name": "e", "in": "query", "required": false, "schema": { "type": "string" }, "description": "Required extras: video, storyboard or video.storyboard" },
+                    { "name": "mods", "in": "query", "required": false, "schema": { "type": "string", "enum": ["NM", "HR", "DT", "HRDT", "EZ", "HT"] }, "description": "stars filters on the star rating under these mods" },
+                    { "name": "include_deleted", "in": "query", "required": false, "schema": { "type": "boolean", "default": false } }
+                ],
+                "responses": {
+                    "200": { "description": "{ beatmaps: [...] } with each beatmapset embedded; fewer than count when not enough match" },
+                    "400": { "description": "Invalid filter or mods" }
+                }
+            }
+        },
+        "/v2/beatmaps/{id}/similar": {
+            "get": {
+                "tags": ["osu!v2 api"],
+                "summary": "Similar beatmaps",
+                "description": "Difficulties from other sets in the same mode (and key count in osu!mania) within 0.5 stars, closest in star rating, BPM, length, AR and CS first. Takes the /v2/search filters to narrow them down.",
+                "parameters": [
+                    { "name": "id", "in": "path", "required": true, "schema": { "type": "integer" } },
+                    { "name": "count", "in": "query", "required": false, "schema": { "type": "integer", "default": 10, "maximum": 50 } },
+                    { "name": "q", "in": "query", "required": false, "schema": { "type": "string" }, "description": "Search filters, e.g. stars=5-6 length<180" },
+                    { "name": "s", "in": "query", "required": false, "schema": { "type": "string" }, "description": "Status category, as on /v2/search" },
+                    { "name": "status", "in": "query", "required": false, "schema": { "type": "string" }, "description": "Single status, as on /v2/search" },
+                    { "name": "g", "in": "query", "required": false, "schema": { "type": "integer" }, "description": "Genre id" },
+                    { "name": "l", "in": "query", "required": false, "schema": { "type": "integer" }, "description": "Language id" },
+                    { "name": "nsfw", "in": "query", "required": false, "schema": { "type": "boolean" }, "description": "false hides explicit sets" },
+                    { "name": "e", "in": "query", "required": false, "schema": { "type": "string" }, "description": "Required extras: video, storyboard or video.storyboard" },
+                    { "name": "mods", "in": "query", "required": false, "schema": { "type": "string", "enum": ["NM", "HR", "DT", "HRDT", "EZ", "HT"] }, "description": "stars filters on the star rating under these mods" },
+                    { "name": "include_deleted", "in": "query", "required": false, "schema": { "type": "boolean", "default": false } }
+                ],
+                "responses": {
+                    "200": { "description": "{ beatmaps: [...] } with each beatmapset embedded" },
+                    "400": { "description": "Invalid filter or mods" },
+                    "404": { "description": "Unknown beatmap" }
+                }
+            }
+        },
+        "/v2/beatmaps/{id}": {
+            "get": {
+                "tags": ["osu!v2 api"],
+                "summary": "Beatmap by id",
+                "parameters": [
+                    { "name": "id", "in": "path", "required": true, "schema": { "type": "integer" } },
+                    { "name": "include_deleted", "in": "query", "required": false, "schema": { "type": "boolean", "default": false } }
+                ],
+                "responses": {
+                    "200": { "description": "Beatmap with its beatmapset, or null" }
+                }
+            }
+        },
+        "/v2/beatmaps/{id}/attributes": {
+            "get": {
+                "tags": ["osu!v2 api"],
+                "summary": "Mod-adjusted difficulty attributes",
+                "description": "Star rating, aim, speed and max combo calculated from the .osu file in the cached archive (or osu! when the set isn't cached), plus CS/AR/OD/HP, BPM and length after the mods. Cached per beatmap and mods.",
+                "parameters": [
+                    { "name": "id", "in": "path", "required": true, "schema": { "type": "integer" } },
+                    { "name": "mods", "in": "query", "required": false, "schema": { "type": "string" }, "description": "Acronyms, e.g. HDDT. Supports NF, EZ, HD, HR, DT, NC, HT and FL." }
+                ],
+                "responses": {
+                    "200": { "description": "Difficulty attributes; aim, speed and flashlight are null outside osu! standard" },
+                    "400": { "description": "Invalid mods" },
+                    "404": { "description": "Unknown beatmap, or no .osu file available" }
+                }
+            }
+        },
+        "/v2/beatmaps/{id}/pp": {
+            "get": {
+                "tags": ["osu!v2 api"],
+                "summary": "Performance points for a score",
+                "parameters": [
+                    { "name": "id", "in": "path", "required": true, "schema": { "type": "integer" } },
+                    { "name": "mods", "in": "query", "required": false, "schema": { "type": "string" }, "description": "Acronyms, e.g. HDDT" },
+                    { "name": "acc", "in": "query", "required": false, "schema": { "type": "number", "minimum": 0, "maximum": 100 }, "description": "Accuracy in percent; 100 when omitted" },
+                    { "name": "combo", "in": "query", "required": false, "schema": { "type": "integer" }, "description": "Full combo when omitted" },
+                    { "name": "misses", "in": "query", "required": false, "schema": { "type": "integer" }, "description": "0 when omitted" }
+                ],
+                "responses": {
+                    "200": { "description": "pp with its aim, speed, accuracy and flashlight parts in osu! standard" },
+                    "400": { "description": "Invalid mods or acc" },
+                    "404": { "description": "Unknown beatmap, or no .osu file available" }
+                }
+            }
+        },
+        "/v2/beatmaps/{id}/details": {
+            "get": {
+                "tags": ["osu!v2 api"],
+                "summary": "Details parsed from the .osu file",
+                "description": "Audio filename, audio lead-in, preview time, mania key count, BPM range and main BPM, slider multiplier and tick rate, object counts, ticks of each slider, drain time and the segments it is made of, break periods and an object density graph of 100 slices. Times are in milliseconds. Parsed from the cached archive (or osu! when the set isn't cached) and stored.",
+                "parameters": [
+                    { "name": "id", "in": "path", "required": true, "schema": { "type": "integer" } }
+                ],
+                "responses": {
+                    "200": { "description": "Beatmap details; keys is null outside osu!mania" },
+                    "404": { "description": "Unknown beatmap, or no .osu file available" }
+                }
+            }
+        },
+        "/v2/beatmaps/md5/{md5}": {
+            "get": {
+                "tags": ["osu!v2 api"],
+                "summary": "Beatmap by .osu checksum",
+                "parameters": [
+                    { "name": "md5", "in": "path", "required": true, "schema": { "type": "string" } },
+                    { "name": "include_deleted", "in": "query", "required": false, "schema": { "type": "boolean", "default": false } }
+                ],
+                "responses": {
+                    "200": { "description": "Beatmap with its beatmapset, or null" }
+                }
+            }
+        },
+        "/v2/beatmaps/packs": {
+            "get": {
+                "tags": ["osu!v2 api"],
+                "summary": "Beatmap packs",
+                "description": "Packs known to the mirror, newest first.",
+                "parameters": [
+                    { "name": "type", "in": "query", "required": false, "schema": { "type": "string", "enum": ["standard", "featured", "tournament", "loved", "chart", "theme", "artist"] }, "description": "All types when omitted" },
+                    { "name": "cursor_string", "in": "query", "required": false, "schema": { "type": "string" }, "description": "cursor_string of the previous page" },
+                    { "name": "limit", "in": "query", "required": false, "schema": { "type": "integer", "default": 50, "maximum": 100 } }
+                ],
+                "responses": {
+                    "200": { "description": "beatmap_packs and the cursor_string of the next page" },
+                    "400": { "description": "Unknown type or invalid cursor" }
+                }
+            }
+        },
+        "/v2/beatmaps/packs/{tag}": {
+            "get": {
+                "tags": ["osu!v2 api"],
+                "summary": "Beatmap pack with its beatmapsets",
+                "description": "Fetched from osu! when the mirror doesn't know the pack or its set list yet.",
+                "parameters": [
+                    { "name": "tag", "in": "path", "required": true, "schema": { "type": "string" }, "description": "e.g. S1234" }
+                ],
+                "responses": {
+                    "200": { "description": "Pack, or null when osu! doesn't know it either" }
+                }
+            }
+        }
+    });
+
+    let v2_tournaments = json!({
+        "/v2/mappools": {
+            "get": {
+                "tags": ["osu!v2 api"],
+                "summary": "List tournament mappools",
+                "parameters": [
+                    { "name": "tournament", "in": "query", "required": false, "schema": { "type": "string" } },
+                    { "name": "round", "in": "query", "required": false, "schema": { "type": "string" } }
+                ],
+                "responses": {
+                    "200": { "description": "Mappools without their slots" }
+                }
+            }
+        },
+        "/v2/mappools/{id}": {
+            "get": {
+                "tags": ["osu!v2 api"],
+                "summary": "Get a mappool sheet",
+                "description": "Slots in order with star rating, length, BPM and CS/AR/OD/HP after the slot's mods, plus a /d/bulk download_url for the whole pool. Star ratings of rate and HR/EZ/FL slots come from osu! when the pool is saved and are null if that failed.",
+                "parameters": [
+                    { "name": "id", "in": "path", "required": true, "schema": { "type": "integer" } }
+                ],
+                "responses": {
+                    "200": { "description": "Mappool, or null" }
+                }
+            }
+        },
+        "/v2/mappools/{id}/sheet.csv": {
+            "get": {
+                "tags": ["osu!v2 api"],
+                "summary": "Get a mappool sheet as CSV",
+                "parameters": [
+                    { "name": "id", "in": "path", "required": true, "schema": { "type": "integer" } }
+                ],
+                "responses": {
+                    "200": {
+                        "description": "One row per slot",
+                        "content": { "text/csv": { "schema": { "type": "string" } } }
+                    },
+                    "404": { "description": "Unknown mappool" }
+                }
+            }
+        },
+        "/v2/sync/runs": {
+            "get": {
+                "tags": ["osu!v2 api"],
+                "summary": "Crawler sync runs",
+                "description": "Every crawler cycle, newest first. Consecutive idle cycles of a worker share one entry with `idle` set, whose `runs` counts them and whose `finished_at` is the end of the last. Runs are kept unless `crawler.sync_runs_retention_days` is set.",
+                "parameters": [
+                    { "name": "worker", "in": "query", "required": false, "schema": { "type": "string" }, "description": "Worker id, e.g. ranked_sync" },
+                    { "name": "failed", "in": "query", "required": false, "schema": { "type": "boolean", "default": false }, "description": "Only runs that failed or had per-set errors" },
+                    { "name": "before", "in": "query", "required": false, "schema": { "type": "integer" }, "description": "Only runs older than this run id" },
+                    { "name": "limit", "in": "query", "required": false, "schema": { "type": "integer", "default": 50, "maximum": 200 } }
+                ],
+                "responses": {
+                    "200": { "description": "Sync runs" }
+                }
+            }
+        },
+        "/v2/sync/runs/latest": {
+            "get": {
+                "tags": ["osu!v2 api"],
+                "summary": "Latest successful run of every crawler worker",
+                "responses": {
+                    "200": { "description": "One run per worker" }
+                }
+            }
+        }
+    });
+
+    let events = json!({
+        "/events": {
+            "get": {
+                "tags": ["Events"],
+                "summary": "Beatmapset event feed (Server-Sent Events)",
+                "description": "Emits new, status_change, update and deleted events. The SSE id is the history id; reconnect with Last-Event-ID or since= to resume. Ids are not strictly increasing, and a resume can repeat events recorded shortly before it, so deduplicate by id.",
+                "parameters": [
+                    {
+                        "name": "type",
+                        "in": "query",
+                        "required": false,
+                        "schema": { "type": "string" },
+                        "description": "Comma separated event types (new, status_change, update, deleted)"
+                    },
+                    {
+                        "name": "status",
+                        "in": "query",
+                        "required": false,
+                        "schema": { "type": "string" },
+                        "description": "Comma separated statuses, e.g. ranked,loved"
+                    },
+                    {
+                        "name": "mode",
+                        "in": "query",
+                        "required": false,
+                        "schema": { "type": "string" },
+                        "description": "Comma separated modes (osu, taiko, fruits, mania or 0-3)"
+                    },
+                    {
+                        "name": "since",
+                        "in": "query",
+                        "required": false,
+                        "schema": { "type": "integer" },
+                        "description": "Replay events after this id before following live events"
+                    }
+                ],
+                "responses": {
+                    "200": {
+                        "description": "Event stream",
+                        "content": {
+                            "text/event-stream": { "schema": { "type": "string" } }
+                        }
+                    },
+                    "400": { "description": "Unknown event type or mode" }
+                }
+            }
+        },
+
+        "/events/ws": {
+            "get": {
+                "tags": ["Events"],
+                "summary": "Beatmapset event feed (WebSocket)",
+                "description": "Same events and query parameters as /events, sent as JSON text messages.",
+                "responses": {
+                    "101": { "description": "Switching protocols" },
+                    "400": { "description": "Unknown event type or mode" }
+                }
+            }
+        }
+    });
 
-            "/v1/beatmaps/md5/{md5}": {
-                "get": {
-                    "tags": ["osu!v1 api"],
-                    "summary": "Get beatmap by MD5 hash",
-                    "parameters": [
-                        {
-                            "name": "md5",
-                            "in": "path",
-                            "required": true,
-                            "schema": { "type": "string" }
-                        }
-                    ],
-                    "responses": {
-                        "200": {
-                            "description": "Beatmap found",
-                            "content": {
-                                "application/json": { "schema": { "type": "array", "items": { "type": "object" } } }
+    let admin = json!({
+        "/admin/crawler": {
+            "get": {
+                "tags": ["Admin"],
+                "summary": "Crawler worker status",
+                "description": "Per worker: last run, last error, cursor, items synced, next run and whether it is paused. Also the size of the resync queue.",
+                "responses": {
+                    "200": { "description": "Crawler status" }
+                }
+            }
+        },
+        "/admin/crawler/workers/{id}/pause": {
+            "post": {
+                "tags": ["Admin"],
+                "summary": "Pause a crawler worker",
+                "description": "Pauses last until resumed or until the server restarts.",
+                "parameters": [
+                    { "name": "id", "in": "path", "required": true, "schema": { "type": "string" } }
+                ],
+                "responses": {
+                    "200": { "description": "Updated worker status" },
+                    "404": { "description": "Unknown worker" }
+                }
+            }
+        },
+        "/admin/crawler/workers/{id}/resume": {
+            "post": {
+                "tags": ["Admin"],
+                "summary": "Resume a paused crawler worker",
+                "parameters": [
+                    { "name": "id", "in": "path", "required": true, "schema": { "type": "string" } }
+                ],
+                "responses": {
+                    "200": { "description": "Updated worker status" },
+                    "404": { "description": "Unknown worker" }
+                }
+            }
+        },
+        "/admin/crawler/cursors/{id}": {
+            "delete": {
+                "tags": ["Admin"],
+                "summary": "Reset a sync cursor",
+                "description": "The worker starts over from the first page on its next run.",
+                "parameters": [
+                    { "name": "id", "in": "path", "required": true, "schema": { "type": "string" } }
+                ],
+                "responses": {
+                    "204": { "description": "Cursor reset" },
+                    "404": { "description": "Unknown cursor" }
+                }
+            }
+        },
+        "/admin/crawler/resync": {
+            "post": {
+                "tags": ["Admin"],
+                "summary": "Queue beatmapsets for a forced refetch",
+                "requestBody": {
+                    "required": true,
+                    "content": {
+                        "application/json": {
+                            "schema": {
+                                "type": "object",
+                                "properties": {
+                                    "ids": { "type": "array", "items": { "type": "integer" } },
+                                    "ranges": {
+                                        "type": "array",
+                                        "description": "Inclusive [from, to] id ranges",
+                                        "items": { "type": "array", "items": { "type": "integer" }, "minItems": 2, "maxItems": 2 }
+                                    }
+                                }
                             }
-                        },
-                        "404": { "description": "Not found" }
+                        }
+                    }
+                },
+                "responses": {
+                    "202": { "description": "Number of ids queued" },
+                    "400": { "description": "Invalid ids or ranges" }
+                }
+            }
+        },
+        "/admin/mappools": {
+            "post": {
+                "tags": ["Admin"],
+                "summary": "Create a mappool",
+                "description": "Slot mods are taken from the slot name (NM1, HD2, HDHR1, DT3; FM and TB have none) unless mods is given. Unknown beatmaps are fetched from osu!.",
+                "requestBody": {
+                    "required": true,
+                    "content": {
+                        "application/json": {
+                            "schema": save_mappool
+                        }
+                    }
+                },
+                "responses": {
+                    "201": { "description": "Mappool sheet" },
+                    "400": { "description": "Invalid pool, or the name is taken in that tournament round" },
+                    "401": { "description": "Missing or wrong admin token" }
+                }
+            }
+        },
+        "/admin/mappools/{id}": {
+            "put": {
+                "tags": ["Admin"],
+                "summary": "Replace a mappool and its slots",
+                "parameters": [
+                    { "name": "id", "in": "path", "required": true, "schema": { "type": "integer" } }
+                ],
+                "requestBody": {
+                    "required": true,
+                    "content": {
+                        "application/json": {
+                            "schema": save_mappool
+                        }
                     }
+                },
+                "responses": {
+                    "200": { "description": "Mappool sheet" },
+                    "400": { "description": "Invalid pool" },
+                    "404": { "description": "Unknown mappool" }
                 }
             },
-
-            "/v2/beatmapsets/{id}": {
-                "get": {
-                    "tags": ["osu!v2 api"],
-                    "summary": "Get beatmapset",
-                    "parameters": [
-                        {
-                            "name": "id",
-                            "in": "path",
-                            "required": true,
-                            "schema": { "type": "integer" }
-                        }
-                    ],
-                    "responses": {
-                        "200": {
-                            "description": "Beatmapset found",
-                            "content": {
-                                "application/json": {
-                                    "schema": { "type": "array", "items": { "type": "object" } }
+            "delete": {
+                "tags": ["Admin"],
+                "summary": "Delete a mappool",
+                "parameters": [
+                    { "name": "id", "in": "path", "required": true, "schema": { "type": "integer" } }
+                ],
+                "responses": {
+                    "204": { "description": "Deleted" },
+                    "404": { "description": "Unknown mappool" }
+                }
+            }
+        },
+        "/admin/webhooks": {
+            "get": {
+                "tags": ["Admin"],
+                "summary": "List webhooks",
+                "responses": {
+                    "200": { "description": "Registered webhooks" },
+                    "401": { "description": "Missing or wrong admin token" }
+                }
+            },
+            "post": {
+                "tags": ["Admin"],
+                "summary": "Register a webhook",
+                "description": "events: new, ranked, loved, qualified, disqualified, status_change, updated, deleted. format: json or discord. With a secret, deliveries carry X-Mirror-Signature: sha256=HMAC(secret, \"{X-Mirror-Timestamp}.{body}\").",
+                "requestBody": {
+                    "required": true,
+                    "content": {
+                        "application/json": {
+                            "schema": {
+                                "type": "object",
+                                "required": ["url"],
+                                "properties": {
+                                    "url": { "type": "string" },
+                                    "secret": { "type": "string" },
+                                    "events": { "type": "array", "items": { "type": "string" } },
+                                    "modes": { "type": "array", "items": { "type": "string" }, "description": "osu, taiko, fruits, mania or 0-3; empty for every mode" },
+                                    "format": { "type": "string", "enum": ["json", "discord"] }
                                 }
                             }
-                        },
-                        "404": { "description": "Not found" }
+                        }
                     }
+                },
+                "responses": {
+                    "201": { "description": "Webhook created" },
+                    "400": { "description": "Invalid webhook" }
+                }
+            }
+        },
+        "/admin/webhooks/{id}": {
+            "delete": {
+                "tags": ["Admin"],
+                "summary": "Delete a webhook",
+                "parameters": [
+                    { "name": "id", "in": "path", "required": true, "schema": { "type": "integer" } }
+                ],
+                "responses": {
+                    "204": { "description": "Deleted" },
+                    "404": { "description": "Webhook not found" }
+                }
+            }
+        },
+        "/admin/webhooks/{id}/deliveries": {
+            "get": {
+                "tags": ["Admin"],
+                "summary": "Delivery log of a webhook",
+                "parameters": [
+                    { "name": "id", "in": "path", "required": true, "schema": { "type": "integer" } },
+                    { "name": "limit", "in": "query", "required": false, "schema": { "type": "integer", "default": 50, "maximum": 200 } }
+                ],
+                "responses": {
+                    "200": { "description": "Deliveries, newest first" }
+                }
+            }
+        }
+    });
+
+    let compat = json!({
+        "/web/osu-search.php": {
+            "get": {
+                "tags": ["osu!direct"],
+                "summary": "osu!direct search",
+                "description": "Plain text: a result count (101 for a full page of 100), then one line per set: filename|artist|title|creator|status|rating|last update|set id|thread id|video|storyboard|size|size without video|difficulties. u and h are accepted and ignored.",
+                "parameters": [
+                    { "name": "q", "in": "query", "required": false, "schema": { "type": "string" }, "description": "Keywords and filters like /v2/search, or Newest, Top Rated, Most Played" },
+                    { "name": "r", "in": "query", "required": false, "schema": { "type": "integer", "default": 4 }, "description": "0 or 7 ranked, 2 pending, 3 qualified, 4 all, 5 graveyard, 8 loved" },
+                    { "name": "m", "in": "query", "required": false, "schema": { "type": "integer", "default": -1 }, "description": "Mode, -1 for all" },
+                    { "name": "p", "in": "query", "required": false, "schema": { "type": "integer", "default": 0 }, "description": "Page of 100" }
+                ],
+                "responses": {
+                    "200": { "description": "Search results", "content": { "text/plain": { "schema": { "type": "string" } } } },
+                    "400": { "description": "Invalid query or mode" }
+                }
+            }
+        },
+        "/web/osu-search-set.php": {
+            "get": {
+                "tags": ["osu!direct"],
+                "summary": "osu!direct set lookup",
+                "description": "The set line of osu-search.php without difficulties, or an empty body when nothing matches.",
+                "parameters": [
+                    { "name": "s", "in": "query", "required": false, "schema": { "type": "integer" }, "description": "Beatmapset id" },
+                    { "name": "b", "in": "query", "required": false, "schema": { "type": "integer" }, "description": "Beatmap id" },
+                    { "name": "c", "in": "query", "required": false, "schema": { "type": "string" }, "description": ".osu file md5" }
+                ],
+                "responses": {
+                    "200": { "description": "Set line", "content": { "text/plain": { "schema": { "type": "string" } } } },
+                    "400": { "description": "None of s, b or c given" }
+                }
+            }
+        },
+        "/search": {
+            "get": {
+                "tags": ["Compatibility"],
+                "summary": "Nerinyan search",
+                "description": "Only served with compat.nerinyan enabled. Returns osu! API v2 shaped sets.",
+                "parameters": [
+                    { "name": "q", "in": "query", "required": false, "schema": { "type": "string" } },
+                    { "name": "s", "in": "query", "required": false, "schema": { "type": "string" }, "description": "all, or statuses by name or number, comma separated" },
+                    { "name": "m", "in": "query", "required": false, "schema": { "type": "string" } },
+                    { "name": "nsfw", "in": "query", "required": false, "schema": { "type": "boolean" } },
+                    { "name": "e", "in": "query", "required": false, "schema": { "type": "string" } },
+                    { "name": "sort", "in": "query", "required": false, "schema": { "type": "string" }, "description": "Our sort names or Nerinyan's, e.g. play_count_desc" },
+                    { "name": "p", "in": "query", "required": false, "schema": { "type": "integer", "default": 0 } },
+                    { "name": "ps", "in": "query", "required": false, "schema": { "type": "integer", "default": 50, "maximum": 100 } }
+                ],
+                "responses": {
+                    "200": { "description": "Beatmapsets" }
                 }
             },
+            "post": {
+                "tags": ["Compatibility"],
+                "summary": "Nerinyan advanced search",
+                "description": "Only served with compat.nerinyan enabled. Takes Nerinyan's JSON body: query, ranked, m, nsfw, extra, sort, page, ps, and min/max ranges for totalLength, difficultyRating, accuracy, ar, cs, drain and bpm, where 0 means unbounded. maxCombo and option are ignored.",
+                "requestBody": { "required": true, "content": { "application/json": { "schema": { "type": "object" } } } },
+                "responses": {
+                    "200": { "description": "Beatmapsets" }
+                }
+            }
+        },
+        "/api/v2/s/{id}": {
+            "get": {
+                "tags": ["Compatibility"],
+                "summary": "catboy beatmapset",
+                "description": "Only served with compat.catboy enabled. Same as /v2/beatmapsets/{id}.",
+                "parameters": [
+                    { "name": "id", "in": "path", "required": true, "schema": { "type": "integer" } }
+                ],
+                "responses": {
+                    "200": { "description": "Beatmapset or null" }
+                }
+            }
+        },
+        "/api/v2/b/{id}": {
+            "get": {
+                "tags": ["Compatibility"],
+                "summary": "catboy beatmap",
+                "description": "Only served with compat.catboy enabled. Same as /v2/beatmaps/{id}.",
+                "parameters": [
+                    { "name": "id", "in": "path", "required": true, "schema": { "type": "integer" } }
+                ],
+                "responses": {
+                    "200": { "description": "Beatmap or null" }
+                }
+            }
+        },
+        "/api/v2/md5/{md5}": {
+            "get": {
+                "tags": ["Compatibility"],
+                "summary": "catboy beatmap by checksum",
+                "description": "Only served with compat.catboy enabled. Same as /v2/beatmaps/md5/{md5}.",
+                "parameters": [
+                    { "name": "md5", "in": "path", "required": true, "schema": { "type": "string" } }
+                ],
+                "responses": {
+                    "200": { "description": "Beatmap or null" }
+                }
+            }
+        },
+        "/api/v2/search": {
+            "get": {
+                "tags": ["Compatibility"],
+                "summary": "catboy search",
+                "description": "Only served with compat.catboy enabled. Returns osu! API v2 shaped sets.",
+                "parameters": [
+                    { "name": "query", "in": "query", "required": false, "schema": { "type": "string" } },
+                    { "name": "limit", "in": "query", "required": false, "schema": { "type": "integer", "default": 50, "maximum": 100 } },
+                    { "name": "offset", "in": "query", "required": false, "schema": { "type": "integer", "default": 0 } },
+                    { "name": "status", "in": "query", "required": false, "schema": { "type": "string" }, "description": "Statuses by name or number, comma separated" },
+                    { "name": "mode", "in": "query", "required": false, "schema": { "type": "string" } },
+                    { "name": "sort", "in": "query", "required": false, "schema": { "type": "string" } }
+                ],
+                "responses": {
+                    "200": { "description": "Beatmapsets" }
+                }
+            }
+        },
+        "/api/v1/set/{id}": {
+            "get": {
+                "tags": ["Compatibility"],
+                "summary": "Chimu beatmapset",
+                "description": "Only served with compat.chimu enabled. cheesegull-style set with ChildrenBeatmaps.",
+                "parameters": [
+                    { "name": "id", "in": "path", "required": true, "schema": { "type": "integer" } }
+                ],
+                "responses": {
+                    "200": { "description": "Set or null" }
+                }
+            }
+        },
+        "/api/v1/map/{id}": {
+            "get": {
+                "tags": ["Compatibility"],
+                "summary": "Chimu beatmap",
+                "description": "Only served with compat.chimu enabled.",
+                "parameters": [
+                    { "name": "id", "in": "path", "required": true, "schema": { "type": "integer" } }
+                ],
+                "responses": {
+                    "200": { "description": "Beatmap or null" }
+                }
+            }
+        },
+        "/api/v1/search": {
+            "get": {
+                "tags": ["Compatibility"],
+                "summary": "Chimu search",
+                "description": "Only served with compat.chimu enabled.",
+                "parameters": [
+                    { "name": "query", "in": "query", "required": false, "schema": { "type": "string" } },
+                    { "name": "amount", "in": "query", "required": false, "schema": { "type": "integer", "default": 50, "maximum": 100 } },
+                    { "name": "offset", "in": "query", "required": false, "schema": { "type": "integer", "default": 0 } },
+                    { "name": "status", "in": "query", "required": false, "schema": { "type": "string" } },
+                    { "name": "mode", "in": "query", "required": false, "schema": { "type": "string" } }
+                ],
+                "responses": {
+                    "200": { "description": "Sets" }
+                }
+            }
+        }
+    });
 
-            "/d/{id}": {
-                "get": {
-                    "summary": "Download beatmapset (.osz)",
-                    "parameters": [
-                        {
-                            "name": "id",
-                            "in": "path",
-                            "required": true,
-                            "schema": { "type": "integer" }
-                        },
-                        {
-                            "name": "nv",
-                            "in": "query",
-                            "schema": { "type": "string" },
-                            "required": false,
-                            "description": "No video flag (0/1/true/false)"
-                        },
-                        {
-                            "name": "novideo",
-                            "in": "query",
-                            "schema": { "type": "string" },
-                            "required": false,
-                            "description": "Alias of nv"
-                        }
-                    ],
-                    "responses": {
-                        "200": {
-                            "description": "Beatmapset download",
-                            "content": {
-                                "application/x-osu-beatmap-archive": {
-                                    "schema": { "type": "string", "format": "binary" }
+    let downloads = json!({
+        "/d/{id}": {
+            "get": {
+                "summary": "Download beatmapset (.osz)",
+                "parameters": [
+                    {
+                        "name": "id",
+                        "in": "path",
+                        "required": true,
+                        "schema": { "type": "string" },
+                        "description": "Beatmapset id; a trailing n (osu!direct) asks for the no-video variant"
+                    },
+                    {
+                        "name": "nv",
+                        "in": "query",
+                        "schema": { "type": "string" },
+                        "required": false,
+                        "description": "No video flag (0/1/true/false)"
+                    },
+                    {
+                        "name": "novideo",
+                        "in": "query",
+                        "schema": { "type": "string" },
+                        "required": false,
+                        "description": "Alias of nv"
+                    },
+                    {
+                        "name": "n",
+                        "in": "query",
+                        "schema": { "type": "string" },
+                        "required": false,
+                        "description": "Alias of nv"
+                    }
+                ],
+                "responses": {
+                    "200": {
+                        "description": "Beatmapset download",
+                        "content": {
+                            "application/x-osu-beatmap-archive": {
+                                "schema": { "type": "string", "format": "binary" }
+                            }
+                        }
+                    },
+                    "404": { "description": "Not found" }
+                }
+            }
+        },
+        "/b/{id}": {
+            "get": {
+                "summary": "Download the beatmapset of a beatmap (.osz)",
+                "description": "Resolves the set from the beatmap id, looking it up on osu! when unknown, then serves it like /d/{id}.",
+                "parameters": [
+                    { "name": "id", "in": "path", "required": true, "schema": { "type": "string" }, "description": "Beatmap id; a trailing n asks for the no-video variant" },
+                    { "name": "nv", "in": "query", "required": false, "schema": { "type": "string" }, "description": "No video flag (0/1/true/false)" },
+                    { "name": "redirect", "in": "query", "required": false, "schema": { "type": "string" }, "description": "Redirect to /d/{set_id} instead of serving the file (0/1/true/false)" }
+                ],
+                "responses": {
+                    "200": { "description": "Beatmapset download", "content": { "application/x-osu-beatmap-archive": { "schema": { "type": "string", "format": "binary" } } } },
+                    "302": { "description": "Redirect to /d/{set_id}" },
+                    "404": { "description": "Not found" }
+                }
+            }
+        },
+        "/d/md5/{checksum}": {
+            "get": {
+                "summary": "Download the beatmapset of a .osu checksum (.osz)",
+                "description": "Resolves the set from the .osu file md5, looking it up on osu! when unknown, then serves it like /d/{id}.",
+                "parameters": [
+                    { "name": "checksum", "in": "path", "required": true, "schema": { "type": "string" } },
+                    { "name": "nv", "in": "query", "required": false, "schema": { "type": "string" }, "description": "No video flag (0/1/true/false)" },
+                    { "name": "redirect", "in": "query", "required": false, "schema": { "type": "string" }, "description": "Redirect to /d/{set_id} instead of serving the file (0/1/true/false)" }
+                ],
+                "responses": {
+                    "200": { "description": "Beatmapset download", "content": { "application/x-osu-beatmap-archive": { "schema": { "type": "string", "format": "binary" } } } },
+                    "302": { "description": "Redirect to /d/{set_id}" },
+                    "404": { "description": "Not found" }
+                }
+            }
+        },
+        "/d/bulk": {
+            "get": {
+                "summary": "Download several beatmapsets as one .zip",
+                "description": "Streams a zip of the given sets, up to 50. Uncached sets are fetched from mirrors a few at a time; sets that fail are listed in FAILED.txt inside the archive.",
+                "parameters": [
+                    { "name": "ids", "in": "query", "required": true, "schema": { "type": "string" }, "description": "Comma separated beatmapset ids" },
+                    { "name": "nv", "in": "query", "required": false, "schema": { "type": "string" }, "description": "No-video variants (0/1/true/false)" },
+                    { "name": "novideo", "in": "query", "required": false, "schema": { "type": "string" }, "description": "Alias of nv" }
+                ],
+                "responses": {
+                    "200": {
+                        "description": "Archive of .osz files",
+                        "content": {
+                            "application/zip": {
+                                "schema": { "type": "string", "format": "binary" }
+                            }
+                        }
+                    },
+                    "400": { "description": "Missing or invalid ids, or more than 50" }
+                }
+            },
+            "post": {
+                "summary": "Download several beatmapsets as one .zip",
+                "description": "Same as the GET form with the ids in a JSON body.",
+                "requestBody": {
+                    "required": true,
+                    "content": {
+                        "application/json": {
+                            "schema": {
+                                "type": "object",
+                                "required": ["ids"],
+                                "properties": {
+                                    "ids": { "type": "array", "items": { "type": "integer" } },
+                                    "nv": { "type": "boolean" }
                                 }
                             }
-                        },
-                        "404": { "description": "Not found" }
+                        }
                     }
+                },
+                "responses": {
+                    "200": {
+                        "description": "Archive of .osz files",
+                        "content": {
+                            "application/zip": {
+                                "schema": { "type": "string", "format": "binary" }
+                            }
+                        }
+                    },
+                    "400": { "description": "Missing or invalid ids, or more than 50" }
+                }
+            }
+        },
+        "/d/pack/{tag}": {
+            "get": {
+                "summary": "Download a beatmap pack (.zip)",
+                "description": "Zip of the pack's sets that are cached on this mirror, falling back to the other video variant per set. Sets that aren't cached are listed in MISSING.txt inside the archive.",
+                "parameters": [
+                    { "name": "tag", "in": "path", "required": true, "schema": { "type": "string" } },
+                    { "name": "nv", "in": "query", "required": false, "schema": { "type": "string" }, "description": "Prefer no-video variants (0/1/true/false)" },
+                    { "name": "novideo", "in": "query", "required": false, "schema": { "type": "string" }, "description": "Alias of nv" }
+                ],
+                "responses": {
+                    "200": {
+                        "description": "Pack archive",
+                        "content": {
+                            "application/zip": {
+                                "schema": { "type": "string", "format": "binary" }
+                            }
+                        }
+                    },
+                    "404": { "description": "Unknown pack, or none of its sets are cached" }
                 }
             }
         }
+    });
+
+    // One json! per section, as a single literal this size needs a raised
+    // recursion limit.
+    let mut paths = Map::new();
+    for section in [
+        system,
+        v1,
+        v2_sets,
+        v2_beatmaps,
+        v2_tournaments,
+        events,
+        admin,
+        compat,
+        downloads,
+    ] {
+        if let Value::Object(section) = section {
+            paths.extend(section);
+        }
+    }
+
+    Json(json!({
+        "openapi": "3.1.1",
+
+        "info": {
+            "title": "osu-mirror-rs Documentation",
+            "version": VERSION,
+            "description": "This is the API documentation for the osu-mirror-rs an osu! beatmap mirror.",
+        },
+
+        "tags": [
+            {
+                "name": "System",
+                "description": "Server health and system endpoints"
+            },
+            {
+                "name": "osu!v1 api",
+                "description": "osu!api v1 compatible endpoints"
+            },
+            {
+                "name": "osu!v2 api",
+                "description": "osu!api v2 compatible endpoints"
+            },
+            {
+                "name": "Events",
+                "description": "Live feed of beatmapset changes seen by the crawler"
+            },
+            {
+                "name": "Admin",
+                "description": "Mirror administration. Requires `Authorization: Bearer <admin.token>`"
+            },
+        ],
+
+        "paths": paths
     }))
 }
@@ -1,18 +1,24 @@
 use crate::{
-    AppState, crawler,
-    db::queries,
+    AppState,
+    api::params::parse_bool_param,
+    crawler::{self, client::BeatmapLookup},
+    db::{models::PackArchiveEntry, queries},
     error::{AppError, Result},
+    storage::{BeatmapStorage, archive::ZipWriter},
 };
 use axum::{
+    Json,
     body::Body,
     extract::{Path, Query, State},
     http::{StatusCode, header},
     response::Response,
 };
 use bytes::Bytes;
+use futures::{SinkExt, StreamExt, channel::mpsc, stream};
 use once_cell::sync::Lazy;
 use reqwest::Client;
 use serde::Deserialize;
+use std::collections::HashSet;
 use std::sync::Mutex;
 use std::time::{Duration, Instant};
 
@@ -41,33 +47,45 @@ pub struct DownloadParams {
     nv: Option<String>,
     #[serde(rename = "novideo")]
     novideo: Option<String>,
-}
-
-fn parse_bool_param(v: &str) -> Option<bool> {
-    match v {
-        "" => Some(true),
-        "1" => Some(true),
-        "0" => Some(false),
-        "true" | "True" | "TRUE" => Some(true),
-        "false" | "False" | "FALSE" => Some(false),
-        _ => None,
-    }
+    /// osu!direct's spelling.
+    #[serde(rename = "n")]
+    n: Option<String>,
+    /// `/b/{id}` and `/d/md5/{checksum}` only: redirect to `/d/{set_id}`.
+    redirect: Option<String>,
 }
 
 fn parse_no_video(params: &DownloadParams) -> bool {
-    if let Some(ref nv) = params.nv {
-        if let Some(b) = parse_bool_param(nv) {
-            return b;
-        }
+    if let Some(ref nv) = params.nv
+        && let Some(b) = parse_bool_param(nv)
+    {
+        return b;
     }
-    if let Some(ref nv) = params.novideo {
-        if let Some(b) = parse_bool_param(nv) {
-            return b;
-        }
+    if let Some(ref nv) = params.novideo
+        && let Some(b) = parse_bool_param(nv)
+    {
+        return b;
+    }
+    if let Some(ref nv) = params.n
+        && let Some(b) = parse_bool_param(nv)
+    {
+        return b;
     }
     false
 }
 
+/// Id of `/d/{id}` or `/b/{id}`. osu!direct asks for the no-video variant as
+/// `/d/{id}n`.
+fn parse_download_id(raw: &str) -> Result<(i64, bool)> {
+    let (digits, no_video) = match raw.strip_suffix('n') {
+        Some(digits) => (digits, true),
+        None => (raw, false),
+    };
+    let id = digits
+        .parse()
+        .map_err(|_| AppError::BadRequest(format!("invalid id '{}'", raw)))?;
+    Ok((id, no_video))
+}
+
 fn sanitize_filename(s: &str) -> String {
     s.chars()
         .map(|c| match c {
@@ -157,17 +175,17 @@ async fn download_from_mirrors(id: i64, no_video: bool) -> Result<Bytes> {
     {
         let cache = MIRROR_CACHE.lock().unwrap();
         let ttl = Duration::from_secs(20);
-        if let Some(ref cached_url) = cache.url {
-            if cache.ts.elapsed() < ttl {
-                let mut new_urls = Vec::with_capacity(urls.len());
-                new_urls.push(cached_url.clone());
-                for u in urls.into_iter() {
-                    if u != *cached_url {
-                        new_urls.push(u);
-                    }
+        if let Some(ref cached_url) = cache.url
+            && cache.ts.elapsed() < ttl
+        {
+            let mut new_urls = Vec::with_capacity(urls.len());
+            new_urls.push(cached_url.clone());
+            for u in urls.into_iter() {
+                if u != *cached_url {
+                    new_urls.push(u);
                 }
-                urls = new_urls;
             }
+            urls = new_urls;
         }
     }
 
@@ -187,12 +205,143 @@ async fn download_from_mirrors(id: i64, no_video: bool) -> Result<Bytes> {
 
 pub async fn download_beatmapsets(
     State(state): State<AppState>,
-    Path(id): Path<i64>,
+    Path(raw_id): Path<String>,
     Query(params): Query<DownloadParams>,
 ) -> Result<Response> {
-    let no_video = parse_no_video(&params);
+    let (id, suffix_no_video) = parse_download_id(&raw_id)?;
+    let no_video = suffix_no_video || parse_no_video(&params);
     tracing::info!("download request: {} (no_video: {})", id, no_video);
 
+    serve_beatmapset(&state, id, no_video).await
+}
+
+/// `/b/{id}`: the set containing this beatmap.
+pub async fn download_by_beatmap(
+    State(state): State<AppState>,
+    Path(raw_id): Path<String>,
+    Query(params): Query<DownloadParams>,
+) -> Result<Response> {
+    let (beatmap_id, suffix_no_video) = parse_download_id(&raw_id)?;
+    let no_video = suffix_no_video || parse_no_video(&params);
+    tracing::info!(
+        "beatmap download request: {} (no_video: {})",
+        beatmap_id,
+        no_video
+    );
+
+    let mut set_id = queries::get_beatmap_set_id(&state.db, beatmap_id).await?;
+    if set_id.is_none() {
+        let lookup = BeatmapLookup {
+            id: Some(beatmap_id),
+            ..Default::default()
+        };
+        set_id = lookup_set_id(&state, &lookup).await;
+    }
+    let Some(set_id) = set_id else {
+        return Err(AppError::NotFound(format!(
+            "Beatmap {} not found",
+            beatmap_id
+        )));
+    };
+
+    serve_or_redirect(&state, set_id, no_video, &params).await
+}
+
+/// `/d/md5/{checksum}`: the set containing the beatmap with this .osu checksum.
+pub async fn download_by_checksum(
+    State(state): State<AppState>,
+    Path(checksum): Path<String>,
+    Query(params): Query<DownloadParams>,
+) -> Result<Response> {
+    let no_video = parse_no_video(&params);
+    tracing::info!(
+        "checksum download request: {} (no_video: {})",
+        checksum,
+        no_video
+    );
+
+    let mut set_id = queries::get_beatmap_ids_by_checksum(&state.db, &checksum)
+        .await?
+        .map(|(_, set_id)| set_id);
+    if set_id.is_none() {
+        let lookup = BeatmapLookup {
+            checksum: Some(&checksum),
+            ..Default::default()
+        };
+        set_id = lookup_set_id(&state, &lookup).await;
+    }
+    let Some(set_id) = set_id else {
+        return Err(AppError::NotFound(format!(
+            "Beatmap {} not found",
+            checksum
+        )));
+    };
+
+    serve_or_redirect(&state, set_id, no_video, &params).await
+}
+
+async fn lookup_set_id(state: &AppState, lookup: &BeatmapLookup<'_>) -> Option<i64> {
+    tracing::info!(
+        "beatmap {:?} not found locally → fetching from osu! API",
+        lookup
+    );
+    match state.osu_client.lookup_beatmap(lookup).await {
+        Ok(map) => map.map(|m| m.beatmapset_id),
+        Err(e) => {
+            tracing::warn!("failed to look up beatmap {:?} from API: {}", lookup, e);
+            None
+        }
+    }
+}
+
+/// With `redirect`, points the client at `/d/{set_id}` instead of serving the
+/// archive from this URL.
+async fn serve_or_redirect(
+    state: &AppState,
+    set_id: i64,
+    no_video: bool,
+    params: &DownloadParams,
+) -> Result<Response> {
+    let redirect = params
+        .redirect
+        .as_deref()
+        .and_then(parse_bool_param)
+        .unwrap_or(false);
+    if !redirect {
+        return serve_beatmapset(state, set_id, no_video).await;
+    }
+
+    let location = if no_video {
+        format!("/d/{}?nv=1", set_id)
+    } else {
+        format!("/d/{}", set_id)
+    };
+    Ok(Response::builder()
+        .status(StatusCode::FOUND)
+        .header(header::LOCATION, location)
+        .body(Body::empty())
+        .unwrap())
+}
+
+/// Serves a set from the cache, or from the mirrors on a miss, fetching its
+/// metadata from osu! first when we don't know it.
+async fn serve_beatmapset(state: &AppState, id: i64, no_video: bool) -> Result<Response> {
+    let osz = fetch_beatmapset(state, id, no_video).await?;
+    Ok(build_osz_response(
+        osz.data,
+        &osz.filename,
+        osz.cache_status,
+    ))
+}
+
+/// A set's archive along with the name it is served under.
+struct Osz {
+    data: Bytes,
+    filename: String,
+    cache_status: &'static str,
+}
+
+async fn fetch_beatmapset(state: &AppState, id: i64, no_video: bool) -> Result<Osz> {
     let mut set = queries::get_beatmapset(&state.db, id).await?;
 
     if set.is_none() {
@@ -202,7 +351,10 @@ pub async fn download_beatmapsets(
         );
 
         let api_set = match state.osu_client.get_beatmapset(id).await {
-            Ok(s) => s,
+            Ok(Some(s)) => s,
+            Ok(None) => {
+                return Err(AppError::NotFound(format!("Beatmapset {} not found", id)));
+            }
             Err(e) => {
                 tracing::warn!("failed to fetch beatmapset {} from API: {}", id, e);
                 return Err(AppError::NotFound(format!("Beatmapset {} not found", id)));
@@ -237,7 +389,11 @@ pub async fn download_beatmapsets(
 
     if let Ok(Some(bytes)) = state.storage.get(id, no_video).await {
         tracing::info!("cache HIT: {} (no_video: {})", id, no_video);
-        return Ok(build_osz_response(bytes, &filename, "HIT"));
+        return Ok(Osz {
+            data: bytes,
+            filename,
+            cache_status: "HIT",
+        });
     }
 
     tracing::info!("cache MISS: {} (no_video: {})", id, no_video);
@@ -277,5 +433,296 @@ pub async fn download_beatmapsets(
     .execute(&state.db)
     .await;
 
-    Ok(build_osz_response(data, &filename, "MISS"))
+    Ok(Osz {
+        data,
+        filename,
+        cache_status: "MISS",
+    })
+}
+
+/// Bundles the cached sets of a pack into one zip. Sets that aren't cached in
+/// either variant are left out and listed in `MISSING.txt` instead of being
+/// fetched from mirrors here.
+pub async fn download_pack(
+    State(state): State<AppState>,
+    Path(tag): Path<String>,
+    Query(params): Query<DownloadParams>,
+) -> Result<Response> {
+    let no_video = parse_no_video(&params);
+    tracing::info!("pack download request: {} (no_video: {})", tag, no_video);
+
+    let mut pack = queries::get_beatmap_pack(&state.db, &tag).await?;
+
+    if pack.as_ref().is_none_or(|p| p.sets_synced_at.is_none()) {
+        tracing::info!("pack {} not known locally → fetching from osu! API", tag);
+
+        match crawler::sync::fetch_beatmap_pack(&state.db, &state.osu_client, &tag).await {
+            Ok(true) => pack = queries::get_beatmap_pack(&state.db, &tag).await?,
+            Ok(false) => {}
+            Err(e) => tracing::warn!("failed to fetch pack {} from API: {}", tag, e),
+        }
+    }
+
+    let Some(pack) = pack else {
+        return Err(AppError::NotFound(format!("Pack {} not found", tag)));
+    };
+
+    let mut cached = Vec::new();
+    let mut missing = Vec::new();
+    for entry in queries::get_beatmap_pack_archive_entries(&state.db, &pack.tag).await? {
+        match cached_variant(&state.storage, entry.beatmapset_id, no_video).await {
+            Some(variant) => cached.push((entry, variant)),
+            None => missing.push(entry),
+        }
+    }
+
+    if cached.is_empty() {
+        return Err(AppError::NotFound(format!(
+            "None of the sets in pack {} are cached",
+            pack.tag
+        )));
+    }
+
+    tracing::info!(
+        "pack {}: {} cached, {} missing",
+        pack.tag,
+        cached.len(),
+        missing.len()
+    );
+
+    let (mut tx, rx) = mpsc::channel::<std::io::Result<Bytes>>(4);
+    let storage = state.storage.clone();
+    tokio::spawn(async move {
+        let mut zip = ZipWriter::new();
+
+        for (entry, variant) in cached {
+            let data = match storage.get(entry.beatmapset_id, variant).await {
+                Ok(Some(data)) => data,
+                // Evicted or unreadable since the check above.
+                _ => {
+                    missing.push(entry);
+                    continue;
+                }
+            };
+            for chunk in zip.add_entry(&pack_entry_name(&entry), data) {
+                if tx.send(Ok(chunk)).await.is_err() {
+                    return;
+                }
+            }
+        }
+
+        if !missing.is_empty() {
+            let manifest = missing_manifest(&missing);
+            for chunk in zip.add_entry("MISSING.txt", Bytes::from(manifest)) {
+                if tx.send(Ok(chunk)).await.is_err() {
+                    return;
+                }
+            }
+        }
+
+        let _ = tx.send(Ok(zip.finish())).await;
+    });
+
+    let base_name = format!("{} {}", pack.tag, pack.name);
+    let full_name = if no_video {
+        format!("{} [no video].zip", base_name)
+    } else {
+        format!("{}.zip", base_name)
+    };
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "application/zip")
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!(
+                r#"attachment; filename="{}""#,
+                sanitize_filename(&full_name)
+            ),
+        )
+        .body(Body::from_stream(rx))
+        .unwrap())
+}
+
+/// The cached variant to use for a set, preferring the requested one.
+async fn cached_variant(storage: &BeatmapStorage, id: i64, no_video: bool) -> Option<bool> {
+    for variant in [no_video, !no_video] {
+        if let Ok(true) = storage.exists(id, variant).await {
+            return Some(variant);
+        }
+    }
+    None
+}
+
+fn pack_entry_name(entry: &PackArchiveEntry) -> String {
+    let name = match (&entry.artist, &entry.title) {
+        (Some(artist), Some(title)) => {
+            format!("{} {} - {}.osz", entry.beatmapset_id, artist, title)
+        }
+        _ => format!("{}.osz", entry.beatmapset_id),
+    };
+    sanitize_filename(&name)
+}
+
+fn missing_manifest(missing: &[PackArchiveEntry]) -> String {
+    let mut out =
+        String::from("These sets were not cached on this mirror and are not included:\n\n");
+    for entry in missing {
+        match (&entry.artist, &entry.title) {
+            (Some(artist), Some(title)) => out.push_str(&format!(
+                "{} {} - {} https://osu.ppy.sh/beatmapsets/{}\n",
+                entry.beatmapset_id, artist, title, entry.beatmapset_id
+            )),
+            _ => out.push_str(&format!(
+                "{} https://osu.ppy.sh/beatmapsets/{}\n",
+                entry.beatmapset_id, entry.beatmapset_id
+            )),
+        }
+    }
+    out
+}
+
+/// Cap on sets per bulk download; a mappool is 10–30 sets.
+const MAX_BULK_IDS: usize = 50;
+
+/// Sets fetched from storage or mirrors at once while a bulk zip streams.
+const BULK_CONCURRENCY: usize = 4;
+
+#[derive(Deserialize)]
+pub struct BulkDownloadParams {
+    /// Comma separated set ids.
+    ids: Option<String>,
+    #[serde(flatten)]
+    download: DownloadParams,
+}
+
+#[derive(Deserialize)]
+pub struct BulkDownloadBody {
+    ids: Vec<i64>,
+    #[serde(default)]
+    nv: bool,
+}
+
+/// `GET /d/bulk?ids=1,2,3`.
+pub async fn download_bulk(
+    State(state): State<AppState>,
+    Query(params): Query<BulkDownloadParams>,
+) -> Result<Response> {
+    let ids = params
+        .ids
+        .as_deref()
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map(|id| {
+            id.parse()
+                .map_err(|_| AppError::BadRequest(format!("invalid id '{}'", id)))
+        })
+        .collect::<Result<Vec<i64>>>()?;
+
+    stream_bulk(state, ids, parse_no_video(&params.download))
+}
+
+/// `POST /d/bulk` with `{"ids": [1, 2, 3], "nv": false}`. `nv` and friends
+/// are also read from the query.
+pub async fn download_bulk_post(
+    State(state): State<AppState>,
+    Query(params): Query<DownloadParams>,
+    Json(body): Json<BulkDownloadBody>,
+) -> Result<Response> {
+    let no_video = body.nv || parse_no_video(&params);
+    stream_bulk(state, body.ids, no_video)
+}
+
+/// Streams a zip of the requested sets, fetching uncached ones through the
+/// usual mirror path a few at a time. Only the sets in flight are held in
+/// memory. Sets that can't be served are listed in `FAILED.txt`.
+fn stream_bulk(state: AppState, mut ids: Vec<i64>, no_video: bool) -> Result<Response> {
+    let mut seen = HashSet::new();
+    ids.retain(|id| seen.insert(*id));
+
+    if ids.is_empty() {
+        return Err(AppError::BadRequest("ids is required".to_string()));
+    }
+    if ids.len() > MAX_BULK_IDS {
+        return Err(AppError::BadRequest(format!(
+            "at most {} sets can be downloaded at once",
+            MAX_BULK_IDS
+        )));
+    }
+
+    tracing::info!(
+        "bulk download request: {} sets (no_video: {})",
+        ids.len(),
+        no_video
+    );
+
+    let count = ids.len();
+    let (mut tx, rx) = mpsc::channel::<std::io::Result<Bytes>>(4);
+    tokio::spawn(async move {
+        let mut zip = ZipWriter::new();
+        let mut failed = Vec::new();
+
+        let mut sets = stream::iter(ids)
+            .map(|id| {
+                let state = state.clone();
+                async move { (id, fetch_beatmapset(&state, id, no_video).await) }
+            })
+            .buffered(BULK_CONCURRENCY);
+
+        while let Some((id, result)) = sets.next().await {
+            let osz = match result {
+                Ok(osz) => osz,
+                Err(e) => {
+                    tracing::warn!("bulk download: set {} failed: {}", id, e);
+                    failed.push((id, e));
+                    continue;
+                }
+            };
+            for chunk in zip.add_entry(&osz.filename, osz.data) {
+                if tx.send(Ok(chunk)).await.is_err() {
+                    return;
+                }
+            }
+        }
+
+        if !failed.is_empty() {
+            let manifest = failed_manifest(&failed);
+            for chunk in zip.add_entry("FAILED.txt", Bytes::from(manifest)) {
+                if tx.send(Ok(chunk)).await.is_err() {
+                    return;
+                }
+            }
+        }
+
+        let _ = tx.send(Ok(zip.finish())).await;
+    });
+
+    let full_name = if no_video {
+        format!("beatmapsets ({} sets) [no video].zip", count)
+    } else {
+        format!("beatmapsets ({} sets).zip", count)
+    };
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "application/zip")
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!(r#"attachment; filename="{}""#, full_name),
+        )
+        .body(Body::from_stream(rx))
+        .unwrap())
+}
+
+fn failed_manifest(failed: &[(i64, AppError)]) -> String {
+    let mut out = String::from("These sets could not be downloaded and are not included:\n\n");
+    for (id, e) in failed {
+        out.push_str(&format!(
+            "{} https://osu.ppy.sh/beatmapsets/{} ({})\n",
+            id, id, e
+        ));
+    }
+    out
 }
@@ -0,0 +1,104 @@
+use crate::{
+    AppState,
+    error::Result,
+    events::{self, EventFilter},
+};
+use axum::{
+    extract::{
+        Query, State,
+        ws::{Message, WebSocket, WebSocketUpgrade},
+    },
+    http::HeaderMap,
+    response::{
+        Response,
+        sse::{Event, KeepAlive, Sse},
+    },
+};
+use futures::{Stream, StreamExt};
+use serde::Deserialize;
+use sqlx::PgPool;
+
+#[derive(Deserialize)]
+pub struct EventParams {
+    /// Comma separated event types: new, status_change, update, deleted
+    #[serde(rename = "type", default)]
+    kind: Option<String>,
+    /// Comma separated statuses, e.g. `ranked,loved`
+    #[serde(default)]
+    status: Option<String>,
+    /// Comma separated modes (`osu`, `taiko`, `fruits`, `mania` or 0-3)
+    #[serde(default)]
+    mode: Option<String>,
+    /// Resume after this event id. SSE clients can send `Last-Event-ID` instead.
+    #[serde(default)]
+    since: Option<i64>,
+}
+
+impl EventParams {
+    fn filter(&self) -> Result<EventFilter> {
+        EventFilter::from_params(
+            self.kind.as_deref(),
+            self.status.as_deref(),
+            self.mode.as_deref(),
+        )
+    }
+}
+
+fn last_event_id(headers: &HeaderMap) -> Option<i64> {
+    headers
+        .get("last-event-id")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse().ok())
+}
+
+pub async fn events_sse(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<EventParams>,
+) -> Result<Sse<impl Stream<Item = std::result::Result<Event, axum::Error>>>> {
+    let resume = params.since.or_else(|| last_event_id(&headers));
+
+    let stream = events::stream(state.db.clone(), params.filter()?, resume).map(|ev| {
+        Event::default()
+            .id(ev.id.to_string())
+            .event(ev.kind.as_str())
+            .json_data(&ev)
+    });
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+pub async fn events_ws(
+    ws: WebSocketUpgrade,
+    State(state): State<AppState>,
+    Query(params): Query<EventParams>,
+) -> Result<Response> {
+    let filter = params.filter()?;
+    let resume = params.since;
+    Ok(ws.on_upgrade(move |socket| forward_events(socket, state.db, filter, resume)))
+}
+
+async fn forward_events(
+    mut socket: WebSocket,
+    pool: PgPool,
+    filter: EventFilter,
+    resume: Option<i64>,
+) {
+    let mut events = Box::pin(events::stream(pool, filter, resume));
+
+    loop {
+        tokio::select! {
+            ev = events.next() => {
+                let Some(ev) = ev else { break };
+                let Ok(text) = serde_json::to_string(&ev) else { continue };
+                if socket.send(Message::Text(text.into())).await.is_err() {
+                    break;
+                }
+            }
+            msg = socket.recv() => match msg {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                _ => {}
+            }
+        }
+    }
+}
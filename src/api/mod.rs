@@ -1,6 +1,11 @@
+pub mod admin;
+pub mod compat;
+pub mod direct;
 pub mod docs;
 pub mod download;
+pub mod events;
 pub mod health;
+pub mod params;
 pub mod routes;
 pub mod v1;
 pub mod v2;
@@ -0,0 +1,31 @@
+use serde::{Deserialize, Deserializer, de::Error};
+
+/// `1`/`0`, `true`/`false` in the usual casings, or a bare key meaning true.
+pub fn parse_bool_param(v: &str) -> Option<bool> {
+    match v {
+        "" => Some(true),
+        "1" => Some(true),
+        "0" => Some(false),
+        "true" | "True" | "TRUE" => Some(true),
+        "false" | "False" | "FALSE" => Some(false),
+        _ => None,
+    }
+}
+
+/// `#[serde(default, deserialize_with = "bool_param")]`: a flag that accepts
+/// whatever [`parse_bool_param`] does, so every endpoint takes the same values.
+pub fn bool_param<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Raw {
+        Bool(bool),
+        Str(String),
+    }
+
+    match Raw::deserialize(deserializer)? {
+        Raw::Bool(b) => Ok(b),
+        Raw::Str(s) => {
+            parse_bool_param(&s).ok_or_else(|| D::Error::custom(format!("invalid boolean `{}`", s)))
+        }
+    }
+}
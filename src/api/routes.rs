@@ -1,15 +1,20 @@
+use super::admin;
+use super::compat;
+use super::direct;
 use super::docs::openapi_json;
 use super::download;
+use super::events;
 use super::health;
 use super::v1;
 use super::v2;
 
 use crate::AppState;
-use crate::middleware::rate_limit::RateLimitLayer;
+use crate::middleware::{admin::require_admin, rate_limit::RateLimitLayer};
 
 use axum::{
     Json, Router,
     http::StatusCode,
+    middleware::from_fn_with_state,
     response::{Html, IntoResponse, Response},
     routing::get,
 };
@@ -58,15 +63,36 @@ async fn docs_handler() -> Html<String> {
 pub fn create_router(state: AppState) -> Router {
     let v1_router = v1::routes::router();
     let v2_router = v2::routes::router();
+    let admin_router =
+        admin::routes::router().route_layer(from_fn_with_state(state.clone(), require_admin));
+    let compat_router = compat::routes::router(&state.config.compat);
 
     Router::new()
         .nest("/v1", v1_router)
         .nest("/v2", v2_router)
+        .nest("/admin", admin_router)
+        // osu! API v1 compatibility
+        .route("/api/get_beatmaps", get(v1::legacy::get_beatmaps))
+        // osu!direct
+        .route("/web/osu-search.php", get(direct::osu_search))
+        .route("/web/osu-search-set.php", get(direct::osu_search_set))
         // Status
         .route("/health", get(health::health_check))
         .route("/status", get(health::status))
         // Download
         .route("/d/{id}", get(download::download_beatmapsets))
+        .route(
+            "/d/bulk",
+            get(download::download_bulk).post(download::download_bulk_post),
+        )
+        .route("/d/md5/{checksum}", get(download::download_by_checksum))
+        .route("/b/{id}", get(download::download_by_beatmap))
+        .route("/d/pack/{tag}", get(download::download_pack))
+        // Events
+        .route("/events", get(events::events_sse))
+        .route("/events/ws", get(events::events_ws))
+        // Other mirrors' APIs, per config
+        .merge(compat_router)
         // Docs
         .route("/docs", get(docs_handler))
         .route("/docs/openapi.json", get(openapi_json))
@@ -1,16 +1,24 @@
 use axum::{
     Json,
-    extract::{Path, State},
+    extract::{Path, Query, State},
 };
 use chrono::Utc;
+use serde::Deserialize;
 
-use crate::{AppState, db::models::Beatmap, db::queries, error::Result};
+use crate::{AppState, api::params::bool_param, db::models::Beatmap, db::queries, error::Result};
 
 use super::mapping::BeatmapV1;
 
+#[derive(Deserialize)]
+pub struct BeatmapParams {
+    #[serde(default, deserialize_with = "bool_param")]
+    include_deleted: bool,
+}
+
 pub async fn get_beatmaps_v1(
     State(state): State<AppState>,
     Path(id): Path<i64>,
+    Query(params): Query<BeatmapParams>,
 ) -> Result<Json<Vec<BeatmapV1>>> {
     let row = sqlx::query!(
         r#"
@@ -33,6 +41,8 @@ pub async fn get_beatmaps_v1(
             count_sliders,
             count_spinners,
             checksum,
+            deleted,
+            deleted_at,
             created_at,
             updated_at
         FROM beatmaps
@@ -66,8 +76,12 @@ pub async fn get_beatmaps_v1(
         count_sliders: r.count_sliders,
         count_spinners: r.count_spinners,
         checksum: r.checksum,
+        deleted: r.deleted,
+        deleted_at: r.deleted_at,
         created_at: r.created_at.unwrap_or_else(Utc::now),
         updated_at: r.updated_at.unwrap_or_else(Utc::now),
+        aim: None,
+        speed: None,
     };
 
     let set = queries::get_beatmapset(&state.db, map.beatmapset_id).await?;
@@ -76,7 +90,17 @@ pub async fn get_beatmaps_v1(
         return Ok(Json(Vec::new()));
     };
 
-    let v1 = BeatmapV1::from_models(&set, &map);
+    if !params.include_deleted && (set.deleted || map.deleted) {
+        return Ok(Json(Vec::new()));
+    }
+
+    // The set's copy carries the precomputed difficulty attributes.
+    let map = set
+        .beatmaps
+        .as_deref()
+        .and_then(|maps| maps.iter().find(|m| m.id == map.id))
+        .unwrap_or(&map);
+    let v1 = BeatmapV1::from_models(&set, map);
 
     Ok(Json(vec![v1]))
 }
@@ -84,6 +108,7 @@ pub async fn get_beatmaps_v1(
 pub async fn get_beatmaps_by_md5_v1(
     State(state): State<AppState>,
     Path(md5): Path<String>,
+    Query(params): Query<BeatmapParams>,
 ) -> Result<Json<Vec<BeatmapV1>>> {
     let row = sqlx::query!(
         r#"
@@ -106,6 +131,8 @@ pub async fn get_beatmaps_by_md5_v1(
             count_sliders,
             count_spinners,
             checksum,
+            deleted,
+            deleted_at,
             created_at,
             updated_at
         FROM beatmaps
@@ -140,8 +167,12 @@ pub async fn get_beatmaps_by_md5_v1(
         count_sliders: r.count_sliders,
         count_spinners: r.count_spinners,
         checksum: r.checksum,
+        deleted: r.deleted,
+        deleted_at: r.deleted_at,
         created_at: r.created_at.unwrap_or_else(Utc::now),
         updated_at: r.updated_at.unwrap_or_else(Utc::now),
+        aim: None,
+        speed: None,
     };
 
     let set = queries::get_beatmapset(&state.db, map.beatmapset_id).await?;
@@ -150,7 +181,17 @@ pub async fn get_beatmaps_by_md5_v1(
         return Ok(Json(Vec::new()));
     };
 
-    let v1 = BeatmapV1::from_models(&set, &map);
+    if !params.include_deleted && (set.deleted || map.deleted) {
+        return Ok(Json(Vec::new()));
+    }
+
+    // The set's copy carries the precomputed difficulty attributes.
+    let map = set
+        .beatmaps
+        .as_deref()
+        .and_then(|maps| maps.iter().find(|m| m.id == map.id))
+        .unwrap_or(&map);
+    let v1 = BeatmapV1::from_models(&set, map);
 
     Ok(Json(vec![v1]))
 }
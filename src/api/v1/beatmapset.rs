@@ -1,15 +1,23 @@
 use axum::{
     Json,
-    extract::{Path, State},
+    extract::{Path, Query, State},
 };
+use serde::Deserialize;
 
-use crate::{AppState, db::queries, error::Result};
+use crate::{AppState, api::params::bool_param, db::queries, error::Result};
 
 use super::mapping::BeatmapV1;
 
+#[derive(Deserialize)]
+pub struct BeatmapsetParams {
+    #[serde(default, deserialize_with = "bool_param")]
+    include_deleted: bool,
+}
+
 pub async fn get_beatmapset_v1(
     State(state): State<AppState>,
     Path(id): Path<i64>,
+    Query(params): Query<BeatmapsetParams>,
 ) -> Result<Json<Vec<BeatmapV1>>> {
     let mut set = queries::get_beatmapset(&state.db, id).await?;
 
@@ -20,7 +28,8 @@ pub async fn get_beatmapset_v1(
         );
 
         let api_set = match state.osu_client.get_beatmapset(id).await {
-            Ok(s) => s,
+            Ok(Some(s)) => s,
+            Ok(None) => return Ok(Json(Vec::new())),
             Err(e) => {
                 tracing::warn!("Failed to fetch beatmapset {} from API: {}", id, e);
                 return Ok(Json(Vec::new()));
@@ -35,10 +44,17 @@ pub async fn get_beatmapset_v1(
         set = queries::get_beatmapset(&state.db, id).await?;
     }
 
-    let Some(set) = set else {
+    let Some(mut set) = set else {
         return Ok(Json(Vec::new()));
     };
 
+    if !params.include_deleted {
+        if set.deleted {
+            return Ok(Json(Vec::new()));
+        }
+        set.retain_live_beatmaps();
+    }
+
     let mut result = Vec::new();
 
     if let Some(beatmaps) = set.beatmaps.as_ref() {
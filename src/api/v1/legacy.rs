@@ -0,0 +1,219 @@
+use axum::{
+    Json,
+    extract::{Query, State},
+};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use crate::{
+    AppState,
+    crawler::{self, client::BeatmapLookup},
+    db::{
+        models::Beatmapset,
+        queries,
+        search::{CreatorRef, LegacyBeatmapFilter},
+    },
+    error::{AppError, Result},
+};
+
+use super::mapping::BeatmapV1;
+
+/// Same cap as osu!'s v1 API.
+const MAX_LIMIT: i64 = 500;
+
+/// Query of `/api/get_beatmaps`. Everything is a string because v1 clients
+/// routinely send empty parameters like `s=&b=`.
+#[derive(Deserialize)]
+pub struct GetBeatmapsParams {
+    #[serde(default)]
+    k: Option<String>,
+    #[serde(default)]
+    s: Option<String>,
+    #[serde(default)]
+    b: Option<String>,
+    #[serde(default)]
+    u: Option<String>,
+    #[serde(rename = "type", default)]
+    user_type: Option<String>,
+    #[serde(default)]
+    m: Option<String>,
+    #[serde(default)]
+    a: Option<String>,
+    #[serde(default)]
+    h: Option<String>,
+    #[serde(default)]
+    since: Option<String>,
+    #[serde(default)]
+    limit: Option<String>,
+}
+
+impl GetBeatmapsParams {
+    fn to_filter(&self) -> Result<LegacyBeatmapFilter> {
+        let creator = match (non_empty(&self.u), non_empty(&self.user_type)) {
+            (None, _) => None,
+            (Some(u), Some("string")) => Some(CreatorRef::Name(u.to_string())),
+            (Some(u), Some("id")) => Some(CreatorRef::Id(parse_int("u", u)?)),
+            (Some(u), _) => Some(match u.parse() {
+                Ok(id) => CreatorRef::Id(id),
+                Err(_) => CreatorRef::Name(u.to_string()),
+            }),
+        };
+
+        let mode = non_empty(&self.m)
+            .map(|m| match m.parse::<i32>() {
+                Ok(m @ 0..=3) => Ok(m),
+                _ => Err(AppError::BadRequest(format!("invalid m '{}'", m))),
+            })
+            .transpose()?;
+
+        Ok(LegacyBeatmapFilter {
+            beatmapset_id: non_empty(&self.s).map(|s| parse_int("s", s)).transpose()?,
+            beatmap_id: non_empty(&self.b).map(|b| parse_int("b", b)).transpose()?,
+            creator,
+            checksum: non_empty(&self.h).map(str::to_string),
+            since: non_empty(&self.since).map(parse_since).transpose()?,
+            mode,
+            converts: non_empty(&self.a) == Some("1"),
+        })
+    }
+}
+
+fn non_empty(value: &Option<String>) -> Option<&str> {
+    value.as_deref().filter(|v| !v.is_empty())
+}
+
+fn parse_int(name: &str, value: &str) -> Result<i64> {
+    value
+        .parse()
+        .map_err(|_| AppError::BadRequest(format!("invalid {} '{}'", name, value)))
+}
+
+/// MySQL-style `2020-01-31 12:00:00` or a bare date, in UTC.
+fn parse_since(value: &str) -> Result<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S")
+        .or_else(|_| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d").map(|d| d.and_time(Default::default()))
+        })
+        .map(|dt| dt.and_utc())
+        .map_err(|_| AppError::BadRequest(format!("invalid since '{}'", value)))
+}
+
+/// osu! API v1 `/api/get_beatmaps`. Converts keep the osu! beatmap's
+/// difficulty values and only report the requested mode.
+pub async fn get_beatmaps(
+    State(state): State<AppState>,
+    Query(params): Query<GetBeatmapsParams>,
+) -> Result<Json<Vec<BeatmapV1>>> {
+    let keys = &state.config.legacy_api.keys;
+    if !keys.is_empty() && !non_empty(&params.k).is_some_and(|k| keys.iter().any(|key| key == k)) {
+        return Err(AppError::Unauthorized);
+    }
+
+    let filter = params.to_filter()?;
+    let limit = non_empty(&params.limit)
+        .map(|l| parse_int("limit", l))
+        .transpose()?
+        .unwrap_or(MAX_LIMIT)
+        .clamp(1, MAX_LIMIT);
+
+    let mut ids = queries::get_legacy_beatmap_ids(&state.db, &filter, limit).await?;
+
+    if ids.is_empty() && fetch_missing(&state, &filter).await? {
+        ids = queries::get_legacy_beatmap_ids(&state.db, &filter, limit).await?;
+    }
+
+    let mut set_ids: Vec<i64> = ids.iter().map(|(_, set_id)| *set_id).collect();
+    set_ids.sort_unstable();
+    set_ids.dedup();
+    let sets: HashMap<i64, Beatmapset> = queries::get_beatmapsets(&state.db, &set_ids)
+        .await?
+        .into_iter()
+        .map(|s| (s.id, s))
+        .collect();
+
+    let result = ids
+        .into_iter()
+        .filter_map(|(id, set_id)| {
+            let set = sets.get(&set_id)?;
+            let map = set.beatmaps.as_deref()?.iter().find(|b| b.id == id)?;
+            let mut v1 = BeatmapV1::from_models(set, map);
+            if let Some(mode) = filter.mode {
+                v1.mode = mode.to_string();
+            }
+            Some(v1)
+        })
+        .collect();
+
+    Ok(Json(result))
+}
+
+/// A `s`, `b` or `h` lookup for a set or beatmap we don't know at all is
+/// fetched from osu! like the other single-item endpoints. Returns whether a
+/// set was saved.
+async fn fetch_missing(state: &AppState, filter: &LegacyBeatmapFilter) -> Result<bool> {
+    let set_id = if let Some(id) = filter.beatmapset_id {
+        if queries::get_missing_beatmapset_ids(&state.db, &[id])
+            .await?
+            .is_empty()
+        {
+            return Ok(false);
+        }
+        Some(id)
+    } else if let Some(id) = filter.beatmap_id {
+        if queries::get_beatmap_set_id(&state.db, id).await?.is_some() {
+            return Ok(false);
+        }
+        lookup(
+            state,
+            &BeatmapLookup {
+                id: Some(id),
+                ..Default::default()
+            },
+        )
+        .await
+    } else if let Some(checksum) = filter.checksum.as_deref() {
+        if queries::get_beatmap_ids_by_checksum(&state.db, checksum)
+            .await?
+            .is_some()
+        {
+            return Ok(false);
+        }
+        lookup(
+            state,
+            &BeatmapLookup {
+                checksum: Some(checksum),
+                ..Default::default()
+            },
+        )
+        .await
+    } else {
+        return Ok(false);
+    };
+
+    let Some(set_id) = set_id else {
+        return Ok(false);
+    };
+
+    tracing::info!(
+        "Beatmapset {} not found locally → fetching from osu! API",
+        set_id
+    );
+    match crawler::sync::resync_beatmapset(&state.db, &state.osu_client, set_id).await {
+        Ok(_) => Ok(true),
+        Err(e) => {
+            tracing::warn!("Failed to fetch beatmapset {} from API: {}", set_id, e);
+            Ok(false)
+        }
+    }
+}
+
+async fn lookup(state: &AppState, lookup: &BeatmapLookup<'_>) -> Option<i64> {
+    match state.osu_client.lookup_beatmap(lookup).await {
+        Ok(map) => map.map(|m| m.beatmapset_id),
+        Err(e) => {
+            tracing::warn!("Failed to look up beatmap {:?} from API: {}", lookup, e);
+            None
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use crate::db::models::{Beatmap, Beatmapset};
+use crate::db::models::{Beatmap, Beatmapset, RankStatus};
 use serde::Serialize;
 
 fn format_dt(dt: chrono::DateTime<chrono::Utc>) -> String {
@@ -6,16 +6,7 @@ fn format_dt(dt: chrono::DateTime<chrono::Utc>) -> String {
 }
 
 fn status_to_approved(status: &str) -> i32 {
-    match status {
-        "graveyard" => -2,
-        "wip" => -1,
-        "pending" => 0,
-        "ranked" => 1,
-        "approved" => 2,
-        "qualified" => 3,
-        "loved" => 4,
-        _ => 0,
-    }
+    RankStatus::parse(status).map_or(0, |s| s.as_int())
 }
 
 #[derive(Serialize)]
@@ -153,10 +144,10 @@ impl BeatmapV1 {
             audio_unavailable,
             playcount: play_count.to_string(),
             passcount: "0".to_string(),
-            packs: None,
+            packs: (!set.pack_tags.is_empty()).then(|| set.pack_tags.join(",")),
             max_combo: max_combo.to_string(),
-            diff_aim: Some("0".to_string()),
-            diff_speed: Some("0".to_string()),
+            diff_aim: map.aim.map(|a| a.to_string()),
+            diff_speed: map.speed.map(|s| s.to_string()),
             difficultyrating: diff.to_string(),
         }
     }
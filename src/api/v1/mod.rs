@@ -1,5 +1,6 @@
 pub mod beatmaps;
 pub mod beatmapset;
+pub mod legacy;
 pub mod mapping;
 pub mod routes;
 pub mod search;
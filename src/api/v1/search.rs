@@ -4,7 +4,15 @@ use axum::{
 };
 use serde::Deserialize;
 
-use crate::{AppState, db::queries, error::Result};
+use crate::{
+    AppState,
+    api::params::bool_param,
+    db::{
+        queries,
+        search::{SearchFilter, SearchSort},
+    },
+    error::Result,
+};
 
 use super::mapping::BeatmapV1;
 
@@ -18,6 +26,8 @@ pub struct SearchParams {
     limit: i64,
     #[serde(default)]
     offset: i64,
+    #[serde(default, deserialize_with = "bool_param")]
+    include_deleted: bool,
 }
 
 fn default_limit() -> i64 {
@@ -28,25 +38,33 @@ pub async fn search_v1(
     State(state): State<AppState>,
     Query(params): Query<SearchParams>,
 ) -> Result<Json<Vec<BeatmapV1>>> {
-    let beatmapsets = queries::search_beatmapsets(
+    let mut filter = SearchFilter::parse(&params.query)?;
+    filter.include_deleted = params.include_deleted;
+    if let Some(status) = params.status.as_deref() {
+        filter.add_status(status)?;
+    }
+
+    let page = queries::search_beatmapsets(
         &state.db,
-        &params.query,
-        params.status.as_deref(),
-        params.limit.min(100),
+        &filter,
+        &SearchSort::RANKED_DESC,
+        None,
+        params.limit.clamp(1, 100),
         params.offset,
     )
     .await?;
 
     let mut result = Vec::new();
 
-    'outer: for s in beatmapsets {
-        if let Some(full) = queries::get_beatmapset(&state.db, s.id).await? {
-            if let Some(beatmaps) = full.beatmaps.as_ref() {
-                for m in beatmaps {
-                    result.push(BeatmapV1::from_models(&full, m));
-                    if result.len() as i64 >= params.limit {
-                        break 'outer;
-                    }
+    'outer: for mut set in page.sets {
+        if !params.include_deleted {
+            set.retain_live_beatmaps();
+        }
+        if let Some(beatmaps) = set.beatmaps.as_ref() {
+            for m in beatmaps {
+                result.push(BeatmapV1::from_models(&set, m));
+                if result.len() as i64 >= params.limit {
+                    break 'outer;
                 }
             }
         }
@@ -0,0 +1,219 @@
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+};
+use rosu_pp::any::{DifficultyAttributes, PerformanceAttributes};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    AppState,
+    crawler::{self, client::BeatmapLookup},
+    db::{models::Beatmap, queries},
+    difficulty::{self, ScoreInput},
+    error::{AppError, Result},
+    mods::{Difficulty, Mods},
+};
+
+#[derive(Deserialize)]
+pub struct AttributesParams {
+    #[serde(default)]
+    mods: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct PerformanceParams {
+    #[serde(default)]
+    mods: Option<String>,
+    /// Percent, 0–100.
+    #[serde(default)]
+    acc: Option<f64>,
+    #[serde(default)]
+    combo: Option<u32>,
+    #[serde(default)]
+    misses: Option<u32>,
+}
+
+#[derive(Serialize)]
+pub struct BeatmapAttributesV2 {
+    pub beatmap_id: i64,
+    pub mode: String,
+    pub mods: Mods,
+    pub star_rating: f64,
+    /// osu! standard only.
+    pub aim: Option<f64>,
+    pub speed: Option<f64>,
+    pub flashlight: Option<f64>,
+    pub max_combo: u32,
+    pub cs: Option<f64>,
+    pub ar: Option<f64>,
+    pub od: Option<f64>,
+    pub hp: Option<f64>,
+    pub bpm: Option<f64>,
+    /// Seconds.
+    pub total_length: Option<i32>,
+    pub hit_length: Option<i32>,
+}
+
+#[derive(Serialize)]
+pub struct BeatmapPerformanceV2 {
+    pub beatmap_id: i64,
+    pub mode: String,
+    pub mods: Mods,
+    pub accuracy: Option<f64>,
+    pub combo: Option<u32>,
+    pub misses: Option<u32>,
+    pub pp: f64,
+    /// osu! standard only.
+    pub pp_aim: Option<f64>,
+    pub pp_speed: Option<f64>,
+    pub pp_accuracy: Option<f64>,
+    pub pp_flashlight: Option<f64>,
+    pub star_rating: f64,
+    pub max_combo: u32,
+}
+
+/// `/beatmaps/{id}/attributes?mods=HDDT`: star rating and skill values
+/// calculated from the .osu file, with the map's settings after the mods.
+pub async fn get_beatmap_attributes_v2(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Query(params): Query<AttributesParams>,
+) -> Result<Json<BeatmapAttributesV2>> {
+    let mods = parse_mods(params.mods.as_deref())?;
+    let map = load_beatmap(&state, id).await?;
+    let attrs = difficulty::attributes(&state, &map, mods).await?;
+
+    let (aim, speed, flashlight) = match &attrs {
+        DifficultyAttributes::Osu(a) => (Some(a.aim), Some(a.speed), Some(a.flashlight)),
+        _ => (None, None, None),
+    };
+
+    let rate = mods.clock_rate();
+    let adjusted = mods.apply(Difficulty {
+        cs: map.cs.unwrap_or(0.0),
+        ar: map.ar.unwrap_or(0.0),
+        od: map.accuracy.unwrap_or(0.0),
+        hp: map.drain.unwrap_or(0.0),
+    });
+    let scale_length = |l: i32| (f64::from(l) / rate).round() as i32;
+
+    Ok(Json(BeatmapAttributesV2 {
+        beatmap_id: map.id,
+        mode: map.mode.clone(),
+        mods,
+        star_rating: attrs.stars(),
+        aim,
+        speed,
+        flashlight,
+        max_combo: attrs.max_combo(),
+        cs: map.cs.map(|_| adjusted.cs),
+        ar: map.ar.map(|_| adjusted.ar),
+        od: map.accuracy.map(|_| adjusted.od),
+        hp: map.drain.map(|_| adjusted.hp),
+        bpm: map.bpm.map(|b| b * rate),
+        total_length: map.total_length.map(scale_length),
+        hit_length: map.hit_length.map(scale_length),
+    }))
+}
+
+/// `/beatmaps/{id}/pp?acc=&combo=&misses=&mods=`. Omitted values mean an SS
+/// with full combo.
+pub async fn get_beatmap_pp_v2(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Query(params): Query<PerformanceParams>,
+) -> Result<Json<BeatmapPerformanceV2>> {
+    if let Some(acc) = params.acc
+        && !(0.0..=100.0).contains(&acc)
+    {
+        return Err(AppError::BadRequest(
+            "acc must be between 0 and 100".to_string(),
+        ));
+    }
+
+    let mods = parse_mods(params.mods.as_deref())?;
+    let map = load_beatmap(&state, id).await?;
+    let attrs = difficulty::attributes(&state, &map, mods).await?;
+
+    let score = ScoreInput {
+        accuracy: params.acc,
+        combo: params.combo,
+        misses: params.misses,
+    };
+    let perf = difficulty::performance(attrs, mods, score);
+
+    let (pp_aim, pp_speed, pp_accuracy, pp_flashlight) = match &perf {
+        PerformanceAttributes::Osu(p) => (
+            Some(p.pp_aim),
+            Some(p.pp_speed),
+            Some(p.pp_acc),
+            Some(p.pp_flashlight),
+        ),
+        _ => (None, None, None, None),
+    };
+
+    Ok(Json(BeatmapPerformanceV2 {
+        beatmap_id: map.id,
+        mode: map.mode.clone(),
+        mods,
+        accuracy: params.acc,
+        combo: params.combo,
+        misses: params.misses,
+        pp: perf.pp(),
+        pp_aim,
+        pp_speed,
+        pp_accuracy,
+        pp_flashlight,
+        star_rating: perf.stars(),
+        max_combo: perf.max_combo(),
+    }))
+}
+
+fn parse_mods(mods: Option<&str>) -> Result<Mods> {
+    match mods.filter(|m| !m.is_empty()) {
+        Some(m) => {
+            Mods::parse(m).ok_or_else(|| AppError::BadRequest(format!("invalid mods '{}'", m)))
+        }
+        None => Ok(Mods::NONE),
+    }
+}
+
+/// A beatmap by id, fetching its set from osu! on a local miss.
+pub async fn load_beatmap(state: &AppState, id: i64) -> Result<Beatmap> {
+    let mut set_id = queries::get_beatmap_set_id(&state.db, id).await?;
+
+    if set_id.is_none() {
+        tracing::info!("Beatmap {} not found locally → fetching from osu! API", id);
+        let lookup = BeatmapLookup {
+            id: Some(id),
+            ..Default::default()
+        };
+        match state.osu_client.lookup_beatmap(&lookup).await {
+            Ok(Some(m)) => {
+                match crawler::sync::resync_beatmapset(
+                    &state.db,
+                    &state.osu_client,
+                    m.beatmapset_id,
+                )
+                .await
+                {
+                    Ok(_) => set_id = Some(m.beatmapset_id),
+                    Err(e) => tracing::warn!(
+                        "Failed to fetch beatmapset {} from API: {}",
+                        m.beatmapset_id,
+                        e
+                    ),
+                }
+            }
+            Ok(None) => {}
+            Err(e) => tracing::warn!("Failed to look up beatmap {} from API: {}", id, e),
+        }
+    }
+
+    let set = match set_id {
+        Some(set_id) => queries::get_beatmapset(&state.db, set_id).await?,
+        None => None,
+    };
+    set.and_then(|s| s.beatmaps?.into_iter().find(|b| b.id == id))
+        .ok_or_else(|| AppError::NotFound(format!("Beatmap {} not found", id)))
+}
@@ -0,0 +1,362 @@
+use axum::{
+    Json,
+    extract::{Path, Query, RawQuery, State},
+};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    AppState,
+    api::params::{bool_param, parse_bool_param},
+    crawler::{self, client::BeatmapLookup},
+    db::{models::Beatmapset, queries},
+    error::{AppError, Result},
+};
+
+use super::{
+    attributes::load_beatmap,
+    mapping::{BeatmapExtendedV2, BeatmapsResponseV2, map_beatmap_v2},
+    search::SearchV2Params,
+};
+
+/// Same cap as osu!'s `/beatmaps` endpoint.
+const MAX_BATCH_IDS: usize = 50;
+
+/// Most beatmaps `/beatmaps/random` and `/beatmaps/{id}/similar` return.
+const MAX_PICKS: i64 = 50;
+
+#[derive(Deserialize)]
+pub struct BeatmapV2Params {
+    #[serde(default, deserialize_with = "bool_param")]
+    include_deleted: bool,
+}
+
+#[derive(Deserialize)]
+pub struct RandomParams {
+    #[serde(default = "default_random_count")]
+    count: i64,
+}
+
+#[derive(Deserialize)]
+pub struct SimilarParams {
+    #[serde(default = "default_similar_count")]
+    count: i64,
+}
+
+fn default_random_count() -> i64 {
+    1
+}
+
+fn default_similar_count() -> i64 {
+    10
+}
+
+#[derive(Deserialize)]
+pub struct BeatmapLookupParams {
+    #[serde(default)]
+    checksum: Option<String>,
+    #[serde(default)]
+    filename: Option<String>,
+    #[serde(default)]
+    id: Option<i64>,
+    #[serde(default, deserialize_with = "bool_param")]
+    include_deleted: bool,
+}
+
+pub async fn get_beatmap_v2(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Query(params): Query<BeatmapV2Params>,
+) -> Result<Json<Option<BeatmapExtendedV2>>> {
+    let lookup = BeatmapLookup {
+        id: Some(id),
+        ..Default::default()
+    };
+    resolve(&state, &lookup, params.include_deleted)
+        .await
+        .map(Json)
+}
+
+pub async fn get_beatmap_by_md5_v2(
+    State(state): State<AppState>,
+    Path(md5): Path<String>,
+    Query(params): Query<BeatmapV2Params>,
+) -> Result<Json<Option<BeatmapExtendedV2>>> {
+    let lookup = BeatmapLookup {
+        checksum: Some(&md5),
+        ..Default::default()
+    };
+    resolve(&state, &lookup, params.include_deleted)
+        .await
+        .map(Json)
+}
+
+/// Looks a beatmap up by the first of `checksum`, `filename` and `id` that is given.
+pub async fn lookup_beatmap_v2(
+    State(state): State<AppState>,
+    Query(params): Query<BeatmapLookupParams>,
+) -> Result<Json<Option<BeatmapExtendedV2>>> {
+    if params.checksum.is_none() && params.filename.is_none() && params.id.is_none() {
+        return Err(AppError::BadRequest(
+            "one of checksum, filename or id is required".to_string(),
+        ));
+    }
+
+    let lookup = BeatmapLookup {
+        checksum: params.checksum.as_deref(),
+        filename: params.filename.as_deref(),
+        id: params.id,
+    };
+    resolve(&state, &lookup, params.include_deleted)
+        .await
+        .map(Json)
+}
+
+/// `/beatmaps?ids[]=1&ids[]=2`. Unknown ids are left out, in the order given.
+pub async fn get_beatmaps_v2(
+    State(state): State<AppState>,
+    RawQuery(query): RawQuery,
+) -> Result<Json<BeatmapsResponseV2>> {
+    let mut ids = Vec::new();
+    let mut include_deleted = false;
+
+    for pair in query.as_deref().unwrap_or_default().split('&') {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let key = urlencoding::decode(key).map_err(|_| bad_query())?;
+        let value = urlencoding::decode(value).map_err(|_| bad_query())?;
+        match key.as_ref() {
+            "ids[]" | "ids" => {
+                let id = value.parse::<i64>().map_err(|_| bad_query())?;
+                if !ids.contains(&id) {
+                    ids.push(id);
+                }
+            }
+            "include_deleted" => {
+                include_deleted = parse_bool_param(&value).ok_or_else(|| {
+                    AppError::BadRequest("include_deleted must be a boolean".to_string())
+                })?;
+            }
+            _ => {}
+        }
+    }
+
+    if ids.len() > MAX_BATCH_IDS {
+        return Err(AppError::BadRequest(format!(
+            "at most {} ids can be requested at once",
+            MAX_BATCH_IDS
+        )));
+    }
+
+    let mut set_ids: HashMap<i64, i64> = queries::get_beatmap_set_ids(&state.db, &ids)
+        .await?
+        .into_iter()
+        .collect();
+
+    let unknown: Vec<i64> = ids
+        .iter()
+        .copied()
+        .filter(|id| !set_ids.contains_key(id))
+        .collect();
+    if !unknown.is_empty() {
+        tracing::info!(
+            "{} beatmaps not found locally → fetching from osu! API",
+            unknown.len()
+        );
+        match state.osu_client.get_beatmaps(&unknown).await {
+            Ok(maps) => {
+                let mut fetched = HashSet::new();
+                for map in maps {
+                    if fetched.insert(map.beatmapset_id)
+                        && let Err(e) = crawler::sync::resync_beatmapset(
+                            &state.db,
+                            &state.osu_client,
+                            map.beatmapset_id,
+                        )
+                        .await
+                    {
+                        tracing::warn!(
+                            "Failed to fetch beatmapset {} from API: {}",
+                            map.beatmapset_id,
+                            e
+                        );
+                        continue;
+                    }
+                    set_ids.insert(map.id, map.beatmapset_id);
+                }
+            }
+            Err(e) => tracing::warn!("Failed to fetch beatmaps from API: {}", e),
+        }
+    }
+
+    let ids: Vec<(i64, i64)> = ids
+        .into_iter()
+        .filter_map(|id| Some((id, *set_ids.get(&id)?)))
+        .collect();
+    load_many(&state, &ids, include_deleted).await.map(Json)
+}
+
+/// `/beatmaps/random?q=stars=5-6 length<180&s=ranked&m=0&count=3`: random
+/// difficulties matching the same filters as `/search`.
+pub async fn get_random_beatmaps_v2(
+    State(state): State<AppState>,
+    Query(search): Query<SearchV2Params>,
+    Query(params): Query<RandomParams>,
+) -> Result<Json<BeatmapsResponseV2>> {
+    let filter = search.to_filter()?;
+    let ids = queries::get_random_beatmap_ids(&state.db, &filter, params.count.clamp(1, MAX_PICKS))
+        .await?;
+    load_many(&state, &ids, search.include_deleted)
+        .await
+        .map(Json)
+}
+
+/// Difficulties like this one, closest first. Takes the `/search` filters to
+/// narrow them down, e.g. `s=ranked`; the mode is always the beatmap's own.
+pub async fn get_similar_beatmaps_v2(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Query(search): Query<SearchV2Params>,
+    Query(params): Query<SimilarParams>,
+) -> Result<Json<BeatmapsResponseV2>> {
+    let mut filter = search.to_filter()?;
+    filter.mode = None;
+    let map = load_beatmap(&state, id).await?;
+    let ids = queries::get_similar_beatmap_ids(
+        &state.db,
+        &map,
+        &filter,
+        params.count.clamp(1, MAX_PICKS),
+    )
+    .await?;
+    load_many(&state, &ids, search.include_deleted)
+        .await
+        .map(Json)
+}
+
+fn bad_query() -> AppError {
+    AppError::BadRequest("ids[] must be integers".to_string())
+}
+
+async fn resolve(
+    state: &AppState,
+    lookup: &BeatmapLookup<'_>,
+    include_deleted: bool,
+) -> Result<Option<BeatmapExtendedV2>> {
+    let mut ids = find_local(state, lookup).await?;
+
+    if ids.is_none() {
+        tracing::info!(
+            "Beatmap {:?} not found locally → fetching from osu! API",
+            lookup
+        );
+
+        let api_map = match state.osu_client.lookup_beatmap(lookup).await {
+            Ok(Some(m)) => m,
+            Ok(None) => return Ok(None),
+            Err(e) => {
+                tracing::warn!("Failed to look up beatmap {:?} from API: {}", lookup, e);
+                return Ok(None);
+            }
+        };
+
+        if let Err(e) =
+            crawler::sync::resync_beatmapset(&state.db, &state.osu_client, api_map.beatmapset_id)
+                .await
+        {
+            tracing::error!(
+                "Failed to upsert beatmapset {} into DB: {}",
+                api_map.beatmapset_id,
+                e
+            );
+            return Ok(None);
+        }
+
+        ids = Some((api_map.id, api_map.beatmapset_id));
+    }
+
+    match ids {
+        Some((id, set_id)) => load(state, id, set_id, include_deleted).await,
+        None => Ok(None),
+    }
+}
+
+async fn find_local(state: &AppState, lookup: &BeatmapLookup<'_>) -> Result<Option<(i64, i64)>> {
+    if let Some(checksum) = lookup.checksum {
+        return queries::get_beatmap_ids_by_checksum(&state.db, checksum).await;
+    }
+
+    if let Some(filename) = lookup.filename {
+        let Some((artist_title, creator, version)) = parse_osu_filename(filename) else {
+            return Ok(None);
+        };
+        return queries::get_beatmap_ids_by_filename(&state.db, artist_title, creator, version)
+            .await;
+    }
+
+    if let Some(id) = lookup.id {
+        let set_id = queries::get_beatmap_set_id(&state.db, id).await?;
+        return Ok(set_id.map(|s| (id, s)));
+    }
+
+    Ok(None)
+}
+
+/// Splits `{artist} - {title} ({creator}) [{version}].osu` into
+/// `("{artist} - {title}", creator, version)`.
+fn parse_osu_filename(filename: &str) -> Option<(&str, &str, &str)> {
+    let stem = filename.strip_suffix(".osu").unwrap_or(filename);
+    let stem = stem.strip_suffix(']')?;
+    let (rest, version) = stem.rsplit_once(" [")?;
+    let rest = rest.strip_suffix(')')?;
+    let (artist_title, creator) = rest.rsplit_once(" (")?;
+    Some((artist_title, creator, version))
+}
+
+/// `(beatmap_id, beatmapset_id)` pairs as beatmaps, in the order given.
+async fn load_many(
+    state: &AppState,
+    ids: &[(i64, i64)],
+    include_deleted: bool,
+) -> Result<BeatmapsResponseV2> {
+    let mut unique_set_ids: Vec<i64> = ids.iter().map(|(_, set_id)| *set_id).collect();
+    unique_set_ids.sort_unstable();
+    unique_set_ids.dedup();
+    let sets: HashMap<i64, Beatmapset> = queries::get_beatmapsets(&state.db, &unique_set_ids)
+        .await?
+        .into_iter()
+        .map(|s| (s.id, s))
+        .collect();
+
+    let beatmaps = ids
+        .iter()
+        .filter_map(|(id, set_id)| to_beatmap_v2(sets.get(set_id)?.clone(), *id, include_deleted))
+        .collect();
+
+    Ok(BeatmapsResponseV2 { beatmaps })
+}
+
+async fn load(
+    state: &AppState,
+    id: i64,
+    set_id: i64,
+    include_deleted: bool,
+) -> Result<Option<BeatmapExtendedV2>> {
+    let set = queries::get_beatmapset(&state.db, set_id).await?;
+    Ok(set.and_then(|set| to_beatmap_v2(set, id, include_deleted)))
+}
+
+fn to_beatmap_v2(set: Beatmapset, id: i64, include_deleted: bool) -> Option<BeatmapExtendedV2> {
+    if !include_deleted {
+        let map_deleted = set
+            .beatmaps
+            .as_deref()
+            .unwrap_or_default()
+            .iter()
+            .any(|b| b.id == id && b.deleted);
+        if set.deleted || map_deleted {
+            return None;
+        }
+    }
+
+    map_beatmap_v2(set, id)
+}
@@ -1,15 +1,23 @@
 use axum::{
     Json,
-    extract::{Path, State},
+    extract::{Path, Query, State},
 };
+use serde::Deserialize;
 
-use crate::{AppState, db::queries, error::Result};
+use crate::{AppState, api::params::bool_param, db::queries, error::Result};
 
 use super::mapping::{BeatmapsetV2, map_set_v2};
 
+#[derive(Deserialize)]
+pub struct BeatmapsetV2Params {
+    #[serde(default, deserialize_with = "bool_param")]
+    include_deleted: bool,
+}
+
 pub async fn get_beatmapset_v2(
     State(state): State<AppState>,
     Path(id): Path<i64>,
+    Query(params): Query<BeatmapsetV2Params>,
 ) -> Result<Json<Option<BeatmapsetV2>>> {
     let mut set = queries::get_beatmapset(&state.db, id).await?;
 
@@ -20,7 +28,8 @@ pub async fn get_beatmapset_v2(
         );
 
         let api_set = match state.osu_client.get_beatmapset(id).await {
-            Ok(s) => s,
+            Ok(Some(s)) => s,
+            Ok(None) => return Ok(Json(None)),
             Err(e) => {
                 tracing::warn!("Failed to fetch beatmapset {} from API: {}", id, e);
                 return Ok(Json(None));
@@ -35,9 +44,16 @@ pub async fn get_beatmapset_v2(
         set = queries::get_beatmapset(&state.db, id).await?;
     }
 
-    let Some(set) = set else {
+    let Some(mut set) = set else {
         return Ok(Json(None));
     };
 
+    if !params.include_deleted {
+        if set.deleted {
+            return Ok(Json(None));
+        }
+        set.retain_live_beatmaps();
+    }
+
     Ok(Json(Some(map_set_v2(set))))
 }
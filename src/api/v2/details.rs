@@ -0,0 +1,34 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+};
+
+use super::attributes::load_beatmap;
+use crate::{
+    AppState,
+    db::{
+        models::{BeatmapDetails, OsuFileRef},
+        queries,
+    },
+    difficulty,
+    error::Result,
+};
+
+/// `/beatmaps/{id}/details`: what the .osu file has beyond the osu! API, parsed
+/// on first request unless the background job got to it already.
+pub async fn get_beatmap_details_v2(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<BeatmapDetails>> {
+    if let Some(details) = queries::get_beatmap_details(&state.db, id).await? {
+        return Ok(Json(details));
+    }
+
+    let map = load_beatmap(&state, id).await?;
+    let file = difficulty::load_osu_file(&state, &OsuFileRef::from(&map)).await?;
+    let details = difficulty::parse_details(file.into(), map.id, map.checksum.clone()).await?;
+    if let Err(e) = queries::save_beatmap_details(&state.db, &details).await {
+        tracing::warn!("Failed to save details of beatmap {}: {}", map.id, e);
+    }
+    Ok(Json(details))
+}
@@ -0,0 +1,83 @@
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    AppState,
+    db::{
+        models::{BeatmapChange, BeatmapSnapshot, BeatmapsetHistory},
+        queries,
+    },
+    error::Result,
+};
+
+#[derive(Deserialize)]
+pub struct HistoryParams {
+    #[serde(default = "default_limit")]
+    limit: i64,
+    /// Only return entries older than this history id.
+    #[serde(default)]
+    before: Option<i64>,
+}
+
+fn default_limit() -> i64 {
+    50
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldChangeV2<T> {
+    pub from: Option<T>,
+    pub to: Option<T>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryEntryV2 {
+    pub id: i64,
+    pub beatmapset_id: i64,
+    pub recorded_at: DateTime<Utc>,
+    pub changes: Vec<String>,
+    pub status: FieldChangeV2<String>,
+    pub ranked_date: FieldChangeV2<DateTime<Utc>>,
+    pub last_updated: FieldChangeV2<DateTime<Utc>>,
+    pub beatmap_changes: Vec<BeatmapChange>,
+    /// Difficulties as they were before this change.
+    pub previous_beatmaps: Vec<BeatmapSnapshot>,
+}
+
+fn map_history(h: BeatmapsetHistory) -> HistoryEntryV2 {
+    HistoryEntryV2 {
+        id: h.id,
+        beatmapset_id: h.beatmapset_id,
+        recorded_at: h.recorded_at,
+        changes: h.changes,
+        status: FieldChangeV2 {
+            from: h.previous_status,
+            to: Some(h.status),
+        },
+        ranked_date: FieldChangeV2 {
+            from: h.previous_ranked_date,
+            to: h.ranked_date,
+        },
+        last_updated: FieldChangeV2 {
+            from: h.previous_last_updated,
+            to: h.last_updated,
+        },
+        beatmap_changes: h.beatmap_changes,
+        previous_beatmaps: h.beatmaps,
+    }
+}
+
+pub async fn get_beatmapset_history_v2(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Query(params): Query<HistoryParams>,
+) -> Result<Json<Vec<HistoryEntryV2>>> {
+    let entries =
+        queries::get_beatmapset_history(&state.db, id, params.before, params.limit.clamp(1, 100))
+            .await?;
+
+    Ok(Json(entries.into_iter().map(map_history).collect()))
+}
@@ -1,7 +1,7 @@
 use chrono::{DateTime, Utc};
 use serde::Serialize;
 
-use crate::db::models::{Beatmap, Beatmapset};
+use crate::db::models::{Beatmap, BeatmapPack, Beatmapset, RankStatus, User};
 
 const OSU_PREVIEW_BASE: &str = "//b.ppy.sh/preview";
 const OSU_ASSETS_BASE_URL: &str = "https://assets.ppy.sh/beatmaps";
@@ -39,6 +39,19 @@ pub struct BeatmapV2 {
     pub max_combo: i32,
 }
 
+/// A difficulty with its set embedded, as osu!'s `/beatmaps` endpoints return it.
+#[derive(Debug, Clone, Serialize)]
+pub struct BeatmapExtendedV2 {
+    #[serde(flatten)]
+    pub beatmap: BeatmapV2,
+    pub beatmapset: BeatmapsetV2,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BeatmapsResponseV2 {
+    pub beatmaps: Vec<BeatmapExtendedV2>,
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct CoversV2 {
     pub cover: String,
@@ -103,17 +116,63 @@ pub struct BeatmapsetV2 {
     pub availability: AvailabilityV2,
     pub beatmaps: Vec<BeatmapV2>,
     pub pack_tags: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<UserCompactV2>,
 }
 
 #[derive(Debug, Clone, Serialize)]
-pub struct SearchMetaV2 {
-    pub sort: String,
+pub struct CountryV2 {
+    pub code: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UserCompactV2 {
+    pub avatar_url: Option<String>,
+    pub country_code: Option<String>,
+    pub id: i64,
+    pub is_deleted: bool,
+    pub username: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UserV2 {
+    #[serde(flatten)]
+    pub compact: UserCompactV2,
+    pub country: Option<CountryV2>,
+    pub previous_usernames: Vec<String>,
+    pub ranked_beatmapset_count: i32,
+    pub loved_beatmapset_count: i32,
+    pub pending_beatmapset_count: i32,
+    pub graveyard_beatmapset_count: i32,
+    pub guest_beatmapset_count: i32,
+    pub last_synced_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BeatmapPackV2 {
+    pub author: Option<String>,
+    pub date: Option<DateTime<Utc>>,
+    pub name: String,
+    pub no_diff_reduction: bool,
+    pub ruleset_id: Option<i32>,
+    pub tag: String,
+    #[serde(rename = "type")]
+    pub pack_type: String,
+    pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub beatmapsets: Option<Vec<BeatmapsetV2>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BeatmapPacksResponseV2 {
+    pub beatmap_packs: Vec<BeatmapPackV2>,
+    pub cursor_string: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize)]
-pub struct CursorV2 {
-    pub approved_date: Option<i64>,
-    pub id: Option<i64>,
+pub struct SearchMetaV2 {
+    pub sort: String,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -123,22 +182,13 @@ pub struct SearchResponseV2 {
     pub recommended_difficulty: Option<f64>,
     pub error: Option<String>,
     pub total: i64,
-    pub cursor: Option<CursorV2>,
+    pub cursor: Option<serde_json::Value>,
     #[serde(rename = "cursor_string")]
     pub cursor_string: Option<String>,
 }
 
 fn status_to_ranked_int(status: &str) -> i32 {
-    match status {
-        "graveyard" => -2,
-        "wip" => -1,
-        "pending" => 0,
-        "ranked" => 1,
-        "approved" => 2,
-        "qualified" => 3,
-        "loved" => 4,
-        _ => 0,
-    }
+    RankStatus::parse(status).map_or(0, |s| s.as_int())
 }
 
 fn normalize_status(status: &str) -> String {
@@ -193,7 +243,7 @@ fn map_diff(set: &Beatmapset, map: &Beatmap) -> BeatmapV2 {
         count_sliders: map.count_sliders.unwrap_or(0),
         count_spinners: map.count_spinners.unwrap_or(0),
         cs: map.cs.unwrap_or(0.0),
-        deleted_at: None,
+        deleted_at: map.deleted_at,
         drain: map.drain.unwrap_or(0.0),
         hit_length,
         is_scoreable: is_scoreable(&set.status),
@@ -255,7 +305,7 @@ pub fn map_set_v2(set: Beatmapset) -> BeatmapsetV2 {
         video: set.video,
         bpm,
         can_be_hyped: false,
-        deleted_at: None,
+        deleted_at: set.deleted_at,
         discussion_enabled: false,
         discussion_locked: false,
         is_scoreable: is_scoreable(&set.status),
@@ -270,6 +320,66 @@ pub fn map_set_v2(set: Beatmapset) -> BeatmapsetV2 {
         tags: set.tags,
         availability,
         beatmaps: beatmaps_vec,
-        pack_tags: Vec::new(),
+        pack_tags: set.pack_tags,
+        user: set.user.as_ref().map(map_user_compact),
+    }
+}
+
+/// `None` when the set doesn't contain the beatmap. The embedded set leaves its
+/// difficulties out, like osu! does.
+pub fn map_beatmap_v2(mut set: Beatmapset, beatmap_id: i64) -> Option<BeatmapExtendedV2> {
+    let map = set
+        .beatmaps
+        .as_ref()?
+        .iter()
+        .find(|b| b.id == beatmap_id)?
+        .clone();
+    let beatmap = map_diff(&set, &map);
+    set.beatmaps = None;
+
+    Some(BeatmapExtendedV2 {
+        beatmap,
+        beatmapset: map_set_v2(set),
+    })
+}
+
+pub fn map_user_compact(user: &User) -> UserCompactV2 {
+    UserCompactV2 {
+        avatar_url: user.avatar_url.clone(),
+        country_code: user.country_code.clone(),
+        id: user.id,
+        is_deleted: !user.available,
+        username: user.username.clone(),
+    }
+}
+
+pub fn map_user_v2(user: User) -> UserV2 {
+    UserV2 {
+        compact: map_user_compact(&user),
+        country: user
+            .country_code
+            .zip(user.country_name)
+            .map(|(code, name)| CountryV2 { code, name }),
+        previous_usernames: user.previous_usernames,
+        ranked_beatmapset_count: user.ranked_beatmapset_count,
+        loved_beatmapset_count: user.loved_beatmapset_count,
+        pending_beatmapset_count: user.pending_beatmapset_count,
+        graveyard_beatmapset_count: user.graveyard_beatmapset_count,
+        guest_beatmapset_count: user.guest_beatmapset_count,
+        last_synced_at: user.fetched_at,
+    }
+}
+
+pub fn map_pack_v2(pack: BeatmapPack) -> BeatmapPackV2 {
+    BeatmapPackV2 {
+        author: pack.author,
+        date: pack.date,
+        name: pack.name,
+        no_diff_reduction: pack.no_diff_reduction,
+        ruleset_id: pack.ruleset_id,
+        tag: pack.tag,
+        pack_type: pack.pack_type,
+        url: pack.url,
+        beatmapsets: None,
     }
 }
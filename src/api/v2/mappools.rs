@@ -0,0 +1,259 @@
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::header,
+    response::IntoResponse,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::{
+    AppState,
+    db::{
+        models::{Beatmapset, Mappool, MappoolSlot},
+        queries,
+    },
+    error::{AppError, Result},
+    mods::{Difficulty, Mods},
+};
+
+#[derive(Deserialize)]
+pub struct MappoolsParams {
+    #[serde(default)]
+    tournament: Option<String>,
+    #[serde(default)]
+    round: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct MappoolV2 {
+    pub id: i64,
+    pub tournament: String,
+    pub round: String,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// `/d/bulk` URL for every set in the pool; absent while none are known.
+    pub download_url: Option<String>,
+    pub slots: Vec<MappoolSlotV2>,
+}
+
+/// One row of a pool sheet. Difficulty values are after the slot's mods;
+/// beatmap fields are absent when the beatmap isn't stored yet.
+#[derive(Serialize)]
+pub struct MappoolSlotV2 {
+    pub slot: String,
+    pub mods: Mods,
+    pub beatmap_id: i64,
+    pub beatmapset_id: Option<i64>,
+    pub artist: Option<String>,
+    pub title: Option<String>,
+    pub version: Option<String>,
+    pub creator: Option<String>,
+    pub star_rating: Option<f64>,
+    /// Seconds.
+    pub length: Option<i32>,
+    pub bpm: Option<f64>,
+    pub cs: Option<f64>,
+    pub ar: Option<f64>,
+    pub od: Option<f64>,
+    pub hp: Option<f64>,
+    pub max_combo: Option<i32>,
+}
+
+/// Pools without their slots, optionally narrowed to a tournament and round.
+pub async fn get_mappools_v2(
+    State(state): State<AppState>,
+    Query(params): Query<MappoolsParams>,
+) -> Result<Json<Vec<Mappool>>> {
+    Ok(Json(
+        queries::list_mappools(
+            &state.db,
+            params.tournament.as_deref(),
+            params.round.as_deref(),
+        )
+        .await?,
+    ))
+}
+
+pub async fn get_mappool_v2(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<Option<MappoolV2>>> {
+    load_mappool(&state, id).await.map(Json)
+}
+
+/// The pool sheet as CSV, one row per slot.
+pub async fn get_mappool_csv_v2(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse> {
+    let Some(pool) = load_mappool(&state, id).await? else {
+        return Err(AppError::NotFound(format!("Mappool {} not found", id)));
+    };
+
+    let mut out = String::from(
+        "slot,mods,beatmap_id,beatmapset_id,artist,title,version,creator,star_rating,length,bpm,cs,ar,od,hp,max_combo\n",
+    );
+    for s in &pool.slots {
+        let fields = [
+            csv_field(&s.slot),
+            s.mods.acronyms(),
+            s.beatmap_id.to_string(),
+            opt(s.beatmapset_id),
+            csv_field(s.artist.as_deref().unwrap_or_default()),
+            csv_field(s.title.as_deref().unwrap_or_default()),
+            csv_field(s.version.as_deref().unwrap_or_default()),
+            csv_field(s.creator.as_deref().unwrap_or_default()),
+            opt(s.star_rating.map(|v| format!("{:.2}", v))),
+            opt(s.length),
+            opt(s.bpm.map(|v| format!("{:.0}", v))),
+            opt(s.cs.map(|v| format!("{:.1}", v))),
+            opt(s.ar.map(|v| format!("{:.1}", v))),
+            opt(s.od.map(|v| format!("{:.1}", v))),
+            opt(s.hp.map(|v| format!("{:.1}", v))),
+            opt(s.max_combo),
+        ];
+        out.push_str(&fields.join(","));
+        out.push('\n');
+    }
+
+    let filename = format!("{} {} {}.csv", pool.tournament, pool.round, pool.name)
+        .replace(['/', '\\', ':', '*', '?', '"', '<', '>', '|'], "_");
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!(r#"attachment; filename="{}""#, filename),
+            ),
+        ],
+        out,
+    ))
+}
+
+fn opt<T: ToString>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// A pool with its sheet, from the stored beatmaps.
+pub async fn load_mappool(state: &AppState, id: i64) -> Result<Option<MappoolV2>> {
+    let Some(pool) = queries::get_mappool(&state.db, id).await? else {
+        return Ok(None);
+    };
+    let slots = queries::get_mappool_slots(&state.db, id).await?;
+
+    let beatmap_ids: Vec<i64> = slots.iter().map(|s| s.beatmap_id).collect();
+    let set_ids: HashMap<i64, i64> = queries::get_beatmap_set_ids(&state.db, &beatmap_ids)
+        .await?
+        .into_iter()
+        .collect();
+
+    let mut unique_set_ids: Vec<i64> = Vec::new();
+    for id in &beatmap_ids {
+        if let Some(set_id) = set_ids.get(id)
+            && !unique_set_ids.contains(set_id)
+        {
+            unique_set_ids.push(*set_id);
+        }
+    }
+    let sets: HashMap<i64, Beatmapset> = queries::get_beatmapsets(&state.db, &unique_set_ids)
+        .await?
+        .into_iter()
+        .map(|s| (s.id, s))
+        .collect();
+
+    let slots = slots
+        .into_iter()
+        .map(|slot| {
+            let set = set_ids.get(&slot.beatmap_id).and_then(|id| sets.get(id));
+            sheet_row(slot, set)
+        })
+        .collect();
+
+    let download_url = (!unique_set_ids.is_empty()).then(|| {
+        let ids: Vec<String> = unique_set_ids.iter().map(i64::to_string).collect();
+        format!("/d/bulk?ids={}", ids.join(","))
+    });
+
+    Ok(Some(MappoolV2 {
+        id: pool.id,
+        tournament: pool.tournament,
+        round: pool.round,
+        name: pool.name,
+        created_at: pool.created_at,
+        updated_at: pool.updated_at,
+        download_url,
+        slots,
+    }))
+}
+
+fn sheet_row(slot: MappoolSlot, set: Option<&Beatmapset>) -> MappoolSlotV2 {
+    let mods = Mods::from_bits(slot.mods);
+    let map = set.and_then(|s| {
+        s.beatmaps
+            .as_deref()?
+            .iter()
+            .find(|b| b.id == slot.beatmap_id)
+    });
+
+    let mut row = MappoolSlotV2 {
+        slot: slot.slot,
+        mods,
+        beatmap_id: slot.beatmap_id,
+        beatmapset_id: set.map(|s| s.id),
+        artist: set.map(|s| s.artist.clone()),
+        title: set.map(|s| s.title.clone()),
+        version: None,
+        creator: set.map(|s| s.creator.clone()),
+        star_rating: None,
+        length: None,
+        bpm: None,
+        cs: None,
+        ar: None,
+        od: None,
+        hp: None,
+        max_combo: None,
+    };
+    let Some(map) = map else {
+        return row;
+    };
+
+    let rate = mods.clock_rate();
+    let difficulty = mods.apply(Difficulty {
+        cs: map.cs.unwrap_or(0.0),
+        ar: map.ar.unwrap_or(0.0),
+        od: map.accuracy.unwrap_or(0.0),
+        hp: map.drain.unwrap_or(0.0),
+    });
+
+    row.version = Some(map.version.clone());
+    row.star_rating = if mods.changes_star_rating() {
+        slot.star_rating
+    } else {
+        map.difficulty_rating
+    };
+    row.length = map
+        .total_length
+        .map(|l| (f64::from(l) / rate).round() as i32);
+    row.bpm = map.bpm.map(|b| b * rate);
+    row.cs = map.cs.map(|_| round2(difficulty.cs));
+    row.ar = map.ar.map(|_| round2(difficulty.ar));
+    row.od = map.accuracy.map(|_| round2(difficulty.od));
+    row.hp = map.drain.map(|_| round2(difficulty.hp));
+    row.max_combo = map.max_combo;
+    row
+}
+
+fn round2(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
@@ -1,4 +1,12 @@
+pub mod attributes;
+pub mod beatmaps;
 pub mod beatmapset;
+pub mod details;
+pub mod history;
 pub mod mapping;
+pub mod mappools;
+pub mod packs;
 pub mod routes;
 pub mod search;
+pub mod sync;
+pub mod users;
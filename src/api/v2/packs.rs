@@ -0,0 +1,126 @@
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    AppState,
+    crawler::{self, sync::PACK_TYPES},
+    db::queries,
+    error::{AppError, Result},
+};
+
+use super::mapping::{BeatmapPackV2, BeatmapPacksResponseV2, map_pack_v2, map_set_v2};
+
+#[derive(Deserialize)]
+pub struct BeatmapPacksParams {
+    /// One of `PACK_TYPES`; all types when omitted.
+    #[serde(default, rename = "type")]
+    pack_type: Option<String>,
+    #[serde(default)]
+    cursor_string: Option<String>,
+    #[serde(default = "default_limit")]
+    limit: i64,
+}
+
+fn default_limit() -> i64 {
+    50
+}
+
+/// Position of the last pack of a page, handed out base64-encoded.
+#[derive(Serialize, Deserialize)]
+struct PackCursor {
+    date: Option<DateTime<Utc>>,
+    tag: String,
+}
+
+fn encode_cursor(cursor: &PackCursor) -> String {
+    URL_SAFE_NO_PAD.encode(serde_json::to_vec(cursor).unwrap_or_default())
+}
+
+fn decode_cursor(s: &str) -> Option<PackCursor> {
+    let bytes = URL_SAFE_NO_PAD.decode(s).ok()?;
+    serde_json::from_slice(&bytes).ok()
+}
+
+pub async fn get_beatmap_packs_v2(
+    State(state): State<AppState>,
+    Query(params): Query<BeatmapPacksParams>,
+) -> Result<Json<BeatmapPacksResponseV2>> {
+    if let Some(t) = params.pack_type.as_deref()
+        && !PACK_TYPES.contains(&t)
+    {
+        return Err(AppError::BadRequest(format!(
+            "unknown pack type '{}', expected one of: {}",
+            t,
+            PACK_TYPES.join(", ")
+        )));
+    }
+
+    let after = match params.cursor_string.as_deref().filter(|c| !c.is_empty()) {
+        Some(c) => {
+            let cursor = decode_cursor(c)
+                .ok_or_else(|| AppError::BadRequest("invalid cursor_string".to_string()))?;
+            Some((cursor.date, cursor.tag))
+        }
+        None => None,
+    };
+
+    let limit = params.limit.clamp(1, 100);
+    let packs =
+        queries::list_beatmap_packs(&state.db, params.pack_type.as_deref(), after, limit).await?;
+
+    let cursor_string = if packs.len() as i64 == limit {
+        packs.last().map(|p| {
+            encode_cursor(&PackCursor {
+                date: p.date,
+                tag: p.tag.clone(),
+            })
+        })
+    } else {
+        None
+    };
+
+    Ok(Json(BeatmapPacksResponseV2 {
+        beatmap_packs: packs.into_iter().map(map_pack_v2).collect(),
+        cursor_string,
+    }))
+}
+
+pub async fn get_beatmap_pack_v2(
+    State(state): State<AppState>,
+    Path(tag): Path<String>,
+) -> Result<Json<Option<BeatmapPackV2>>> {
+    let mut pack = queries::get_beatmap_pack(&state.db, &tag).await?;
+
+    if pack.as_ref().is_none_or(|p| p.sets_synced_at.is_none()) {
+        tracing::info!("Pack {} not known locally → fetching from osu! API", tag);
+
+        match crawler::sync::fetch_beatmap_pack(&state.db, &state.osu_client, &tag).await {
+            Ok(true) => pack = queries::get_beatmap_pack(&state.db, &tag).await?,
+            Ok(false) => {}
+            Err(e) => tracing::warn!("Failed to fetch pack {} from API: {}", tag, e),
+        }
+    }
+
+    let Some(pack) = pack else {
+        return Ok(Json(None));
+    };
+
+    let ids = queries::get_beatmap_pack_set_ids(&state.db, &pack.tag).await?;
+    let sets = queries::get_beatmapsets(&state.db, &ids)
+        .await?
+        .into_iter()
+        .map(|mut set| {
+            set.retain_live_beatmaps();
+            map_set_v2(set)
+        })
+        .collect();
+
+    let mut pack = map_pack_v2(pack);
+    pack.beatmapsets = Some(sets);
+    Ok(Json(Some(pack)))
+}
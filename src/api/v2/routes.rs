@@ -2,12 +2,49 @@ use axum::{Router, routing::get};
 
 use crate::AppState;
 
-use super::{beatmapset, search};
+use super::{
+    attributes, beatmaps, beatmapset, details, history, mappools, packs, search, sync, users,
+};
 
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/search", get(search::search_v2))
-        // .route("/beatmaps/{id}", get(beatmaps::get_beatmaps_v2))
-        // .route("/beatmaps/md5/{md5}", get(beatmaps::get_beatmaps_by_md5_v2))
+        .route("/beatmaps/packs", get(packs::get_beatmap_packs_v2))
+        .route("/beatmaps/packs/{tag}", get(packs::get_beatmap_pack_v2))
+        .route("/beatmaps", get(beatmaps::get_beatmaps_v2))
+        .route("/beatmaps/lookup", get(beatmaps::lookup_beatmap_v2))
+        .route("/beatmaps/random", get(beatmaps::get_random_beatmaps_v2))
+        .route("/beatmaps/{id}", get(beatmaps::get_beatmap_v2))
+        .route(
+            "/beatmaps/{id}/attributes",
+            get(attributes::get_beatmap_attributes_v2),
+        )
+        .route("/beatmaps/{id}/pp", get(attributes::get_beatmap_pp_v2))
+        .route(
+            "/beatmaps/{id}/details",
+            get(details::get_beatmap_details_v2),
+        )
+        .route(
+            "/beatmaps/{id}/similar",
+            get(beatmaps::get_similar_beatmaps_v2),
+        )
+        .route("/beatmaps/md5/{md5}", get(beatmaps::get_beatmap_by_md5_v2))
         .route("/beatmapsets/{id}", get(beatmapset::get_beatmapset_v2))
+        .route(
+            "/beatmapsets/{id}/history",
+            get(history::get_beatmapset_history_v2),
+        )
+        .route("/users/{id}", get(users::get_user_v2))
+        .route(
+            "/users/{id}/beatmapsets",
+            get(users::get_user_beatmapsets_v2),
+        )
+        .route("/mappools", get(mappools::get_mappools_v2))
+        .route("/mappools/{id}", get(mappools::get_mappool_v2))
+        .route(
+            "/mappools/{id}/sheet.csv",
+            get(mappools::get_mappool_csv_v2),
+        )
+        .route("/sync/runs", get(sync::get_sync_runs_v2))
+        .route("/sync/runs/latest", get(sync::get_latest_sync_runs_v2))
 }
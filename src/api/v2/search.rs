@@ -2,10 +2,24 @@ use axum::{
     Json,
     extract::{Query, State},
 };
+use base64::{
+    Engine,
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+};
 use serde::Deserialize;
 
 use super::mapping::{SearchMetaV2, SearchResponseV2, map_set_v2};
-use crate::{AppState, db::queries, error::Result};
+use crate::{
+    AppState,
+    api::params::bool_param,
+    db::{
+        queries,
+        search::{SearchFilter, SearchSort},
+    },
+    difficulty::PRECOMPUTED_MODS,
+    error::{AppError, Result},
+    mods::Mods,
+};
 
 #[derive(Deserialize)]
 pub struct SearchV2Params {
@@ -17,6 +31,72 @@ pub struct SearchV2Params {
     pub limit: i64,
     #[serde(default)]
     pub offset: i64,
+    #[serde(default, deserialize_with = "bool_param")]
+    pub include_deleted: bool,
+    /// Mode: 0 osu, 1 taiko, 2 catch, 3 mania.
+    #[serde(default)]
+    pub m: Option<i32>,
+    /// Status category as on the website (`any`, `leaderboard`, `ranked`, ...).
+    #[serde(default)]
+    pub s: Option<String>,
+    #[serde(default)]
+    pub g: Option<i32>,
+    #[serde(default)]
+    pub l: Option<i32>,
+    #[serde(default)]
+    pub nsfw: Option<bool>,
+    /// Extras, e.g. `video.storyboard`.
+    #[serde(default)]
+    pub e: Option<String>,
+    /// `{field}_{asc|desc}`; relevance with keywords, ranked date without.
+    #[serde(default)]
+    pub sort: Option<String>,
+    /// Opaque position returned by the previous page. Takes precedence over `offset`.
+    #[serde(default)]
+    pub cursor_string: Option<String>,
+    /// Filters `stars` on the star rating under these mods, e.g. `DT`.
+    #[serde(default)]
+    pub mods: Option<String>,
+}
+
+impl SearchV2Params {
+    pub fn to_filter(&self) -> Result<SearchFilter> {
+        let mut filter = SearchFilter::parse(&self.query)?;
+        filter.include_deleted = self.include_deleted;
+
+        if let Some(status) = self.status.as_deref() {
+            filter.add_status(status)?;
+        }
+        if let Some(category) = self.s.as_deref() {
+            filter.add_status_category(category)?;
+        }
+        if let Some(m) = self.m {
+            if !(0..=3).contains(&m) {
+                return Err(AppError::BadRequest(format!("unknown mode {}", m)));
+            }
+            filter.mode = Some(m);
+        }
+        filter.genre = self.g;
+        filter.language = self.l;
+        filter.exclude_nsfw = self.nsfw == Some(false);
+        if let Some(e) = self.e.as_deref() {
+            filter.set_extra(e)?;
+        }
+        if let Some(m) = self.mods.as_deref().filter(|m| !m.is_empty()) {
+            let mods = Mods::parse(m)
+                .map(Mods::difficulty_mods)
+                .filter(|mods| PRECOMPUTED_MODS.contains(mods))
+                .ok_or_else(|| {
+                    let supported: Vec<String> =
+                        PRECOMPUTED_MODS.iter().map(|m| m.acronyms()).collect();
+                    AppError::BadRequest(format!("mods must be one of {}", supported.join(", ")))
+                })?;
+            // The nomod rating is osu!'s own.
+            filter.star_mods = (mods != Mods::NONE).then(|| mods.bits());
+        }
+
+        Ok(filter)
+    }
 }
 
 fn default_limit() -> i64 {
@@ -27,32 +107,41 @@ pub async fn search_v2(
     State(state): State<AppState>,
     Query(params): Query<SearchV2Params>,
 ) -> Result<Json<SearchResponseV2>> {
-    let total =
-        queries::count_beatmapsets(&state.db, &params.query, params.status.as_deref()).await?;
+    let filter = params.to_filter()?;
+    let sort = params.sort.as_deref().map(SearchSort::parse).transpose()?;
+    let sort = SearchSort::resolve(sort, &filter);
+    let cursor = params
+        .cursor_string
+        .as_deref()
+        .filter(|c| !c.is_empty())
+        .map(decode_cursor)
+        .transpose()?;
 
-    let beatmapsets = queries::search_beatmapsets(
+    let total = queries::count_beatmapsets(&state.db, &filter).await?;
+
+    let page = queries::search_beatmapsets(
         &state.db,
-        &params.query,
-        params.status.as_deref(),
-        params.limit.min(100),
+        &filter,
+        &sort,
+        cursor.as_ref(),
+        params.limit.clamp(1, 100),
         params.offset,
     )
     .await?;
 
-    let mut mapped_sets = Vec::new();
-
-    for s in beatmapsets {
-        if let Some(full) = queries::get_beatmapset(&state.db, s.id).await? {
-            mapped_sets.push(map_set_v2(full));
-            if mapped_sets.len() as i64 >= params.limit {
-                break;
+    let mapped_sets = page
+        .sets
+        .into_iter()
+        .map(|mut set| {
+            if !params.include_deleted {
+                set.retain_live_beatmaps();
             }
-        }
-    }
+            map_set_v2(set)
+        })
+        .collect();
 
-    let search_meta = SearchMetaV2 {
-        sort: "ranked_desc".to_string(),
-    };
+    let search_meta = SearchMetaV2 { sort: sort.name() };
+    let cursor_string = page.next_cursor.as_ref().map(encode_cursor);
 
     let response = SearchResponseV2 {
         beatmapsets: mapped_sets,
@@ -60,9 +149,27 @@ pub async fn search_v2(
         recommended_difficulty: None,
         error: None,
         total,
-        cursor: None,
-        cursor_string: None,
+        cursor: page.next_cursor,
+        cursor_string,
     };
 
     Ok(Json(response))
 }
+
+/// osu! hands out base64 JSON; clients send it back unchanged.
+fn encode_cursor(cursor: &serde_json::Value) -> String {
+    STANDARD.encode(cursor.to_string())
+}
+
+fn decode_cursor(s: &str) -> Result<serde_json::Value> {
+    let invalid = || AppError::BadRequest("invalid cursor_string".to_string());
+    let bytes = STANDARD
+        .decode(s)
+        .or_else(|_| URL_SAFE_NO_PAD.decode(s.trim_end_matches('=')))
+        .map_err(|_| invalid())?;
+    let value: serde_json::Value = serde_json::from_slice(&bytes).map_err(|_| invalid())?;
+    if !value.is_object() {
+        return Err(invalid());
+    }
+    Ok(value)
+}
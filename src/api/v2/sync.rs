@@ -0,0 +1,53 @@
+use axum::{
+    Json,
+    extract::{Query, State},
+};
+use serde::Deserialize;
+
+use crate::{
+    AppState,
+    db::{models::SyncRun, queries},
+    error::Result,
+};
+
+#[derive(Deserialize)]
+pub struct SyncRunParams {
+    /// Only runs of this worker, e.g. `ranked_sync`.
+    #[serde(default)]
+    worker: Option<String>,
+    /// Only runs that failed or had per-set errors.
+    #[serde(default)]
+    failed: bool,
+    /// Only runs older than this run id.
+    #[serde(default)]
+    before: Option<i64>,
+    #[serde(default = "default_limit")]
+    limit: i64,
+}
+
+fn default_limit() -> i64 {
+    50
+}
+
+pub async fn get_sync_runs_v2(
+    State(state): State<AppState>,
+    Query(params): Query<SyncRunParams>,
+) -> Result<Json<Vec<SyncRun>>> {
+    let runs = queries::get_sync_runs(
+        &state.db,
+        params.worker.as_deref(),
+        params.failed,
+        params.before,
+        params.limit.clamp(1, 200),
+    )
+    .await?;
+
+    Ok(Json(runs))
+}
+
+/// The latest successful run of every worker.
+pub async fn get_latest_sync_runs_v2(State(state): State<AppState>) -> Result<Json<Vec<SyncRun>>> {
+    Ok(Json(
+        queries::get_latest_successful_sync_runs(&state.db).await?,
+    ))
+}
@@ -0,0 +1,93 @@
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+};
+use serde::Deserialize;
+
+use crate::{
+    AppState,
+    api::params::bool_param,
+    crawler,
+    db::{models::RankStatus, queries},
+    error::{AppError, Result},
+};
+
+use super::mapping::{BeatmapsetV2, UserV2, map_set_v2, map_user_v2};
+
+#[derive(Deserialize)]
+pub struct UserBeatmapsetsParams {
+    #[serde(default)]
+    status: Option<String>,
+    #[serde(default = "default_limit")]
+    limit: i64,
+    #[serde(default)]
+    offset: i64,
+    #[serde(default, deserialize_with = "bool_param")]
+    include_deleted: bool,
+}
+
+fn default_limit() -> i64 {
+    50
+}
+
+pub async fn get_user_v2(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<Option<UserV2>>> {
+    let mut user = queries::get_user(&state.db, id).await?;
+
+    if user.is_none() {
+        tracing::info!("User {} not found locally → fetching from osu! API", id);
+
+        match crawler::sync::store_user(&state.db, &state.osu_client, id).await {
+            Ok(Some(_)) => {}
+            Ok(None) => return Ok(Json(None)),
+            Err(e) => {
+                tracing::warn!("Failed to fetch user {} from API: {}", id, e);
+                return Ok(Json(None));
+            }
+        }
+
+        user = queries::get_user(&state.db, id).await?;
+    }
+
+    Ok(Json(user.map(map_user_v2)))
+}
+
+pub async fn get_user_beatmapsets_v2(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Query(params): Query<UserBeatmapsetsParams>,
+) -> Result<Json<Vec<BeatmapsetV2>>> {
+    let status = params
+        .status
+        .as_deref()
+        .map(|s| {
+            RankStatus::parse(s)
+                .ok_or_else(|| AppError::BadRequest(format!("unknown status '{}'", s)))
+        })
+        .transpose()?;
+
+    let ids = queries::get_user_beatmapsets(
+        &state.db,
+        id,
+        status,
+        params.include_deleted,
+        params.limit.clamp(1, 100),
+        params.offset.max(0),
+    )
+    .await?;
+
+    let sets = queries::get_beatmapsets(&state.db, &ids)
+        .await?
+        .into_iter()
+        .map(|mut set| {
+            if !params.include_deleted {
+                set.retain_live_beatmaps();
+            }
+            map_set_v2(set)
+        })
+        .collect();
+
+    Ok(Json(sets))
+}
@@ -12,6 +12,16 @@ pub struct Config {
     pub crawler: CrawlerConfig,
     #[serde(default)]
     pub rate_limit: RateLimitConfig,
+    #[serde(default)]
+    pub admin: AdminConfig,
+    #[serde(default)]
+    pub legacy_api: LegacyApiConfig,
+    #[serde(default)]
+    pub compat: CompatConfig,
+    #[serde(default)]
+    pub difficulty: DifficultyConfig,
+    #[serde(default)]
+    pub webhooks: Vec<WebhookConfig>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -72,6 +82,21 @@ pub struct CrawlerConfig {
     pub enabled: bool,
     #[serde(default = "default_sync_interval")]
     pub sync_interval_seconds: u64,
+    #[serde(default = "default_revalidate_batch_size")]
+    pub revalidate_batch_size: i64,
+    /// Poll interval of the osu! beatmapset events feed. 0 turns it off and
+    /// falls back to polling `sort=updated_desc` every 30 seconds.
+    #[serde(default = "default_events_interval")]
+    pub events_interval_seconds: u64,
+    /// Mapper profiles fetched per users cycle. 0 turns user crawling off.
+    #[serde(default = "default_users_batch_size")]
+    pub users_batch_size: i64,
+    /// Pack set lists fetched per packs cycle. 0 turns pack crawling off.
+    #[serde(default = "default_packs_batch_size")]
+    pub packs_batch_size: i64,
+    /// Sync runs older than this are deleted. 0 keeps every run.
+    #[serde(default)]
+    pub sync_runs_retention_days: u32,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, Default)]
@@ -82,6 +107,70 @@ pub struct RateLimitConfig {
     pub downloads_per_10min: u32,
 }
 
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct AdminConfig {
+    /// Bearer token for `/admin`. The admin API is disabled when unset.
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct LegacyApiConfig {
+    /// Keys accepted as `k` by `/api/get_beatmaps`. When empty, `k` is ignored.
+    #[serde(default)]
+    pub keys: Vec<String>,
+}
+
+/// Route groups that mimic other mirrors' APIs, all off by default.
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct CompatConfig {
+    /// Nerinyan: `GET`/`POST /search`.
+    #[serde(default)]
+    pub nerinyan: bool,
+    /// catboy.best (Mino): `/api/v2/s/{id}`, `/api/v2/b/{id}`, `/api/v2/md5/{md5}`
+    /// and `/api/v2/search`.
+    #[serde(default)]
+    pub catboy: bool,
+    /// Chimu's cheesegull-style `/api/v1/set/{id}`, `/api/v1/map/{id}` and
+    /// `/api/v1/search`.
+    #[serde(default)]
+    pub chimu: bool,
+}
+
+/// Precomputing per-mod attributes and .osu details of cached ranked and
+/// loved difficulties.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DifficultyConfig {
+    /// Difficulties calculated per cycle. 0 turns precomputing off.
+    #[serde(default = "default_difficulty_batch_size")]
+    pub batch_size: i64,
+    #[serde(default = "default_difficulty_interval")]
+    pub interval_seconds: u64,
+}
+
+impl Default for DifficultyConfig {
+    fn default() -> Self {
+        Self {
+            batch_size: default_difficulty_batch_size(),
+            interval_seconds: default_difficulty_interval(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct WebhookConfig {
+    pub url: String,
+    #[serde(default)]
+    pub secret: Option<String>,
+    #[serde(default = "default_webhook_events")]
+    pub events: Vec<String>,
+    /// osu, taiko, fruits, mania or 0-3. Empty means every mode.
+    #[serde(default)]
+    pub modes: Vec<String>,
+    #[serde(default = "default_webhook_format")]
+    pub format: String,
+}
+
 fn default_server() -> ServerConfig {
     ServerConfig {
         port: 8080,
@@ -109,6 +198,33 @@ fn default_true() -> bool {
 fn default_sync_interval() -> u64 {
     300
 }
+fn default_revalidate_batch_size() -> i64 {
+    10
+}
+fn default_events_interval() -> u64 {
+    15
+}
+fn default_users_batch_size() -> i64 {
+    10
+}
+fn default_packs_batch_size() -> i64 {
+    20
+}
+fn default_difficulty_batch_size() -> i64 {
+    50
+}
+fn default_difficulty_interval() -> u64 {
+    60
+}
+pub fn default_webhook_events() -> Vec<String> {
+    ["ranked", "loved", "qualified", "updated"]
+        .into_iter()
+        .map(String::from)
+        .collect()
+}
+pub fn default_webhook_format() -> String {
+    "json".to_string()
+}
 fn default_requests_per_minute() -> u32 {
     200
 }
@@ -159,6 +275,11 @@ impl Default for Config {
             },
             crawler: CrawlerConfig::default(),
             rate_limit: RateLimitConfig::default(),
+            admin: AdminConfig::default(),
+            legacy_api: LegacyApiConfig::default(),
+            compat: CompatConfig::default(),
+            difficulty: DifficultyConfig::default(),
+            webhooks: Vec::new(),
         }
     }
 }
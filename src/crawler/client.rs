@@ -65,6 +65,104 @@ pub struct ApiBeatmapset {
     pub beatmaps: Option<Vec<ApiBeatmap>>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct ApiUser {
+    pub id: i64,
+    pub username: String,
+    #[serde(default)]
+    pub previous_usernames: Vec<String>,
+    pub country_code: Option<String>,
+    #[serde(default)]
+    pub country: Option<ApiCountry>,
+    pub avatar_url: Option<String>,
+    #[serde(default)]
+    pub ranked_beatmapset_count: i32,
+    #[serde(default)]
+    pub loved_beatmapset_count: i32,
+    #[serde(default)]
+    pub pending_beatmapset_count: i32,
+    #[serde(default)]
+    pub graveyard_beatmapset_count: i32,
+    #[serde(default)]
+    pub guest_beatmapset_count: i32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApiCountry {
+    pub code: String,
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BeatmapsetEventsResponse {
+    pub events: Vec<ApiBeatmapsetEvent>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApiBeatmapsetEvent {
+    pub id: i64,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    /// Missing when the set has since been deleted.
+    #[serde(default)]
+    pub beatmapset: Option<ApiEventBeatmapset>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApiEventBeatmapset {
+    pub id: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BeatmapsResponse {
+    pub beatmaps: Vec<ApiBeatmap>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BeatmapAttributesResponse {
+    attributes: BeatmapAttributes,
+}
+
+#[derive(Debug, Deserialize)]
+struct BeatmapAttributes {
+    star_rating: f64,
+}
+
+/// What `/beatmaps/lookup` can search by. osu! uses the first one that's set.
+#[derive(Debug, Default)]
+pub struct BeatmapLookup<'a> {
+    pub checksum: Option<&'a str>,
+    pub filename: Option<&'a str>,
+    pub id: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BeatmapPacksResponse {
+    pub beatmap_packs: Vec<ApiBeatmapPack>,
+    pub cursor_string: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApiBeatmapPack {
+    pub tag: String,
+    pub name: String,
+    pub author: Option<String>,
+    pub date: Option<chrono::DateTime<chrono::Utc>>,
+    pub url: Option<String>,
+    pub ruleset_id: Option<i32>,
+    #[serde(default)]
+    pub no_diff_reduction: bool,
+    /// Only present on the single-pack endpoint.
+    #[serde(default)]
+    pub beatmapsets: Option<Vec<ApiPackBeatmapset>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApiPackBeatmapset {
+    pub id: i64,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Availability {
     pub download_disabled: bool,
@@ -144,10 +242,10 @@ impl OsuClient {
     pub async fn ensure_token(&self) -> Result<String> {
         {
             let t = self.token.read().await;
-            if let Some((tok, exp)) = t.as_ref() {
-                if Instant::now() < *exp {
-                    return Ok(tok.clone());
-                }
+            if let Some((tok, exp)) = t.as_ref()
+                && Instant::now() < *exp
+            {
+                return Ok(tok.clone());
             }
         }
 
@@ -188,7 +286,8 @@ impl OsuClient {
         Ok(resp.json().await?)
     }
 
-    pub async fn get_beatmapset(&self, id: i64) -> Result<ApiBeatmapset> {
+    /// Returns `None` when osu! reports the set as missing (deleted or never existed).
+    pub async fn get_beatmapset(&self, id: i64) -> Result<Option<ApiBeatmapset>> {
         let url = format!("https://osu.ppy.sh/api/v2/beatmapsets/{}", id);
         let token = self.ensure_token().await?;
         let _p = RATE_LIMIT.acquire().await.unwrap();
@@ -200,10 +299,218 @@ impl OsuClient {
             .send()
             .await?;
 
+        if resp.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
         if !resp.status().is_success() {
             anyhow::bail!("Get beatmapset failed: {}", resp.status());
         }
 
+        Ok(Some(resp.json().await?))
+    }
+
+    /// One page of `/beatmapsets/events`, newest first.
+    pub async fn get_beatmapset_events(
+        &self,
+        types: &[&str],
+        page: u32,
+        limit: u32,
+    ) -> Result<Vec<ApiBeatmapsetEvent>> {
+        let mut url = format!(
+            "https://osu.ppy.sh/api/v2/beatmapsets/events?sort=id_desc&page={}&limit={}",
+            page, limit
+        );
+        for t in types {
+            url.push_str(&format!("&types[]={}", urlencoding::encode(t)));
+        }
+
+        let token = self.ensure_token().await?;
+        let _p = RATE_LIMIT.acquire().await.unwrap();
+
+        let resp = self
+            .client
+            .get(&url)
+            .header("Authorization", format!("Bearer {}", token))
+            .send()
+            .await?;
+
+        if !resp.status().is_success() {
+            anyhow::bail!("Get beatmapset events failed: {}", resp.status());
+        }
+
+        let body: BeatmapsetEventsResponse = resp.json().await?;
+        Ok(body.events)
+    }
+
+    /// Full user profile. Returns `None` for restricted or deleted accounts.
+    pub async fn get_user(&self, id: i64) -> Result<Option<ApiUser>> {
+        let url = format!("https://osu.ppy.sh/api/v2/users/{}?key=id", id);
+        let token = self.ensure_token().await?;
+        let _p = RATE_LIMIT.acquire().await.unwrap();
+
+        let resp = self
+            .client
+            .get(&url)
+            .header("Authorization", format!("Bearer {}", token))
+            .send()
+            .await?;
+
+        if resp.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        if !resp.status().is_success() {
+            anyhow::bail!("Get user failed: {}", resp.status());
+        }
+
+        Ok(Some(resp.json().await?))
+    }
+
+    /// One page of `/beatmaps/packs` for a pack type, newest first.
+    pub async fn get_beatmap_packs(
+        &self,
+        pack_type: &str,
+        cursor: Option<&str>,
+    ) -> Result<BeatmapPacksResponse> {
+        let mut url = format!(
+            "https://osu.ppy.sh/api/v2/beatmaps/packs?type={}",
+            urlencoding::encode(pack_type)
+        );
+        if let Some(c) = cursor {
+            url.push_str(&format!("&cursor_string={}", urlencoding::encode(c)));
+        }
+
+        let token = self.ensure_token().await?;
+        let _p = RATE_LIMIT.acquire().await.unwrap();
+
+        let resp = self
+            .client
+            .get(&url)
+            .header("Authorization", format!("Bearer {}", token))
+            .send()
+            .await?;
+
+        if !resp.status().is_success() {
+            anyhow::bail!("Get beatmap packs failed: {}", resp.status());
+        }
+
         Ok(resp.json().await?)
     }
+
+    /// A single pack including its set list.
+    pub async fn get_beatmap_pack(&self, tag: &str) -> Result<Option<ApiBeatmapPack>> {
+        let url = format!(
+            "https://osu.ppy.sh/api/v2/beatmaps/packs/{}",
+            urlencoding::encode(tag)
+        );
+        let token = self.ensure_token().await?;
+        let _p = RATE_LIMIT.acquire().await.unwrap();
+
+        let resp = self
+            .client
+            .get(&url)
+            .header("Authorization", format!("Bearer {}", token))
+            .send()
+            .await?;
+
+        if resp.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        if !resp.status().is_success() {
+            anyhow::bail!("Get beatmap pack failed: {}", resp.status());
+        }
+
+        Ok(Some(resp.json().await?))
+    }
+
+    /// Returns `None` when no beatmap matches.
+    pub async fn lookup_beatmap(&self, lookup: &BeatmapLookup<'_>) -> Result<Option<ApiBeatmap>> {
+        let mut params = Vec::new();
+        if let Some(checksum) = lookup.checksum {
+            params.push(format!("checksum={}", urlencoding::encode(checksum)));
+        }
+        if let Some(filename) = lookup.filename {
+            params.push(format!("filename={}", urlencoding::encode(filename)));
+        }
+        if let Some(id) = lookup.id {
+            params.push(format!("id={}", id));
+        }
+        let url = format!(
+            "https://osu.ppy.sh/api/v2/beatmaps/lookup?{}",
+            params.join("&")
+        );
+
+        let token = self.ensure_token().await?;
+        let _p = RATE_LIMIT.acquire().await.unwrap();
+
+        let resp = self
+            .client
+            .get(&url)
+            .header("Authorization", format!("Bearer {}", token))
+            .send()
+            .await?;
+
+        if resp.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        if !resp.status().is_success() {
+            anyhow::bail!("Beatmap lookup failed: {}", resp.status());
+        }
+
+        Ok(Some(resp.json().await?))
+    }
+
+    /// Up to 50 beatmaps by id. Unknown ids are left out of the response.
+    pub async fn get_beatmaps(&self, ids: &[i64]) -> Result<Vec<ApiBeatmap>> {
+        let query: Vec<String> = ids.iter().map(|id| format!("ids[]={}", id)).collect();
+        let url = format!("https://osu.ppy.sh/api/v2/beatmaps?{}", query.join("&"));
+
+        let token = self.ensure_token().await?;
+        let _p = RATE_LIMIT.acquire().await.unwrap();
+
+        let resp = self
+            .client
+            .get(&url)
+            .header("Authorization", format!("Bearer {}", token))
+            .send()
+            .await?;
+
+        if !resp.status().is_success() {
+            anyhow::bail!("Get beatmaps failed: {}", resp.status());
+        }
+
+        let body: BeatmapsResponse = resp.json().await?;
+        Ok(body.beatmaps)
+    }
+
+    /// Star rating of a beatmap under a legacy mod bitmask. Returns `None` for
+    /// unknown beatmaps.
+    pub async fn get_beatmap_star_rating(&self, id: i64, mods: i32) -> Result<Option<f64>> {
+        let url = format!("https://osu.ppy.sh/api/v2/beatmaps/{}/attributes", id);
+
+        let token = self.ensure_token().await?;
+        let _p = RATE_LIMIT.acquire().await.unwrap();
+
+        let resp = self
+            .client
+            .post(&url)
+            .header("Authorization", format!("Bearer {}", token))
+            .json(&serde_json::json!({ "mods": mods }))
+            .send()
+            .await?;
+
+        if resp.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        if !resp.status().is_success() {
+            anyhow::bail!("Get beatmap attributes failed: {}", resp.status());
+        }
+
+        let body: BeatmapAttributesResponse = resp.json().await?;
+        Ok(Some(body.attributes.star_rating))
+    }
 }
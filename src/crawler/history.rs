@@ -0,0 +1,202 @@
+use crate::db::models::{
+    Beatmap, BeatmapChange, BeatmapSnapshot, Beatmapset, NewBeatmapsetHistory,
+};
+use serde_json::json;
+use std::collections::HashMap;
+
+/// Compares the stored state of a set with what osu! returned and describes the
+/// change, or returns `None` when nothing we track has moved.
+pub fn diff_beatmapset(
+    prev: &Beatmapset,
+    next: &Beatmapset,
+    next_beatmaps: Option<&[Beatmap]>,
+) -> Option<NewBeatmapsetHistory> {
+    let mut changes = Vec::new();
+
+    if prev.deleted {
+        changes.push("restored".to_string());
+    }
+
+    if prev.status != next.status {
+        changes.push("status".to_string());
+        if prev.status == "qualified" && matches!(next.status.as_str(), "pending" | "wip") {
+            changes.push("disqualified".to_string());
+        }
+    }
+
+    if prev.ranked_date != next.ranked_date {
+        changes.push("ranked_date".to_string());
+    }
+
+    let prev_beatmaps: Vec<&Beatmap> = prev
+        .beatmaps
+        .iter()
+        .flatten()
+        .filter(|m| !m.deleted)
+        .collect();
+
+    let mut beatmap_changes = Vec::new();
+
+    // Search results always carry the full difficulty list; without it we can't tell
+    // removals from omissions, so only the set-level fields are compared.
+    if let Some(next_beatmaps) = next_beatmaps {
+        let prev_by_id: HashMap<i64, &Beatmap> = prev_beatmaps.iter().map(|m| (m.id, *m)).collect();
+
+        for m in next_beatmaps {
+            match prev_by_id.get(&m.id) {
+                Some(old) => diff_beatmap(old, m, &mut beatmap_changes),
+                None => beatmap_changes.push(BeatmapChange {
+                    beatmap_id: m.id,
+                    version: m.version.clone(),
+                    field: "added".to_string(),
+                    from: serde_json::Value::Null,
+                    to: json!(m.checksum),
+                }),
+            }
+        }
+
+        for old in &prev_beatmaps {
+            if !next_beatmaps.iter().any(|m| m.id == old.id) {
+                beatmap_changes.push(BeatmapChange {
+                    beatmap_id: old.id,
+                    version: old.version.clone(),
+                    field: "removed".to_string(),
+                    from: json!(old.checksum),
+                    to: serde_json::Value::Null,
+                });
+            }
+        }
+    }
+
+    for (field, kind) in [
+        ("checksum", "checksum"),
+        ("added", "beatmaps_added"),
+        ("removed", "beatmaps_removed"),
+    ] {
+        if beatmap_changes.iter().any(|c| c.field == field) {
+            changes.push(kind.to_string());
+        }
+    }
+
+    if beatmap_changes
+        .iter()
+        .any(|c| !matches!(c.field.as_str(), "checksum" | "added" | "removed"))
+    {
+        changes.push("difficulty".to_string());
+    }
+
+    if changes.is_empty() {
+        return None;
+    }
+
+    Some(NewBeatmapsetHistory {
+        beatmapset_id: next.id,
+        changes,
+        previous_status: Some(prev.status.clone()),
+        status: next.status.clone(),
+        previous_ranked_date: prev.ranked_date,
+        ranked_date: next.ranked_date,
+        previous_last_updated: prev.last_updated,
+        last_updated: next.last_updated,
+        beatmaps: prev_beatmaps
+            .into_iter()
+            .map(BeatmapSnapshot::from)
+            .collect(),
+        beatmap_changes,
+    })
+}
+
+/// History row for a set the mirror has not seen before.
+pub fn creation_entry(
+    next: &Beatmapset,
+    next_beatmaps: Option<&[Beatmap]>,
+) -> NewBeatmapsetHistory {
+    NewBeatmapsetHistory {
+        beatmapset_id: next.id,
+        changes: vec!["created".to_string()],
+        previous_status: None,
+        status: next.status.clone(),
+        previous_ranked_date: None,
+        ranked_date: next.ranked_date,
+        previous_last_updated: None,
+        last_updated: next.last_updated,
+        beatmaps: Vec::new(),
+        beatmap_changes: next_beatmaps
+            .unwrap_or_default()
+            .iter()
+            .map(|m| BeatmapChange {
+                beatmap_id: m.id,
+                version: m.version.clone(),
+                field: "added".to_string(),
+                from: serde_json::Value::Null,
+                to: json!(m.checksum),
+            })
+            .collect(),
+    }
+}
+
+/// History row for a set that disappeared upstream.
+pub fn deletion_entry(prev: &Beatmapset) -> NewBeatmapsetHistory {
+    NewBeatmapsetHistory {
+        beatmapset_id: prev.id,
+        changes: vec!["deleted".to_string()],
+        previous_status: Some(prev.status.clone()),
+        status: prev.status.clone(),
+        previous_ranked_date: prev.ranked_date,
+        ranked_date: prev.ranked_date,
+        previous_last_updated: prev.last_updated,
+        last_updated: prev.last_updated,
+        beatmaps: prev
+            .beatmaps
+            .iter()
+            .flatten()
+            .filter(|m| !m.deleted)
+            .map(BeatmapSnapshot::from)
+            .collect(),
+        beatmap_changes: Vec::new(),
+    }
+}
+
+fn diff_beatmap(old: &Beatmap, new: &Beatmap, out: &mut Vec<BeatmapChange>) {
+    let before = BeatmapSnapshot::from(old);
+    let after = BeatmapSnapshot::from(new);
+    if before == after {
+        return;
+    }
+
+    let mut push = |field: &str, from: serde_json::Value, to: serde_json::Value| {
+        if from != to {
+            out.push(BeatmapChange {
+                beatmap_id: new.id,
+                version: new.version.clone(),
+                field: field.to_string(),
+                from,
+                to,
+            });
+        }
+    };
+
+    push("version", json!(before.version), json!(after.version));
+    push("checksum", json!(before.checksum), json!(after.checksum));
+    push(
+        "difficulty_rating",
+        json!(before.difficulty_rating),
+        json!(after.difficulty_rating),
+    );
+    push("ar", json!(before.ar), json!(after.ar));
+    push("cs", json!(before.cs), json!(after.cs));
+    push("accuracy", json!(before.accuracy), json!(after.accuracy));
+    push("drain", json!(before.drain), json!(after.drain));
+    push("bpm", json!(before.bpm), json!(after.bpm));
+    push(
+        "total_length",
+        json!(before.total_length),
+        json!(after.total_length),
+    );
+    push(
+        "hit_length",
+        json!(before.hit_length),
+        json!(after.hit_length),
+    );
+    push("max_combo", json!(before.max_combo), json!(after.max_combo));
+}
@@ -1,6 +1,9 @@
 pub mod client;
+pub mod history;
+pub mod registry;
 pub mod scheduler;
 pub mod sync;
 
 pub use client::OsuClient;
+pub use registry::WorkerRegistry;
 pub use scheduler::start_scheduler;
@@ -0,0 +1,121 @@
+use super::sync::SyncStats;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+/// What the admin API shows about a crawler worker.
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkerStatus {
+    pub id: String,
+    pub description: String,
+    pub interval_seconds: u64,
+    pub paused: bool,
+    pub running: bool,
+    pub last_run_at: Option<DateTime<Utc>>,
+    pub last_success_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub last_error_at: Option<DateTime<Utc>>,
+    pub cursor: Option<String>,
+    pub items_synced: u64,
+    pub next_run_at: Option<DateTime<Utc>>,
+}
+
+/// Shared view of the crawler workers. Workers report into it and check it for
+/// pauses; pauses live in memory only and are cleared by a restart.
+#[derive(Debug, Default)]
+pub struct WorkerRegistry {
+    workers: Mutex<BTreeMap<String, WorkerStatus>>,
+}
+
+impl WorkerRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&self, id: &str, description: String, interval_seconds: u64) {
+        self.workers.lock().unwrap().insert(
+            id.to_string(),
+            WorkerStatus {
+                id: id.to_string(),
+                description,
+                interval_seconds,
+                paused: false,
+                running: false,
+                last_run_at: None,
+                last_success_at: None,
+                last_error: None,
+                last_error_at: None,
+                cursor: None,
+                items_synced: 0,
+                next_run_at: None,
+            },
+        );
+    }
+
+    pub fn list(&self) -> Vec<WorkerStatus> {
+        self.workers.lock().unwrap().values().cloned().collect()
+    }
+
+    pub fn get(&self, id: &str) -> Option<WorkerStatus> {
+        self.workers.lock().unwrap().get(id).cloned()
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.workers.lock().unwrap().contains_key(id)
+    }
+
+    /// Returns `false` when no worker has this id.
+    pub fn set_paused(&self, id: &str, paused: bool) -> bool {
+        self.update(id, |w| w.paused = paused)
+    }
+
+    pub fn is_paused(&self, id: &str) -> bool {
+        self.workers
+            .lock()
+            .unwrap()
+            .get(id)
+            .is_some_and(|w| w.paused)
+    }
+
+    pub fn set_cursor(&self, id: &str, cursor: Option<String>) {
+        self.update(id, |w| w.cursor = cursor);
+    }
+
+    pub fn set_next_run(&self, id: &str, at: DateTime<Utc>) {
+        self.update(id, |w| w.next_run_at = Some(at));
+    }
+
+    pub fn run_started(&self, id: &str) {
+        self.update(id, |w| {
+            w.running = true;
+            w.last_run_at = Some(Utc::now());
+        });
+    }
+
+    pub fn run_finished(&self, id: &str, result: &anyhow::Result<SyncStats>) {
+        self.update(id, |w| {
+            w.running = false;
+            match result {
+                Ok(stats) => {
+                    w.items_synced += stats.fetched;
+                    w.last_success_at = Some(Utc::now());
+                }
+                Err(e) => {
+                    w.last_error = Some(e.to_string());
+                    w.last_error_at = Some(Utc::now());
+                }
+            }
+        });
+    }
+
+    fn update(&self, id: &str, f: impl FnOnce(&mut WorkerStatus)) -> bool {
+        match self.workers.lock().unwrap().get_mut(id) {
+            Some(w) => {
+                f(w);
+                true
+            }
+            None => false,
+        }
+    }
+}
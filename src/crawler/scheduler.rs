@@ -1,12 +1,64 @@
 use super::OsuClient;
 use super::client::start_rate_limiter;
-use super::sync::{load_cursor, save_cursor, sync_beatmapsets_page};
+use super::registry::WorkerRegistry;
+use super::sync::{
+    PACK_TYPES, SyncStats, load_cursor, resync_beatmapset, save_cursor, sync_beatmap_pack_sets,
+    sync_beatmap_packs_page, sync_beatmapsets_page, sync_user,
+};
+use crate::config::CrawlerConfig;
+use crate::db::{models::NewSyncRun, queries};
 use anyhow::Result;
 use sqlx::PgPool;
+use std::future::Future;
 use std::sync::Arc;
 use std::time::Duration;
 
-pub async fn start_scheduler(pool: PgPool, client: Arc<OsuClient>, interval_seconds: u64) {
+/// osu! beatmapset event types that change what we store about a set.
+///
+/// `delete` events usually come without their set once it's gone, so most
+/// deletions are still found by the revalidate worker; the ones that do carry
+/// it are refetched like any other event, which marks the set deleted.
+const SET_EVENT_TYPES: &[&str] = &[
+    "nominate",
+    "nomination_reset",
+    "qualify",
+    "disqualify",
+    "rank",
+    "approve",
+    "love",
+    "remove_from_loved",
+    "genre_edit",
+    "language_edit",
+    "nsfw_toggle",
+    "tags_edit",
+    "offset_edit",
+    "beatmap_owner_change",
+    "delete",
+];
+const EVENTS_PAGE_SIZE: u32 = 50;
+/// Upper bound on pages walked per cycle, so a long outage doesn't turn into
+/// one huge burst of requests. Older events are left to the search workers.
+const EVENTS_MAX_PAGES: u32 = 10;
+
+const RESYNC_QUEUE_INTERVAL: u64 = 5;
+const RESYNC_QUEUE_BATCH_SIZE: i64 = 20;
+/// Queue entries that failed this often stay in the table for inspection
+/// but are no longer retried.
+pub const RESYNC_MAX_ATTEMPTS: i32 = 5;
+
+const USERS_SYNC_INTERVAL: u64 = 120;
+/// Profiles older than this are refetched to pick up renames and new maps.
+const USERS_STALE_DAYS: i32 = 7;
+
+const PACKS_SYNC_INTERVAL: u64 = 600;
+
+pub async fn start_scheduler(
+    pool: PgPool,
+    client: Arc<OsuClient>,
+    config: CrawlerConfig,
+    registry: Arc<WorkerRegistry>,
+) {
+    let interval_seconds = config.sync_interval_seconds;
     tracing::info!(
         "Starting sync scheduler (base interval: {}s)",
         interval_seconds
@@ -16,110 +68,470 @@ pub async fn start_scheduler(pool: PgPool, client: Arc<OsuClient>, interval_seco
 
     let pool = Arc::new(pool);
 
-    spawn_worker(
-        pool.clone(),
-        client.clone(),
-        "ranked_sync",
-        "status=ranked",
-        interval_seconds,
-    );
+    let search_workers = [
+        ("ranked_sync", "status=ranked", interval_seconds),
+        ("loved_sync", "status=loved", interval_seconds * 2),
+        ("qualified_sync", "status=qualified", interval_seconds),
+        ("pending_sync", "status=pending", interval_seconds * 2),
+        (
+            "graveyard_sync",
+            "status=graveyard&sort=updated_asc",
+            interval_seconds * 3,
+        ),
+        // The events feed reports status changes within seconds, so the newest-first
+        // search only has to pick up edits that don't produce an event.
+        (
+            "any_updated_desc_sync",
+            "sort=updated_desc",
+            if config.events_interval_seconds > 0 {
+                interval_seconds
+            } else {
+                30
+            },
+        ),
+        (
+            "any_updated_asc_sync",
+            "sort=updated_asc",
+            interval_seconds * 3,
+        ),
+    ];
 
-    spawn_worker(
-        pool.clone(),
-        client.clone(),
-        "loved_sync",
-        "status=loved",
-        interval_seconds * 2,
-    );
+    for (cursor_id, query, interval) in search_workers {
+        let (pool, client) = (pool.clone(), client.clone());
+        spawn_loop(
+            registry.clone(),
+            pool.clone(),
+            cursor_id,
+            format!("search {}", query),
+            interval,
+            2,
+            move || {
+                let (pool, client) = (pool.clone(), client.clone());
+                async move { run_sync_cycle(&pool, &client, cursor_id, query).await }
+            },
+        );
+    }
 
-    spawn_worker(
-        pool.clone(),
-        client.clone(),
-        "qualified_sync",
-        "status=qualified",
-        interval_seconds,
-    );
+    // Walks the least recently refreshed sets and refetches them one by one, so sets
+    // that were deleted upstream (and never show up in search again) get noticed.
+    if config.revalidate_batch_size > 0 {
+        let (pool, client) = (pool.clone(), client.clone());
+        let batch_size = config.revalidate_batch_size;
+        spawn_loop(
+            registry.clone(),
+            pool.clone(),
+            "revalidate",
+            format!("refetch {} least recently updated sets", batch_size),
+            interval_seconds,
+            2,
+            move || {
+                let (pool, client) = (pool.clone(), client.clone());
+                async move { run_revalidate_cycle(&pool, &client, batch_size).await }
+            },
+        );
+    }
 
-    spawn_worker(
-        pool.clone(),
-        client.clone(),
-        "pending_sync",
-        "status=pending",
-        interval_seconds * 2,
-    );
+    // Follows osu!'s beatmapset events feed and refetches only the sets it mentions.
+    // The cursor is the id of the newest event already handled.
+    if config.events_interval_seconds > 0 {
+        let (pool, client) = (pool.clone(), client.clone());
+        spawn_loop(
+            registry.clone(),
+            pool.clone(),
+            "events_sync",
+            "follow /beatmapsets/events".to_string(),
+            config.events_interval_seconds,
+            0,
+            move || {
+                let (pool, client) = (pool.clone(), client.clone());
+                async move { run_events_cycle(&pool, &client).await }
+            },
+        );
+    }
 
-    spawn_worker(
-        pool.clone(),
-        client.clone(),
-        "graveyard_sync",
-        "status=graveyard&sort=updated_asc",
-        interval_seconds * 3,
-    );
+    // Fetches profiles of mappers we have sets of, newest unknown ones first.
+    if config.users_batch_size > 0 {
+        let (pool, client) = (pool.clone(), client.clone());
+        let batch_size = config.users_batch_size;
+        spawn_loop(
+            registry.clone(),
+            pool.clone(),
+            "users_sync",
+            format!("fetch {} mapper profiles", batch_size),
+            USERS_SYNC_INTERVAL,
+            1,
+            move || {
+                let (pool, client) = (pool.clone(), client.clone());
+                async move { run_users_cycle(&pool, &client, batch_size).await }
+            },
+        );
+    }
 
-    spawn_worker(
-        pool.clone(),
-        client.clone(),
-        "any_updated_desc_sync",
-        "sort=updated_desc",
-        30,
-    );
+    // Picks up new packs from the head of every listing, walks one older page per
+    // cycle and fetches set lists for packs that don't have one yet.
+    if config.packs_batch_size > 0 {
+        let (pool, client) = (pool.clone(), client.clone());
+        let batch_size = config.packs_batch_size;
+        spawn_loop(
+            registry.clone(),
+            pool.clone(),
+            "packs_sync",
+            format!("crawl beatmap packs, {} set lists per cycle", batch_size),
+            PACKS_SYNC_INTERVAL,
+            1,
+            move || {
+                let (pool, client) = (pool.clone(), client.clone());
+                async move { run_packs_cycle(&pool, &client, batch_size).await }
+            },
+        );
+    }
 
-    spawn_worker(
-        pool.clone(),
-        client.clone(),
-        "any_updated_asc_sync",
-        "sort=updated_asc",
-        interval_seconds * 3,
-    );
+    {
+        let (pool, client) = (pool.clone(), client.clone());
+        spawn_loop(
+            registry.clone(),
+            pool.clone(),
+            "resync_queue",
+            "refetch sets queued through the admin API".to_string(),
+            RESYNC_QUEUE_INTERVAL,
+            0,
+            move || {
+                let (pool, client) = (pool.clone(), client.clone());
+                async move { run_resync_queue_cycle(&pool, &client).await }
+            },
+        );
+    }
+
+    if config.sync_runs_retention_days > 0 {
+        tokio::spawn(prune_sync_runs(
+            pool.clone(),
+            config.sync_runs_retention_days,
+        ));
+    }
 
     futures::future::pending::<()>().await;
 }
 
-fn spawn_worker(
+async fn prune_sync_runs(pool: Arc<PgPool>, retention_days: u32) {
+    let mut interval = tokio::time::interval(Duration::from_secs(60 * 60));
+    loop {
+        interval.tick().await;
+        match queries::delete_sync_runs_older_than(&pool, i64::from(retention_days)).await {
+            Ok(0) => {}
+            Ok(n) => tracing::info!("Pruned {} old sync runs", n),
+            Err(e) => tracing::error!("Failed to prune sync runs: {}", e),
+        }
+    }
+}
+
+/// Runs `cycle` every `interval_seconds` after skipping `warmup_ticks` ticks,
+/// reporting each run to the registry and to `sync_runs`. Consecutive idle runs,
+/// which fetched nothing and didn't move the cursor, share one row.
+fn spawn_loop<F, Fut>(
+    registry: Arc<WorkerRegistry>,
     pool: Arc<PgPool>,
-    client: Arc<OsuClient>,
-    cursor_id: &'static str,
-    query: &'static str,
+    id: &'static str,
+    description: String,
     interval_seconds: u64,
-) {
+    warmup_ticks: u32,
+    cycle: F,
+) where
+    F: Fn() -> Fut + Send + 'static,
+    Fut: Future<Output = Result<SyncStats>> + Send,
+{
     tracing::info!(
-        "Spawning worker: id={} query={} interval={}s",
-        cursor_id,
-        query,
+        "Spawning worker: id={} ({}) interval={}s",
+        id,
+        description,
         interval_seconds
     );
 
+    registry.register(id, description, interval_seconds);
+
     tokio::spawn(async move {
-        let mut interval = tokio::time::interval(Duration::from_secs(interval_seconds));
+        let period = Duration::from_secs(interval_seconds);
+        let mut interval = tokio::time::interval(period);
 
-        interval.tick().await;
-        interval.tick().await;
+        if let Ok(cursor) = load_cursor(&pool, id).await {
+            registry.set_cursor(id, cursor);
+        }
+        registry.set_next_run(id, chrono::Utc::now() + period * warmup_ticks);
+
+        for _ in 0..warmup_ticks {
+            interval.tick().await;
+        }
 
         loop {
             interval.tick().await;
+            registry.set_next_run(id, chrono::Utc::now() + period);
 
-            if let Err(e) = run_sync_cycle(&pool, &client, cursor_id, query).await {
-                tracing::error!(
-                    "Sync cycle failed: id={} query={} error={}",
-                    cursor_id,
-                    query,
-                    e
-                );
-            } else {
-                tracing::info!("Sync cycle completed: id={} query={}", cursor_id, query);
+            if registry.is_paused(id) {
+                continue;
+            }
+
+            let started_at = chrono::Utc::now();
+            let cursor_before = load_cursor(&pool, id).await.ok().flatten();
+
+            registry.run_started(id);
+            let result = cycle().await;
+            if let Err(e) = &result {
+                tracing::error!("Worker cycle failed: id={} error={}", id, e);
+            }
+            registry.run_finished(id, &result);
+
+            let cursor_after = load_cursor(&pool, id).await.ok().flatten();
+            registry.set_cursor(id, cursor_after.clone());
+
+            let (stats, error) = match &result {
+                Ok(stats) => (*stats, None),
+                Err(e) => (SyncStats::default(), Some(e.to_string())),
+            };
+            if error.is_none() && stats.is_idle() && cursor_before == cursor_after {
+                let recorded = queries::record_idle_sync_run(
+                    &pool,
+                    id,
+                    started_at,
+                    chrono::Utc::now(),
+                    cursor_after.as_deref(),
+                )
+                .await;
+                if let Err(e) = recorded {
+                    tracing::error!("Failed to record sync run: id={} error={}", id, e);
+                }
+                continue;
+            }
+
+            let run = NewSyncRun {
+                worker_id: id.to_string(),
+                started_at,
+                finished_at: chrono::Utc::now(),
+                stats,
+                cursor_before,
+                cursor_after,
+                error,
+            };
+            if let Err(e) = queries::insert_sync_run(&pool, &run).await {
+                tracing::error!("Failed to record sync run: id={} error={}", id, e);
             }
         }
     });
 }
 
+async fn run_revalidate_cycle(
+    pool: &PgPool,
+    client: &OsuClient,
+    batch_size: i64,
+) -> Result<SyncStats> {
+    let ids = queries::get_stale_beatmapset_ids(pool, batch_size).await?;
+    let mut stats = SyncStats::default();
+
+    for id in ids.iter().copied() {
+        let result = resync_beatmapset(pool, client, id).await;
+        if let Err(e) = &result {
+            tracing::warn!("Revalidate of beatmapset {} failed: {}", id, e);
+        }
+        stats.record(&result);
+    }
+
+    tracing::info!(
+        "Revalidate cycle completed: checked={} deleted={}",
+        stats.fetched,
+        stats.deleted
+    );
+    Ok(stats)
+}
+
+async fn run_events_cycle(pool: &PgPool, client: &OsuClient) -> Result<SyncStats> {
+    let last_id = load_cursor(pool, "events_sync")
+        .await?
+        .and_then(|c| c.parse::<i64>().ok());
+
+    let mut fresh = Vec::new();
+    let mut newest = last_id;
+
+    'pages: for page in 1..=EVENTS_MAX_PAGES {
+        let events = client
+            .get_beatmapset_events(SET_EVENT_TYPES, page, EVENTS_PAGE_SIZE)
+            .await?;
+        let full_page = events.len() as u32 == EVENTS_PAGE_SIZE;
+
+        for event in events {
+            if last_id.is_some_and(|last| event.id <= last) {
+                break 'pages;
+            }
+            newest = newest.max(Some(event.id));
+            fresh.push(event);
+        }
+
+        // On the very first run only remember where the feed is; the search
+        // workers already cover everything older.
+        if last_id.is_none() || !full_page {
+            break;
+        }
+    }
+
+    let Some(newest) = newest else {
+        return Ok(SyncStats::default());
+    };
+
+    let mut stats = SyncStats::default();
+
+    if last_id.is_some() {
+        // Oldest first, each set once.
+        let mut ids: Vec<i64> = Vec::new();
+        for event in fresh.iter().rev() {
+            if let Some(set) = &event.beatmapset
+                && !ids.contains(&set.id)
+            {
+                ids.push(set.id);
+            }
+        }
+
+        let mut failed = Vec::new();
+        for id in ids.iter().copied() {
+            let result = resync_beatmapset(pool, client, id).await;
+            if let Err(e) = &result {
+                tracing::warn!("Refetch of beatmapset {} failed: {}", id, e);
+                failed.push(id);
+            }
+            stats.record(&result);
+        }
+
+        // The cursor moves past these events regardless, so hand the sets to
+        // the resync queue instead of losing the change.
+        if !failed.is_empty() {
+            queries::enqueue_resync(pool, &failed).await?;
+        }
+
+        if !fresh.is_empty() {
+            tracing::info!(
+                "Events cycle completed: events={} sets={}",
+                fresh.len(),
+                ids.len()
+            );
+        }
+    }
+
+    save_cursor(pool, "events_sync", Some(newest.to_string())).await?;
+    Ok(stats)
+}
+
+async fn run_resync_queue_cycle(pool: &PgPool, client: &OsuClient) -> Result<SyncStats> {
+    let ids =
+        queries::get_resync_queue_batch(pool, RESYNC_QUEUE_BATCH_SIZE, RESYNC_MAX_ATTEMPTS).await?;
+    let mut stats = SyncStats::default();
+
+    for id in ids {
+        let result = resync_beatmapset(pool, client, id).await;
+        match &result {
+            Ok(_) => queries::remove_from_resync_queue(pool, id).await?,
+            Err(e) => {
+                tracing::warn!("Queued resync of beatmapset {} failed: {}", id, e);
+                queries::record_resync_failure(pool, id, &e.to_string()).await?;
+            }
+        }
+        stats.record(&result);
+    }
+
+    if !stats.is_idle() {
+        tracing::info!(
+            "Resync queue cycle completed: synced={} errors={}",
+            stats.fetched,
+            stats.errors
+        );
+    }
+    Ok(stats)
+}
+
+async fn run_users_cycle(pool: &PgPool, client: &OsuClient, batch_size: i64) -> Result<SyncStats> {
+    let ids = queries::get_user_ids_to_sync(pool, batch_size, USERS_STALE_DAYS).await?;
+    let mut stats = SyncStats::default();
+
+    for id in ids {
+        let result = sync_user(pool, client, id).await;
+        if let Err(e) = &result {
+            tracing::warn!("Fetch of user {} failed: {}", id, e);
+        }
+        stats.record(&result);
+    }
+
+    if !stats.is_idle() {
+        tracing::info!(
+            "Users cycle completed: inserted={} updated={} unavailable={} errors={}",
+            stats.inserted,
+            stats.updated,
+            stats.deleted,
+            stats.errors
+        );
+    }
+    Ok(stats)
+}
+
+/// The backfill cursor is stored as `{pack_type}|{cursor_string}`; when one type's
+/// listing runs out the walk continues with the next type, and starts over after
+/// the last one.
+async fn run_packs_cycle(pool: &PgPool, client: &OsuClient, batch_size: i64) -> Result<SyncStats> {
+    let mut stats = SyncStats::default();
+
+    for pack_type in PACK_TYPES {
+        let (_, page_stats) = sync_beatmap_packs_page(pool, client, pack_type, None).await?;
+        stats.merge(&page_stats);
+    }
+
+    let stored = load_cursor(pool, "packs_sync").await?;
+    let (pack_type, cursor) = match stored.as_deref().and_then(|c| c.split_once('|')) {
+        Some((t, c)) if PACK_TYPES.contains(&t) => {
+            (t.to_string(), Some(c.to_string()).filter(|c| !c.is_empty()))
+        }
+        _ => (PACK_TYPES[0].to_string(), None),
+    };
+    let (next, page_stats) =
+        sync_beatmap_packs_page(pool, client, &pack_type, cursor.as_deref()).await?;
+    stats.merge(&page_stats);
+
+    let next = match next {
+        Some(c) => format!("{}|{}", pack_type, c),
+        None => {
+            let idx = PACK_TYPES.iter().position(|t| *t == pack_type).unwrap_or(0);
+            format!("{}|", PACK_TYPES[(idx + 1) % PACK_TYPES.len()])
+        }
+    };
+    save_cursor(pool, "packs_sync", Some(next)).await?;
+
+    for tag in queries::get_unsynced_beatmap_pack_tags(pool, batch_size).await? {
+        let result = sync_beatmap_pack_sets(pool, client, &tag).await;
+        if let Err(e) = &result {
+            tracing::warn!("Fetch of pack {} failed: {}", tag, e);
+        }
+        stats.record(&result);
+    }
+
+    tracing::info!(
+        "Packs cycle completed: inserted={} updated={} errors={}",
+        stats.inserted,
+        stats.updated,
+        stats.errors
+    );
+    Ok(stats)
+}
+
 async fn run_sync_cycle(
     pool: &PgPool,
     client: &OsuClient,
     cursor_id: &str,
     query: &str,
-) -> Result<()> {
+) -> Result<SyncStats> {
     let cursor = load_cursor(pool, cursor_id).await?;
-    let new_cursor = sync_beatmapsets_page(pool, client, query, cursor).await?;
+    let (new_cursor, stats) = sync_beatmapsets_page(pool, client, query, cursor).await?;
     save_cursor(pool, cursor_id, new_cursor).await?;
-    Ok(())
+    tracing::info!(
+        "Sync cycle completed: id={} query={} inserted={} updated={} unchanged={} errors={}",
+        cursor_id,
+        query,
+        stats.inserted,
+        stats.updated,
+        stats.unchanged,
+        stats.errors
+    );
+    Ok(stats)
 }
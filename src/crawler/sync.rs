@@ -1,15 +1,70 @@
-use super::client::{ApiBeatmap, ApiBeatmapset, OsuClient};
-use crate::db::models::{Beatmap, Beatmapset};
+use super::client::{ApiBeatmap, ApiBeatmapPack, ApiBeatmapset, ApiUser, OsuClient};
+use super::history;
+use crate::db::models::{Beatmap, BeatmapPack, Beatmapset, User};
 use crate::db::queries;
+use crate::events::{self, BeatmapEvent};
 use anyhow::Result;
+use serde::Serialize;
 use sqlx::PgPool;
 
+/// What storing a fetched set did to our copy of it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaveOutcome {
+    Inserted,
+    Updated,
+    Unchanged,
+    /// osu! no longer has the set.
+    Deleted,
+}
+
+/// Counters for one worker cycle, persisted in `sync_runs`.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct SyncStats {
+    pub fetched: u64,
+    pub inserted: u64,
+    pub updated: u64,
+    pub unchanged: u64,
+    pub deleted: u64,
+    pub errors: u64,
+}
+
+impl SyncStats {
+    pub fn record(&mut self, result: &Result<SaveOutcome>) {
+        match result {
+            Ok(outcome) => {
+                self.fetched += 1;
+                match outcome {
+                    SaveOutcome::Inserted => self.inserted += 1,
+                    SaveOutcome::Updated => self.updated += 1,
+                    SaveOutcome::Unchanged => self.unchanged += 1,
+                    SaveOutcome::Deleted => self.deleted += 1,
+                }
+            }
+            Err(_) => self.errors += 1,
+        }
+    }
+
+    pub fn merge(&mut self, other: &SyncStats) {
+        self.fetched += other.fetched;
+        self.inserted += other.inserted;
+        self.updated += other.updated;
+        self.unchanged += other.unchanged;
+        self.deleted += other.deleted;
+        self.errors += other.errors;
+    }
+
+    /// Nothing was fetched and nothing failed.
+    pub fn is_idle(&self) -> bool {
+        self.fetched == 0 && self.errors == 0
+    }
+}
+
 pub async fn sync_beatmapsets_page(
     pool: &PgPool,
     client: &OsuClient,
     query: &str,
     cursor: Option<String>,
-) -> Result<Option<String>> {
+) -> Result<(Option<String>, SyncStats)> {
     tracing::info!("Syncing beatmapsets... query={}", query);
 
     let response = match client.search_beatmapsets(query, cursor.as_deref()).await {
@@ -22,16 +77,19 @@ pub async fn sync_beatmapsets_page(
 
     tracing::info!("Fetched {} beatmapsets", response.beatmapsets.len());
 
+    let mut stats = SyncStats::default();
     for api_beatmapset in response.beatmapsets {
-        if let Err(e) = save_beatmapset(pool, api_beatmapset).await {
+        let result = save_beatmapset(pool, api_beatmapset).await;
+        if let Err(e) = &result {
             tracing::error!("Failed to save beatmapset: {}", e);
         }
+        stats.record(&result);
     }
 
-    Ok(response.cursor_string)
+    Ok((response.cursor_string, stats))
 }
 
-pub async fn save_beatmapset(pool: &PgPool, api_set: ApiBeatmapset) -> Result<()> {
+pub async fn save_beatmapset(pool: &PgPool, api_set: ApiBeatmapset) -> Result<SaveOutcome> {
     let creator_id = api_set.user_id;
 
     let beatmapset = Beatmapset {
@@ -42,7 +100,7 @@ pub async fn save_beatmapset(pool: &PgPool, api_set: ApiBeatmapset) -> Result<()
         artist_unicode: api_set.artist_unicode,
         creator: api_set.creator,
 
-        creator_id: creator_id,
+        creator_id,
         genre_id: api_set.genre_id,
         language_id: api_set.language_id,
         rating: api_set.rating,
@@ -64,23 +122,286 @@ pub async fn save_beatmapset(pool: &PgPool, api_set: ApiBeatmapset) -> Result<()
             .as_ref()
             .map(|a| a.download_disabled)
             .unwrap_or(false),
+        deleted: false,
+        deleted_at: None,
         created_at: chrono::Utc::now(),
         updated_at: chrono::Utc::now(),
         beatmaps: None,
+        user: None,
+        pack_tags: Vec::new(),
+    };
+
+    let beatmaps: Option<Vec<Beatmap>> = api_set
+        .beatmaps
+        .map(|maps| maps.into_iter().map(convert_api_beatmap).collect());
+
+    let previous = queries::get_beatmapset(pool, beatmapset.id).await?;
+    let entry = match &previous {
+        Some(previous) => history::diff_beatmapset(previous, &beatmapset, beatmaps.as_deref()),
+        None => Some(history::creation_entry(&beatmapset, beatmaps.as_deref())),
+    };
+
+    let mut tx = pool.begin().await?;
+    queries::upsert_beatmapset(&mut tx, &beatmapset).await?;
+
+    if let Some(beatmaps) = &beatmaps {
+        let live_ids: Vec<i64> = beatmaps.iter().map(|b| b.id).collect();
+
+        for beatmap in beatmaps {
+            queries::upsert_beatmap(&mut tx, beatmap).await?
+        }
+
+        let removed =
+            queries::mark_missing_beatmaps_deleted(&mut tx, beatmapset.id, &live_ids).await?;
+        if removed > 0 {
+            tracing::info!(
+                "Marked {} beatmaps of set {} as deleted",
+                removed,
+                beatmapset.id
+            );
+        }
+    }
+
+    let outcome = match (&previous, &entry) {
+        (None, _) => SaveOutcome::Inserted,
+        (Some(_), Some(_)) => SaveOutcome::Updated,
+        (Some(_), None) => SaveOutcome::Unchanged,
+    };
+
+    // The set and its history row go in together, or the next crawl would
+    // diff against the stored change and never record it.
+    let history_id = match &entry {
+        Some(entry) => Some(queries::insert_beatmapset_history(&mut tx, entry).await?),
+        None => None,
+    };
+    tx.commit().await?;
+
+    if let (Some(entry), Some(history_id)) = (entry, history_id) {
+        let modes_from = beatmaps
+            .as_deref()
+            .or(previous.as_ref().and_then(|p| p.beatmaps.as_deref()))
+            .unwrap_or_default();
+        events::publish(BeatmapEvent::from_history(
+            history_id,
+            &entry,
+            &beatmapset,
+            modes_from,
+        ));
+    }
+
+    Ok(outcome)
+}
+
+/// Refetches a set from osu! and stores it, or marks it deleted if osu! no longer has it.
+pub async fn resync_beatmapset(pool: &PgPool, client: &OsuClient, id: i64) -> Result<SaveOutcome> {
+    match client.get_beatmapset(id).await? {
+        Some(api_set) => save_beatmapset(pool, api_set).await,
+        None => {
+            let Some(previous) = queries::get_beatmapset(pool, id).await? else {
+                return Ok(SaveOutcome::Deleted);
+            };
+
+            if previous.deleted {
+                return Ok(SaveOutcome::Deleted);
+            }
+
+            let mut tx = pool.begin().await?;
+            if queries::mark_beatmapset_deleted(&mut tx, id).await? {
+                let entry = history::deletion_entry(&previous);
+                let history_id = queries::insert_beatmapset_history(&mut tx, &entry).await?;
+                tx.commit().await?;
+
+                events::publish(BeatmapEvent::from_history(
+                    history_id,
+                    &entry,
+                    &previous,
+                    previous.beatmaps.as_deref().unwrap_or_default(),
+                ));
+                tracing::info!(
+                    "Beatmapset {} no longer exists upstream, marked deleted",
+                    id
+                );
+            }
+            Ok(SaveOutcome::Deleted)
+        }
+    }
+}
+
+/// Fetches a mapper profile and stores it. Restricted or deleted accounts are kept
+/// as unavailable.
+pub async fn sync_user(pool: &PgPool, client: &OsuClient, id: i64) -> Result<SaveOutcome> {
+    match store_user(pool, client, id).await? {
+        Some(outcome) => Ok(outcome),
+        None => {
+            queries::mark_user_unavailable(pool, id).await?;
+            Ok(SaveOutcome::Deleted)
+        }
+    }
+}
+
+/// Fetches a profile and stores it; `None`, with nothing stored, when osu!
+/// doesn't serve the user.
+pub async fn store_user(pool: &PgPool, client: &OsuClient, id: i64) -> Result<Option<SaveOutcome>> {
+    let Some(api_user) = client.get_user(id).await? else {
+        return Ok(None);
     };
 
-    queries::upsert_beatmapset(pool, &beatmapset).await?;
+    let inserted = queries::upsert_user(pool, &convert_api_user(api_user)).await?;
+    Ok(Some(if inserted {
+        SaveOutcome::Inserted
+    } else {
+        SaveOutcome::Updated
+    }))
+}
+
+/// Pack types listed by `/beatmaps/packs`.
+pub const PACK_TYPES: &[&str] = &[
+    "standard",
+    "featured",
+    "tournament",
+    "loved",
+    "chart",
+    "theme",
+    "artist",
+];
 
-    if let Some(api_beatmaps) = api_set.beatmaps {
-        for api_beatmap in api_beatmaps {
-            let beatmap = convert_api_beatmap(api_beatmap);
-            queries::upsert_beatmap(pool, &beatmap).await?
+/// Stores one page of a pack listing and returns the cursor for the next page.
+pub async fn sync_beatmap_packs_page(
+    pool: &PgPool,
+    client: &OsuClient,
+    pack_type: &str,
+    cursor: Option<&str>,
+) -> Result<(Option<String>, SyncStats)> {
+    let resp = client.get_beatmap_packs(pack_type, cursor).await?;
+    let mut stats = SyncStats::default();
+
+    for api_pack in resp.beatmap_packs {
+        let result = save_beatmap_pack(pool, api_pack, pack_type).await;
+        if let Err(e) = &result {
+            tracing::error!("Failed to save beatmap pack: {}", e);
         }
+        stats.record(&result);
     }
 
+    Ok((resp.cursor_string, stats))
+}
+
+async fn save_beatmap_pack(
+    pool: &PgPool,
+    api: ApiBeatmapPack,
+    pack_type: &str,
+) -> Result<SaveOutcome> {
+    let inserted = queries::upsert_beatmap_pack(pool, &convert_api_pack(&api, pack_type)).await?;
+    Ok(if inserted {
+        SaveOutcome::Inserted
+    } else {
+        SaveOutcome::Updated
+    })
+}
+
+/// Fetches a pack's set list and stores it.
+pub async fn sync_beatmap_pack_sets(
+    pool: &PgPool,
+    client: &OsuClient,
+    tag: &str,
+) -> Result<SaveOutcome> {
+    let Some(api) = client.get_beatmap_pack(tag).await? else {
+        // Gone upstream; keep what we have but stop asking for it.
+        queries::mark_beatmap_pack_sets_synced(pool, tag).await?;
+        return Ok(SaveOutcome::Deleted);
+    };
+
+    save_beatmap_pack_sets(pool, &api).await?;
+    Ok(SaveOutcome::Updated)
+}
+
+/// Fetches a pack we have never listed, along with its set list. Returns `false`
+/// when osu! doesn't know the tag either.
+pub async fn fetch_beatmap_pack(pool: &PgPool, client: &OsuClient, tag: &str) -> Result<bool> {
+    let Some(api) = client.get_beatmap_pack(tag).await? else {
+        return Ok(false);
+    };
+
+    queries::upsert_beatmap_pack(pool, &convert_api_pack(&api, pack_type_from_tag(&api.tag)))
+        .await?;
+    save_beatmap_pack_sets(pool, &api).await?;
+    Ok(true)
+}
+
+/// Sets we don't know yet are queued for a resync so the pack archive can
+/// include them.
+async fn save_beatmap_pack_sets(pool: &PgPool, api: &ApiBeatmapPack) -> Result<()> {
+    let set_ids: Vec<i64> = api
+        .beatmapsets
+        .as_deref()
+        .unwrap_or_default()
+        .iter()
+        .map(|s| s.id)
+        .collect();
+
+    queries::replace_beatmap_pack_sets(pool, &api.tag, &set_ids).await?;
+
+    let missing = queries::get_missing_beatmapset_ids(pool, &set_ids).await?;
+    if !missing.is_empty() {
+        queries::enqueue_resync(pool, &missing).await?;
+    }
     Ok(())
 }
 
+/// The single-pack endpoint doesn't say which listing a pack belongs to, so
+/// callers pass the type they already know (or derive it from the tag).
+fn convert_api_pack(api: &ApiBeatmapPack, pack_type: &str) -> BeatmapPack {
+    BeatmapPack {
+        tag: api.tag.clone(),
+        name: api.name.clone(),
+        author: api.author.clone(),
+        pack_type: pack_type.to_string(),
+        date: api.date,
+        url: api.url.clone(),
+        ruleset_id: api.ruleset_id,
+        no_diff_reduction: api.no_diff_reduction,
+        sets_synced_at: None,
+    }
+}
+
+/// Best guess at a pack's type from its tag prefix (`S123`, `F45`, `T12`, ...).
+fn pack_type_from_tag(tag: &str) -> &'static str {
+    match tag.chars().next() {
+        Some('F') => "featured",
+        Some('T') => "tournament",
+        Some('L') => "loved",
+        Some('R') => "chart",
+        Some('P') => "theme",
+        Some('A') => "artist",
+        _ => "standard",
+    }
+}
+
+fn convert_api_user(api: ApiUser) -> User {
+    let (country_code, country_name) = match api.country {
+        Some(c) => (Some(c.code), Some(c.name)),
+        None => (api.country_code, None),
+    };
+
+    User {
+        id: api.id,
+        username: api.username,
+        previous_usernames: api.previous_usernames,
+        country_code,
+        country_name,
+        avatar_url: api.avatar_url,
+        ranked_beatmapset_count: api.ranked_beatmapset_count,
+        loved_beatmapset_count: api.loved_beatmapset_count,
+        pending_beatmapset_count: api.pending_beatmapset_count,
+        graveyard_beatmapset_count: api.graveyard_beatmapset_count,
+        guest_beatmapset_count: api.guest_beatmapset_count,
+        available: true,
+        fetched_at: chrono::Utc::now(),
+        created_at: chrono::Utc::now(),
+        updated_at: chrono::Utc::now(),
+    }
+}
+
 fn convert_api_beatmap(api: ApiBeatmap) -> Beatmap {
     Beatmap {
         id: api.id,
@@ -101,8 +422,12 @@ fn convert_api_beatmap(api: ApiBeatmap) -> Beatmap {
         count_sliders: api.count_sliders,
         count_spinners: api.count_spinners,
         checksum: api.checksum,
+        deleted: false,
+        deleted_at: None,
         created_at: chrono::Utc::now(),
         updated_at: chrono::Utc::now(),
+        aim: None,
+        speed: None,
     }
 }
 
@@ -127,3 +452,11 @@ pub async fn load_cursor(pool: &PgPool, id: &str) -> Result<Option<String>> {
     .await?;
     Ok(record.and_then(|r| r.cursor_string))
 }
+
+/// Drops a stored cursor so the worker starts over from the first page.
+pub async fn reset_cursor(pool: &PgPool, id: &str) -> Result<bool> {
+    let result = sqlx::query!(r#"DELETE FROM sync_cursors WHERE id = $1"#, id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
@@ -1,3 +1,4 @@
 pub mod models;
 pub mod pool;
 pub mod queries;
+pub mod search;
@@ -29,12 +29,27 @@ pub struct Beatmapset {
     pub favourite_count: i32,
     pub play_count: i32,
     pub availability_download_disabled: bool,
+    pub deleted: bool,
+    pub deleted_at: Option<DateTime<Utc>>,
 
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 
     #[sqlx(skip)]
     pub beatmaps: Option<Vec<Beatmap>>,
+    #[sqlx(skip)]
+    pub user: Option<User>,
+    #[sqlx(skip)]
+    pub pack_tags: Vec<String>,
+}
+
+impl Beatmapset {
+    /// Drops difficulties that were removed from the set upstream.
+    pub fn retain_live_beatmaps(&mut self) {
+        if let Some(maps) = self.beatmaps.as_mut() {
+            maps.retain(|m| !m.deleted);
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
@@ -57,6 +72,357 @@ pub struct Beatmap {
     pub count_sliders: Option<i32>,
     pub count_spinners: Option<i32>,
     pub checksum: Option<String>,
+    pub deleted: bool,
+    pub deleted_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// Nomod aim and speed difficulty, when precomputed. osu! standard only.
+    #[serde(skip)]
+    #[sqlx(default)]
+    pub aim: Option<f64>,
+    #[serde(skip)]
+    #[sqlx(default)]
+    pub speed: Option<f64>,
+}
+
+/// The difficulty fields that are kept in history snapshots.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BeatmapSnapshot {
+    pub id: i64,
+    pub version: String,
+    pub checksum: Option<String>,
+    pub difficulty_rating: Option<f64>,
+    pub ar: Option<f64>,
+    pub cs: Option<f64>,
+    pub accuracy: Option<f64>,
+    pub drain: Option<f64>,
+    pub bpm: Option<f64>,
+    pub total_length: Option<i32>,
+    pub hit_length: Option<i32>,
+    pub max_combo: Option<i32>,
+}
+
+impl From<&Beatmap> for BeatmapSnapshot {
+    fn from(m: &Beatmap) -> Self {
+        Self {
+            id: m.id,
+            version: m.version.clone(),
+            checksum: m.checksum.clone(),
+            difficulty_rating: m.difficulty_rating,
+            ar: m.ar,
+            cs: m.cs,
+            accuracy: m.accuracy,
+            drain: m.drain,
+            bpm: m.bpm,
+            total_length: m.total_length,
+            hit_length: m.hit_length,
+            max_combo: m.max_combo,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BeatmapChange {
+    pub beatmap_id: i64,
+    pub version: String,
+    pub field: String,
+    pub from: serde_json::Value,
+    pub to: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BeatmapsetHistory {
+    pub id: i64,
+    pub beatmapset_id: i64,
+    pub changes: Vec<String>,
+    pub previous_status: Option<String>,
+    pub status: String,
+    pub previous_ranked_date: Option<DateTime<Utc>>,
+    pub ranked_date: Option<DateTime<Utc>>,
+    pub previous_last_updated: Option<DateTime<Utc>>,
+    pub last_updated: Option<DateTime<Utc>>,
+    /// Difficulties as they were before the change.
+    pub beatmaps: Vec<BeatmapSnapshot>,
+    pub beatmap_changes: Vec<BeatmapChange>,
+    pub recorded_at: DateTime<Utc>,
+}
+
+/// A history row that has not been written yet.
+#[derive(Debug, Clone)]
+pub struct NewBeatmapsetHistory {
+    pub beatmapset_id: i64,
+    pub changes: Vec<String>,
+    pub previous_status: Option<String>,
+    pub status: String,
+    pub previous_ranked_date: Option<DateTime<Utc>>,
+    pub ranked_date: Option<DateTime<Utc>>,
+    pub previous_last_updated: Option<DateTime<Utc>>,
+    pub last_updated: Option<DateTime<Utc>>,
+    pub beatmaps: Vec<BeatmapSnapshot>,
+    pub beatmap_changes: Vec<BeatmapChange>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Webhook {
+    pub id: i64,
+    pub url: String,
+    pub has_secret: bool,
+    pub events: Vec<String>,
+    pub modes: Vec<String>,
+    pub format: String,
+    pub source: String,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct WebhookDelivery {
+    pub id: i64,
+    pub webhook_id: i64,
+    pub event_id: i64,
+    pub event: String,
+    pub payload: serde_json::Value,
+    pub status: String,
+    pub attempts: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub last_status_code: Option<i32>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+/// A delivery claimed by the sender, joined with its target.
+#[derive(Debug, Clone)]
+pub struct DueDelivery {
+    pub id: i64,
+    pub event: String,
+    pub payload: serde_json::Value,
+    pub attempts: i32,
+    pub url: String,
+    pub secret: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct SyncRun {
+    pub id: i64,
+    pub worker_id: String,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub fetched: i32,
+    pub inserted: i32,
+    pub updated: i32,
+    pub unchanged: i32,
+    pub deleted: i32,
+    pub errors: i32,
+    pub cursor_before: Option<String>,
+    pub cursor_after: Option<String>,
+    pub error: Option<String>,
+    /// Fetched nothing, failed nothing and left the cursor where it was.
+    pub idle: bool,
+    /// Cycles this row stands for. Only idle rows cover more than one.
+    pub runs: i32,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewSyncRun {
+    pub worker_id: String,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub stats: crate::crawler::sync::SyncStats,
+    pub cursor_before: Option<String>,
+    pub cursor_after: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct User {
+    pub id: i64,
+    pub username: String,
+    pub previous_usernames: Vec<String>,
+    pub country_code: Option<String>,
+    pub country_name: Option<String>,
+    pub avatar_url: Option<String>,
+    pub ranked_beatmapset_count: i32,
+    pub loved_beatmapset_count: i32,
+    pub pending_beatmapset_count: i32,
+    pub graveyard_beatmapset_count: i32,
+    pub guest_beatmapset_count: i32,
+    pub available: bool,
+    pub fetched_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct BeatmapPack {
+    pub tag: String,
+    pub name: String,
+    pub author: Option<String>,
+    pub pack_type: String,
+    pub date: Option<DateTime<Utc>>,
+    pub url: Option<String>,
+    pub ruleset_id: Option<i32>,
+    pub no_diff_reduction: bool,
+    pub sets_synced_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Mappool {
+    pub id: i64,
+    pub tournament: String,
+    pub round: String,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct MappoolSlot {
+    pub slot: String,
+    pub beatmap_id: i64,
+    /// Legacy mod bitmask.
+    pub mods: i32,
+    pub star_rating: Option<f64>,
+}
+
+/// A beatmap as needed for finding its .osu file in the set's archive.
+#[derive(Debug, Clone)]
+pub struct OsuFileRef {
+    pub beatmap_id: i64,
+    pub beatmapset_id: i64,
+    pub version: String,
+    pub checksum: Option<String>,
+}
+
+impl From<&Beatmap> for OsuFileRef {
+    fn from(map: &Beatmap) -> Self {
+        Self {
+            beatmap_id: map.id,
+            beatmapset_id: map.beatmapset_id,
+            version: map.version.clone(),
+            checksum: map.checksum.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct BeatmapDifficultyAttributes {
+    pub beatmap_id: i64,
+    /// Legacy mod bitmask.
+    pub mods: i32,
+    pub star_rating: f64,
+    pub aim: Option<f64>,
+    pub speed: Option<f64>,
+    pub max_combo: i32,
+}
+
+/// Values parsed from a difficulty's .osu file. Times are in milliseconds.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BeatmapDetails {
+    pub beatmap_id: i64,
+    pub checksum: Option<String>,
+    pub audio_filename: Option<String>,
+    pub audio_lead_in: i32,
+    pub preview_time: Option<i32>,
+    /// osu!mania only.
+    pub keys: Option<i32>,
+    pub bpm_min: Option<f64>,
+    pub bpm_max: Option<f64>,
+    /// The BPM that lasts longest.
+    pub bpm_main: Option<f64>,
+    pub slider_multiplier: f64,
+    pub slider_tick_rate: f64,
+    pub circles: i32,
+    pub sliders: i32,
+    pub spinners: i32,
+    /// osu!mania hold notes.
+    pub holds: i32,
+    /// Ticks of each slider in object order, over all of its slides.
+    pub slider_ticks: Vec<i32>,
+    pub first_object_time: Option<i32>,
+    /// End of the last object.
+    pub last_object_time: Option<i32>,
+    /// From the first object to the end of the last one, minus breaks.
+    pub drain_time: Option<i32>,
+    /// The parts of the map between breaks, which add up to `drain_time`.
+    pub drain_segments: Vec<DrainSegment>,
+    pub breaks: Vec<BreakPeriod>,
+    /// Objects starting in each of 100 equal slices of the map.
+    pub density: Vec<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BreakPeriod {
+    pub start: i32,
+    pub end: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DrainSegment {
+    pub start: i32,
+    pub end: i32,
+}
+
+/// A pack member as needed for building the pack archive.
+#[derive(Debug, Clone)]
+pub struct PackArchiveEntry {
+    pub beatmapset_id: i64,
+    pub artist: Option<String>,
+    pub title: Option<String>,
+}
+
+/// A beatmapset's rank status as stored in `beatmapsets.status`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RankStatus {
+    Graveyard,
+    Wip,
+    Pending,
+    Ranked,
+    Approved,
+    Qualified,
+    Loved,
+}
+
+impl RankStatus {
+    /// Accepts the stored names, osu!'s one-letter search aliases and the
+    /// numeric values of the v1 API (`-2` to `4`).
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "g" | "graveyard" | "-2" => Some(Self::Graveyard),
+            "w" | "wip" | "-1" => Some(Self::Wip),
+            "p" | "pending" | "0" => Some(Self::Pending),
+            "r" | "ranked" | "1" => Some(Self::Ranked),
+            "a" | "approved" | "2" => Some(Self::Approved),
+            "q" | "qualified" | "3" => Some(Self::Qualified),
+            "l" | "loved" | "4" => Some(Self::Loved),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Graveyard => "graveyard",
+            Self::Wip => "wip",
+            Self::Pending => "pending",
+            Self::Ranked => "ranked",
+            Self::Approved => "approved",
+            Self::Qualified => "qualified",
+            Self::Loved => "loved",
+        }
+    }
+
+    /// The v1 `approved` / v2 `ranked` integer.
+    pub fn as_int(&self) -> i32 {
+        match self {
+            Self::Graveyard => -2,
+            Self::Wip => -1,
+            Self::Pending => 0,
+            Self::Ranked => 1,
+            Self::Approved => 2,
+            Self::Qualified => 3,
+            Self::Loved => 4,
+        }
+    }
 }
@@ -1,8 +1,17 @@
-use super::models::{Beatmap, Beatmapset};
+use super::models::{
+    Beatmap, BeatmapChange, BeatmapDetails, BeatmapDifficultyAttributes, BeatmapPack,
+    BeatmapSnapshot, Beatmapset, BeatmapsetHistory, BreakPeriod, DrainSegment, DueDelivery,
+    Mappool, MappoolSlot, NewBeatmapsetHistory, NewSyncRun, OsuFileRef, PackArchiveEntry,
+    RankStatus, SyncRun, User, Webhook, WebhookDelivery,
+};
+use super::search::{LegacyBeatmapFilter, SearchFilter, SearchPage, SearchSort};
 use crate::error::Result;
-use sqlx::{PgPool, Row};
+use crate::events::{BeatmapEvent, EventKind};
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, PgPool, Postgres, QueryBuilder, Row, types::Json};
+use std::collections::HashMap;
 
-pub async fn upsert_beatmapset(pool: &PgPool, set: &Beatmapset) -> Result<()> {
+pub async fn upsert_beatmapset(conn: &mut PgConnection, set: &Beatmapset) -> Result<()> {
     sqlx::query!(
         r#"
         INSERT INTO beatmapsets (
@@ -42,6 +51,8 @@ pub async fn upsert_beatmapset(pool: &PgPool, set: &Beatmapset) -> Result<()> {
             favourite_count = EXCLUDED.favourite_count,
             play_count = EXCLUDED.play_count,
             availability_download_disabled = EXCLUDED.availability_download_disabled,
+            deleted = FALSE,
+            deleted_at = NULL,
             updated_at = NOW()
         "#,
         set.id,
@@ -68,12 +79,12 @@ pub async fn upsert_beatmapset(pool: &PgPool, set: &Beatmapset) -> Result<()> {
         set.play_count,
         set.availability_download_disabled
     )
-    .execute(pool)
+    .execute(&mut *conn)
     .await?;
     Ok(())
 }
 
-pub async fn upsert_beatmap(pool: &PgPool, m: &Beatmap) -> Result<()> {
+pub async fn upsert_beatmap(conn: &mut PgConnection, m: &Beatmap) -> Result<()> {
     sqlx::query!(
         r#"
         INSERT INTO beatmaps (
@@ -107,6 +118,8 @@ pub async fn upsert_beatmap(pool: &PgPool, m: &Beatmap) -> Result<()> {
             count_sliders = EXCLUDED.count_sliders,
             count_spinners = EXCLUDED.count_spinners,
             checksum = EXCLUDED.checksum,
+            deleted = FALSE,
+            deleted_at = NULL,
             updated_at = NOW()
         "#,
         m.id,
@@ -128,13 +141,24 @@ pub async fn upsert_beatmap(pool: &PgPool, m: &Beatmap) -> Result<()> {
         m.count_spinners,
         m.checksum,
     )
-    .execute(pool)
+    .execute(&mut *conn)
     .await?;
     Ok(())
 }
 
 pub async fn get_beatmapset(pool: &PgPool, id: i64) -> Result<Option<Beatmapset>> {
-    let row = sqlx::query!(
+    Ok(get_beatmapsets(pool, &[id]).await?.into_iter().next())
+}
+
+/// Sets with their beatmaps, mapper and pack tags, in the order of `ids`.
+/// Unknown ids are left out. Runs a fixed number of queries however many ids
+/// are given.
+pub async fn get_beatmapsets(pool: &PgPool, ids: &[i64]) -> Result<Vec<Beatmapset>> {
+    if ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let rows = sqlx::query!(
         r#"
         SELECT
             id, title, title_unicode, artist, artist_unicode, creator,
@@ -142,132 +166,336 @@ pub async fn get_beatmapset(pool: &PgPool, id: i64) -> Result<Option<Beatmapset>
             source, tags, status, ranked_date, submitted_date,
             last_updated, bpm, video, storyboard, nsfw,
             favourite_count, play_count, availability_download_disabled,
-            created_at, updated_at
-        FROM beatmapsets WHERE id = $1
+            deleted, deleted_at, created_at, updated_at
+        FROM beatmapsets WHERE id = ANY($1)
         "#,
-        id
+        ids
     )
-    .fetch_optional(pool)
+    .fetch_all(pool)
     .await?;
 
-    let Some(r) = row else {
-        return Ok(None);
-    };
+    let mut by_id: HashMap<i64, Beatmapset> = rows
+        .into_iter()
+        .map(|r| {
+            let set = Beatmapset {
+                id: r.id,
+                title: r.title,
+                title_unicode: r.title_unicode,
+                artist: r.artist,
+                artist_unicode: r.artist_unicode,
+                creator: r.creator,
+                creator_id: r.creator_id,
+                genre_id: r.genre_id,
+                language_id: r.language_id,
+                rating: r.rating,
+                source: r.source,
+                tags: r.tags,
+                status: r.status,
+                ranked_date: r.ranked_date,
+                submitted_date: r.submitted_date,
+                last_updated: r.last_updated,
+                bpm: r.bpm,
+                video: r.video.unwrap_or(false),
+                storyboard: r.storyboard.unwrap_or(false),
+                nsfw: r.nsfw.unwrap_or(false),
+                favourite_count: r.favourite_count.unwrap_or(0),
+                play_count: r.play_count.unwrap_or(0),
+                availability_download_disabled: r.availability_download_disabled.unwrap_or(false),
+                deleted: r.deleted,
+                deleted_at: r.deleted_at,
+                created_at: r.created_at.unwrap_or_else(chrono::Utc::now),
+                updated_at: r.updated_at.unwrap_or_else(chrono::Utc::now),
+                beatmaps: None,
+                user: None,
+                pack_tags: Vec::new(),
+            };
+            (set.id, set)
+        })
+        .collect();
 
-    let mut set = Beatmapset {
-        id: r.id,
-        title: r.title,
-        title_unicode: r.title_unicode,
-        artist: r.artist,
-        artist_unicode: r.artist_unicode,
-        creator: r.creator,
-        creator_id: r.creator_id,
-        genre_id: r.genre_id,
-        language_id: r.language_id,
-        rating: r.rating,
-        source: r.source,
-        tags: r.tags,
-        status: r.status,
-        ranked_date: r.ranked_date,
-        submitted_date: r.submitted_date,
-        last_updated: r.last_updated,
-        bpm: r.bpm,
-        video: r.video.unwrap_or(false),
-        storyboard: r.storyboard.unwrap_or(false),
-        nsfw: r.nsfw.unwrap_or(false),
-        favourite_count: r.favourite_count.unwrap_or(0),
-        play_count: r.play_count.unwrap_or(0),
-        availability_download_disabled: r.availability_download_disabled.unwrap_or(false),
-        created_at: r.created_at.unwrap_or_else(chrono::Utc::now),
-        updated_at: r.updated_at.unwrap_or_else(chrono::Utc::now),
-        beatmaps: None,
-    };
+    let mut sets: Vec<Beatmapset> = ids.iter().filter_map(|id| by_id.remove(id)).collect();
+    load_beatmapset_relations(pool, &mut sets).await?;
+    Ok(sets)
+}
+
+/// Fills `beatmaps`, `user` and `pack_tags` of already loaded sets with one
+/// query each.
+pub async fn load_beatmapset_relations(pool: &PgPool, sets: &mut [Beatmapset]) -> Result<()> {
+    if sets.is_empty() {
+        return Ok(());
+    }
+
+    let set_ids: Vec<i64> = sets.iter().map(|s| s.id).collect();
 
     let rows = sqlx::query!(
         r#"
         SELECT
-            id, beatmapset_id, version, mode, mode_int,
-            difficulty_rating, ar, cs, drain, accuracy, bpm,
-            total_length, hit_length, max_combo,
-            count_circles, count_sliders, count_spinners,
-            checksum, created_at, updated_at
-        FROM beatmaps
-        WHERE beatmapset_id = $1
-        ORDER BY id ASC
+            b.id, b.beatmapset_id, b.version, b.mode, b.mode_int,
+            b.difficulty_rating, b.ar, b.cs, b.drain, b.accuracy, b.bpm,
+            b.total_length, b.hit_length, b.max_combo,
+            b.count_circles, b.count_sliders, b.count_spinners,
+            b.checksum, b.deleted, b.deleted_at, b.created_at, b.updated_at,
+            a.aim as "aim?", a.speed as "speed?"
+        FROM beatmaps b
+        LEFT JOIN beatmap_difficulty_attributes a
+            ON a.beatmap_id = b.id
+            AND a.mods = 0
+            AND a.checksum IS NOT DISTINCT FROM b.checksum
+        WHERE b.beatmapset_id = ANY($1)
+        ORDER BY b.id ASC
         "#,
-        id
+        &set_ids
     )
     .fetch_all(pool)
     .await?;
 
-    set.beatmaps = Some(
-        rows.into_iter()
-            .map(|b| Beatmap {
-                id: b.id,
-                beatmapset_id: b.beatmapset_id,
-                version: b.version,
-                mode: b.mode,
-                mode_int: b.mode_int,
-                difficulty_rating: b.difficulty_rating,
-                ar: b.ar,
-                cs: b.cs,
-                drain: b.drain,
-                accuracy: b.accuracy,
-                bpm: b.bpm,
-                total_length: b.total_length,
-                hit_length: b.hit_length,
-                max_combo: b.max_combo,
-                count_circles: b.count_circles,
-                count_sliders: b.count_sliders,
-                count_spinners: b.count_spinners,
-                checksum: b.checksum,
-                created_at: b.created_at.unwrap_or_else(chrono::Utc::now),
-                updated_at: b.updated_at.unwrap_or_else(chrono::Utc::now),
-            })
-            .collect(),
-    );
+    let mut beatmaps: HashMap<i64, Vec<Beatmap>> = HashMap::new();
+    for b in rows {
+        beatmaps.entry(b.beatmapset_id).or_default().push(Beatmap {
+            id: b.id,
+            beatmapset_id: b.beatmapset_id,
+            version: b.version,
+            mode: b.mode,
+            mode_int: b.mode_int,
+            difficulty_rating: b.difficulty_rating,
+            ar: b.ar,
+            cs: b.cs,
+            drain: b.drain,
+            accuracy: b.accuracy,
+            bpm: b.bpm,
+            total_length: b.total_length,
+            hit_length: b.hit_length,
+            max_combo: b.max_combo,
+            count_circles: b.count_circles,
+            count_sliders: b.count_sliders,
+            count_spinners: b.count_spinners,
+            checksum: b.checksum,
+            deleted: b.deleted,
+            deleted_at: b.deleted_at,
+            created_at: b.created_at.unwrap_or_else(chrono::Utc::now),
+            updated_at: b.updated_at.unwrap_or_else(chrono::Utc::now),
+            aim: b.aim,
+            speed: b.speed,
+        });
+    }
+
+    let creator_ids: Vec<i64> = sets
+        .iter()
+        .filter_map(|s| s.creator_id.filter(|id| *id > 0))
+        .collect();
+    let users: HashMap<i64, User> = get_users(pool, &creator_ids)
+        .await?
+        .into_iter()
+        .map(|u| (u.id, u))
+        .collect();
+
+    let mut pack_tags: HashMap<i64, Vec<String>> = HashMap::new();
+    for (set_id, tag) in get_beatmapsets_pack_tags(pool, &set_ids).await? {
+        pack_tags.entry(set_id).or_default().push(tag);
+    }
+
+    for set in sets.iter_mut() {
+        set.beatmaps = Some(beatmaps.remove(&set.id).unwrap_or_default());
+        set.user = set.creator_id.and_then(|id| users.get(&id).cloned());
+        set.pack_tags = pack_tags.remove(&set.id).unwrap_or_default();
+    }
+
+    Ok(())
+}
+
+/// Set id of a beatmap, whether or not the beatmap is deleted.
+pub async fn get_beatmap_set_id(pool: &PgPool, beatmap_id: i64) -> Result<Option<i64>> {
+    let row = sqlx::query_scalar!(
+        "SELECT beatmapset_id FROM beatmaps WHERE id = $1",
+        beatmap_id
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(row)
+}
+
+/// `(beatmap_id, beatmapset_id)` for the subset of `beatmap_ids` we have.
+pub async fn get_beatmap_set_ids(pool: &PgPool, beatmap_ids: &[i64]) -> Result<Vec<(i64, i64)>> {
+    let rows = sqlx::query!(
+        "SELECT id, beatmapset_id FROM beatmaps WHERE id = ANY($1)",
+        beatmap_ids
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(|r| (r.id, r.beatmapset_id)).collect())
+}
+
+/// `(beatmap_id, beatmapset_id)` of up to `count` random difficulties matching
+/// `filter`. Reads forward from a random point in `random_key`, wrapping
+/// around, so it stays an index scan however large the table is.
+pub async fn get_random_beatmap_ids(
+    pool: &PgPool,
+    filter: &SearchFilter,
+    count: i64,
+) -> Result<Vec<(i64, i64)>> {
+    let pivot: f64 = rand::random();
+    let mut ids = Vec::new();
+
+    for after_pivot in [true, false] {
+        let remaining = count - ids.len() as i64;
+        if remaining <= 0 {
+            break;
+        }
+        let mut qb = QueryBuilder::<Postgres>::new("SELECT b.id, b.beatmapset_id");
+        filter.push_beatmap_from(&mut qb);
+        qb.push(if after_pivot {
+            " AND b.random_key >= "
+        } else {
+            " AND b.random_key < "
+        })
+        .push_bind(pivot)
+        .push(" ORDER BY b.random_key LIMIT ")
+        .push_bind(remaining);
+        ids.extend(qb.build_query_as::<(i64, i64)>().fetch_all(pool).await?);
+    }
+
+    Ok(ids)
+}
+
+/// How far from a beatmap's star rating similar maps may be.
+const SIMILAR_STARS_WINDOW: f64 = 0.5;
+
+/// `(beatmap_id, beatmapset_id)` of difficulties from other sets in the same
+/// mode (and key count in mania) that are closest in star rating, BPM,
+/// length, AR and CS, among those matching `filter`.
+pub async fn get_similar_beatmap_ids(
+    pool: &PgPool,
+    map: &Beatmap,
+    filter: &SearchFilter,
+    limit: i64,
+) -> Result<Vec<(i64, i64)>> {
+    let Some(stars) = map.difficulty_rating else {
+        return Ok(Vec::new());
+    };
+
+    let mut qb = QueryBuilder::<Postgres>::new("SELECT b.id, b.beatmapset_id");
+    filter.push_beatmap_from(&mut qb);
+    qb.push(" AND b.mode_int = ")
+        .push_bind(map.mode_int)
+        .push(" AND b.difficulty_rating BETWEEN ")
+        .push_bind(stars - SIMILAR_STARS_WINDOW)
+        .push(" AND ")
+        .push_bind(stars + SIMILAR_STARS_WINDOW)
+        .push(" AND b.beatmapset_id <> ")
+        .push_bind(map.beatmapset_id);
+    if map.mode_int == 3 {
+        qb.push(" AND b.cs IS NOT DISTINCT FROM ").push_bind(map.cs);
+    }
+
+    // Each difference is scaled to roughly how much it matters to a player;
+    // values unknown on either side count as one step off.
+    qb.push(" ORDER BY ABS(b.difficulty_rating - ")
+        .push_bind(stars)
+        .push(") / 0.25 + COALESCE(ABS(COALESCE(b.bpm, s.bpm) - ")
+        .push_bind(map.bpm)
+        .push(") / 20, 1) + COALESCE(ABS(b.total_length - ")
+        .push_bind(map.total_length)
+        .push(")::FLOAT8 / 30, 1) + COALESCE(ABS(b.ar - ")
+        .push_bind(map.ar)
+        .push(") / 0.5, 1) + COALESCE(ABS(b.cs - ")
+        .push_bind(map.cs)
+        .push(") / 0.5, 1), b.id LIMIT ")
+        .push_bind(limit);
 
-    Ok(Some(set))
+    Ok(qb.build_query_as::<(i64, i64)>().fetch_all(pool).await?)
 }
 
+/// `(beatmap_id, beatmapset_id)` of the beatmap with this .osu checksum.
+pub async fn get_beatmap_ids_by_checksum(
+    pool: &PgPool,
+    checksum: &str,
+) -> Result<Option<(i64, i64)>> {
+    let row = sqlx::query!(
+        r#"
+        SELECT id, beatmapset_id FROM beatmaps
+        WHERE checksum = $1
+        ORDER BY deleted ASC, id DESC
+        LIMIT 1
+        "#,
+        checksum
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|r| (r.id, r.beatmapset_id)))
+}
+
+/// `(beatmap_id, beatmapset_id)` of the beatmap whose .osu file would be named
+/// `{artist} - {title} ({creator}) [{version}].osu`.
+pub async fn get_beatmap_ids_by_filename(
+    pool: &PgPool,
+    artist_title: &str,
+    creator: &str,
+    version: &str,
+) -> Result<Option<(i64, i64)>> {
+    let row = sqlx::query!(
+        r#"
+        SELECT b.id, b.beatmapset_id FROM beatmaps b
+        JOIN beatmapsets s ON s.id = b.beatmapset_id
+        WHERE s.creator = $2
+          AND b.version = $3
+          AND s.artist || ' - ' || s.title = $1
+        ORDER BY b.deleted ASC, b.id DESC
+        LIMIT 1
+        "#,
+        artist_title,
+        creator,
+        version
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|r| (r.id, r.beatmapset_id)))
+}
+
+/// One page of results. With a `cursor` the page starts after that position
+/// and `offset` is ignored.
 pub async fn search_beatmapsets(
     pool: &PgPool,
-    keyword: &str,
-    status: Option<&str>,
+    filter: &SearchFilter,
+    sort: &SearchSort,
+    cursor: Option<&serde_json::Value>,
     limit: i64,
     offset: i64,
-) -> Result<Vec<Beatmapset>> {
-    let mut sql = String::from(
-        r#"
-        SELECT
-            id, title, title_unicode, artist, artist_unicode, creator,
-            creator_id, genre_id, language_id, rating,
-            source, tags, status, ranked_date, submitted_date,
-            last_updated, bpm, video, storyboard, nsfw,
-            favourite_count, play_count, availability_download_disabled,
-            created_at, updated_at
-        FROM beatmapsets
-        WHERE 1=1
+) -> Result<SearchPage> {
+    let mut qb = QueryBuilder::<Postgres>::new("SELECT to_jsonb(");
+    sort.push_expr(&mut qb, filter);
+    qb.push(
+        r#") AS sort_key,
+            s.id, s.title, s.title_unicode, s.artist, s.artist_unicode, s.creator,
+            s.creator_id, s.genre_id, s.language_id, s.rating,
+            s.source, s.tags, s.status, s.ranked_date, s.submitted_date,
+            s.last_updated, s.bpm, s.video, s.storyboard, s.nsfw,
+            s.favourite_count, s.play_count, s.availability_download_disabled,
+            s.deleted, s.deleted_at, s.created_at, s.updated_at
+        FROM beatmapsets s
         "#,
     );
-
-    if !keyword.is_empty() {
-        sql.push_str(
-            " AND (title ILIKE $1 OR artist ILIKE $1 OR creator ILIKE $1 OR tags ILIKE $1)",
-        );
+    filter.push_where(&mut qb);
+    if let Some(cursor) = cursor {
+        sort.push_after(&mut qb, filter, cursor)?;
     }
-
-    if let Some(s) = status {
-        sql.push_str(&format!(" AND status = '{}'", s));
+    sort.push_order_by(&mut qb, filter);
+    qb.push(" LIMIT ").push_bind(limit);
+    if cursor.is_none() {
+        qb.push(" OFFSET ").push_bind(offset);
     }
 
-    sql.push_str(" ORDER BY ranked_date DESC NULLS LAST");
-    sql.push_str(&format!(" LIMIT {} OFFSET {}", limit, offset));
+    let rows = qb.build().fetch_all(pool).await?;
 
-    let kw = format!("%{}%", keyword);
-    let rows = sqlx::query(&sql).bind(kw).fetch_all(pool).await?;
+    let next_cursor = match rows.last() {
+        Some(last) if rows.len() as i64 == limit => {
+            let key: Json<serde_json::Value> = last.try_get("sort_key")?;
+            let id: i64 = last.try_get("id")?;
+            Some(serde_json::json!({ sort.cursor_key(): key.0, "id": id }))
+        }
+        _ => None,
+    };
 
-    Ok(rows
+    let mut sets: Vec<Beatmapset> = rows
         .into_iter()
         .map(|r| Beatmapset {
             id: r.try_get("id").unwrap(),
@@ -295,6 +523,8 @@ pub async fn search_beatmapsets(
             availability_download_disabled: r
                 .try_get("availability_download_disabled")
                 .unwrap_or(false),
+            deleted: r.try_get("deleted").unwrap_or(false),
+            deleted_at: r.try_get("deleted_at").ok().flatten(),
             created_at: r
                 .try_get("created_at")
                 .unwrap_or_else(|_| chrono::Utc::now()),
@@ -302,25 +532,1415 @@ pub async fn search_beatmapsets(
                 .try_get("updated_at")
                 .unwrap_or_else(|_| chrono::Utc::now()),
             beatmaps: None,
+            user: None,
+            pack_tags: Vec::new(),
+        })
+        .collect();
+
+    load_beatmapset_relations(pool, &mut sets).await?;
+
+    Ok(SearchPage { sets, next_cursor })
+}
+
+pub async fn count_beatmapsets(pool: &PgPool, filter: &SearchFilter) -> Result<i64> {
+    let mut qb = QueryBuilder::<Postgres>::new("SELECT COUNT(*) FROM beatmapsets s");
+    filter.push_where(&mut qb);
+
+    let count = qb.build_query_scalar::<i64>().fetch_one(pool).await?;
+    Ok(count)
+}
+
+/// `(beatmap_id, beatmapset_id)` matching a v1 `/api/get_beatmaps` request,
+/// oldest ranked first like osu!.
+pub async fn get_legacy_beatmap_ids(
+    pool: &PgPool,
+    filter: &LegacyBeatmapFilter,
+    limit: i64,
+) -> Result<Vec<(i64, i64)>> {
+    let mut qb = QueryBuilder::<Postgres>::new(
+        "SELECT b.id, b.beatmapset_id FROM beatmaps b JOIN beatmapsets s ON s.id = b.beatmapset_id",
+    );
+    filter.push_where(&mut qb);
+    qb.push(" ORDER BY s.ranked_date ASC NULLS LAST, b.id ASC LIMIT ")
+        .push_bind(limit);
+
+    let rows = qb.build_query_as::<(i64, i64)>().fetch_all(pool).await?;
+    Ok(rows)
+}
+
+pub async fn mark_beatmapset_deleted(conn: &mut PgConnection, id: i64) -> Result<bool> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE beatmapsets
+        SET deleted = TRUE, deleted_at = NOW(), updated_at = NOW()
+        WHERE id = $1 AND NOT deleted
+        "#,
+        id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        UPDATE beatmaps
+        SET deleted = TRUE, deleted_at = NOW(), updated_at = NOW()
+        WHERE beatmapset_id = $1 AND NOT deleted
+        "#,
+        id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn mark_missing_beatmaps_deleted(
+    conn: &mut PgConnection,
+    beatmapset_id: i64,
+    live_ids: &[i64],
+) -> Result<u64> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE beatmaps
+        SET deleted = TRUE, deleted_at = NOW(), updated_at = NOW()
+        WHERE beatmapset_id = $1 AND id <> ALL($2) AND NOT deleted
+        "#,
+        beatmapset_id,
+        live_ids
+    )
+    .execute(&mut *conn)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+pub async fn get_stale_beatmapset_ids(pool: &PgPool, limit: i64) -> Result<Vec<i64>> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT id FROM beatmapsets
+        WHERE NOT deleted
+        ORDER BY updated_at ASC
+        LIMIT $1
+        "#,
+        limit
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(|r| r.id).collect())
+}
+
+pub async fn insert_beatmapset_history(
+    conn: &mut PgConnection,
+    h: &NewBeatmapsetHistory,
+) -> Result<i64> {
+    let row = sqlx::query!(
+        r#"
+        INSERT INTO beatmapset_history (
+            beatmapset_id, changes,
+            previous_status, status,
+            previous_ranked_date, ranked_date,
+            previous_last_updated, last_updated,
+            beatmaps, beatmap_changes
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        RETURNING id
+        "#,
+        h.beatmapset_id,
+        &h.changes,
+        h.previous_status,
+        h.status,
+        h.previous_ranked_date,
+        h.ranked_date,
+        h.previous_last_updated,
+        h.last_updated,
+        Json(&h.beatmaps) as _,
+        Json(&h.beatmap_changes) as _,
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+    Ok(row.id)
+}
+
+pub async fn get_beatmapset_history(
+    pool: &PgPool,
+    beatmapset_id: i64,
+    before: Option<i64>,
+    limit: i64,
+) -> Result<Vec<BeatmapsetHistory>> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            id, beatmapset_id, changes,
+            previous_status, status,
+            previous_ranked_date, ranked_date,
+            previous_last_updated, last_updated,
+            beatmaps as "beatmaps: Json<Vec<BeatmapSnapshot>>",
+            beatmap_changes as "beatmap_changes: Json<Vec<BeatmapChange>>",
+            recorded_at
+        FROM beatmapset_history
+        WHERE beatmapset_id = $1 AND ($2::BIGINT IS NULL OR id < $2)
+        ORDER BY id DESC
+        LIMIT $3
+        "#,
+        beatmapset_id,
+        before,
+        limit
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| BeatmapsetHistory {
+            id: r.id,
+            beatmapset_id: r.beatmapset_id,
+            changes: r.changes,
+            previous_status: r.previous_status,
+            status: r.status,
+            previous_ranked_date: r.previous_ranked_date,
+            ranked_date: r.ranked_date,
+            previous_last_updated: r.previous_last_updated,
+            last_updated: r.last_updated,
+            beatmaps: r.beatmaps.0,
+            beatmap_changes: r.beatmap_changes.0,
+            recorded_at: r.recorded_at,
+        })
+        .collect())
+}
+
+/// Where to start replaying history to resume after `after_id`: before every
+/// row recorded up to `overlap_secs` earlier than it, so rows that committed
+/// out of id order are replayed too.
+pub async fn get_event_replay_start(
+    pool: &PgPool,
+    after_id: i64,
+    overlap_secs: f64,
+) -> Result<i64> {
+    let start = sqlx::query_scalar!(
+        r#"
+        SELECT COALESCE(MIN(id) - 1, $1) AS "start!"
+        FROM beatmapset_history
+        WHERE id <= $1
+          AND recorded_at >= (SELECT recorded_at FROM beatmapset_history WHERE id = $1)
+                             - make_interval(secs => $2)
+        "#,
+        after_id,
+        overlap_secs
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(start)
+}
+
+/// History rows after `after_id`, shaped as events for feed consumers that resume.
+pub async fn get_beatmap_events_since(
+    pool: &PgPool,
+    after_id: i64,
+    limit: i64,
+) -> Result<Vec<BeatmapEvent>> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            h.id, h.beatmapset_id, h.changes, h.status, h.previous_status, h.recorded_at,
+            s.artist, s.title, s.creator,
+            ARRAY(
+                SELECT DISTINCT b.mode FROM beatmaps b
+                WHERE b.beatmapset_id = h.beatmapset_id
+                ORDER BY b.mode
+            ) as "modes!"
+        FROM beatmapset_history h
+        JOIN beatmapsets s ON s.id = h.beatmapset_id
+        WHERE h.id > $1
+        ORDER BY h.id ASC
+        LIMIT $2
+        "#,
+        after_id,
+        limit
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| BeatmapEvent {
+            id: r.id,
+            kind: EventKind::from_changes(&r.changes),
+            beatmapset_id: r.beatmapset_id,
+            status: r.status,
+            previous_status: r.previous_status,
+            changes: r.changes,
+            modes: r.modes,
+            artist: r.artist,
+            title: r.title,
+            creator: r.creator,
+            created_at: r.recorded_at,
         })
         .collect())
 }
 
-pub async fn count_beatmapsets(pool: &PgPool, keyword: &str, status: Option<&str>) -> Result<i64> {
-    let mut sql = String::from("SELECT COUNT(*) as count FROM beatmapsets WHERE 1=1");
+pub async fn list_webhooks(pool: &PgPool) -> Result<Vec<Webhook>> {
+    let rows = sqlx::query_as!(
+        Webhook,
+        r#"
+        SELECT
+            id, url, secret IS NOT NULL AS "has_secret!", events, modes, format, source,
+            enabled, created_at, updated_at
+        FROM webhooks
+        ORDER BY id ASC
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
 
-    if !keyword.is_empty() {
-        sql.push_str(
-            " AND (title ILIKE $1 OR artist ILIKE $1 OR creator ILIKE $1 OR tags ILIKE $1)",
-        );
-    }
+pub async fn insert_webhook(
+    pool: &PgPool,
+    url: &str,
+    secret: Option<&str>,
+    events: &[String],
+    modes: &[String],
+    format: &str,
+) -> Result<Webhook> {
+    let row = sqlx::query_as!(
+        Webhook,
+        r#"
+        INSERT INTO webhooks (url, secret, events, modes, format, source)
+        VALUES ($1, $2, $3, $4, $5, 'api')
+        RETURNING
+            id, url, secret IS NOT NULL AS "has_secret!", events, modes, format, source,
+            enabled, created_at, updated_at
+        "#,
+        url,
+        secret,
+        events,
+        modes,
+        format
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(row)
+}
+
+/// Makes the `config` webhooks match the config file: listed ones are upserted
+/// and enabled, the rest are disabled so their delivery log is kept.
+pub async fn sync_config_webhooks(
+    pool: &PgPool,
+    webhooks: &[crate::config::WebhookConfig],
+) -> Result<()> {
+    let mut tx = pool.begin().await?;
 
-    if let Some(s) = status {
-        sql.push_str(&format!(" AND status = '{}'", s));
+    for w in webhooks {
+        sqlx::query!(
+            r#"
+            INSERT INTO webhooks (url, secret, events, modes, format, source)
+            VALUES ($1, $2, $3, $4, $5, 'config')
+            ON CONFLICT (url) WHERE source = 'config' DO UPDATE SET
+                secret = EXCLUDED.secret,
+                events = EXCLUDED.events,
+                modes = EXCLUDED.modes,
+                format = EXCLUDED.format,
+                enabled = TRUE,
+                updated_at = NOW()
+            "#,
+            w.url,
+            w.secret,
+            &w.events,
+            &w.modes,
+            w.format
+        )
+        .execute(&mut *tx)
+        .await?;
     }
 
-    let kw = format!("%{}%", keyword);
-    let row = sqlx::query(&sql).bind(kw).fetch_one(pool).await?;
+    let urls: Vec<String> = webhooks.iter().map(|w| w.url.clone()).collect();
+    sqlx::query!(
+        r#"
+        UPDATE webhooks SET enabled = FALSE, updated_at = NOW()
+        WHERE source = 'config' AND url <> ALL($1)
+        "#,
+        &urls
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(())
+}
+
+pub async fn delete_webhook(pool: &PgPool, id: i64) -> Result<bool> {
+    let result = sqlx::query!("DELETE FROM webhooks WHERE id = $1", id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn enqueue_webhook_delivery(
+    pool: &PgPool,
+    webhook_id: i64,
+    event_id: i64,
+    event: &str,
+    payload: &serde_json::Value,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO webhook_deliveries (webhook_id, event_id, event, payload)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (webhook_id, event_id) DO NOTHING
+        "#,
+        webhook_id,
+        event_id,
+        event,
+        payload
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Picks due deliveries and leases them for a few minutes, so a crash mid-send
+/// only delays them instead of losing them.
+pub async fn claim_due_webhook_deliveries(pool: &PgPool, limit: i64) -> Result<Vec<DueDelivery>> {
+    let rows = sqlx::query!(
+        r#"
+        UPDATE webhook_deliveries d
+        SET next_attempt_at = NOW() + INTERVAL '5 minutes'
+        FROM webhooks w
+        WHERE w.id = d.webhook_id
+          AND d.id IN (
+            SELECT id FROM webhook_deliveries
+            WHERE status = 'pending' AND next_attempt_at <= NOW()
+            ORDER BY next_attempt_at ASC
+            LIMIT $1
+            FOR UPDATE SKIP LOCKED
+          )
+        RETURNING d.id, d.event, d.payload, d.attempts, w.url, w.secret
+        "#,
+        limit
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| DueDelivery {
+            id: r.id,
+            event: r.event,
+            payload: r.payload,
+            attempts: r.attempts,
+            url: r.url,
+            secret: r.secret,
+        })
+        .collect())
+}
+
+pub async fn mark_webhook_delivered(pool: &PgPool, id: i64, status_code: i32) -> Result<()> {
+    sqlx::query!(
+        r#"
+        UPDATE webhook_deliveries
+        SET status = 'delivered', attempts = attempts + 1,
+            last_status_code = $2, last_error = NULL, delivered_at = NOW()
+        WHERE id = $1
+        "#,
+        id,
+        status_code
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Records a failed attempt. Without `retry_at` the delivery is given up.
+pub async fn mark_webhook_attempt_failed(
+    pool: &PgPool,
+    id: i64,
+    status_code: Option<i32>,
+    error: &str,
+    retry_at: Option<DateTime<Utc>>,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+        UPDATE webhook_deliveries
+        SET attempts = attempts + 1,
+            last_status_code = $2,
+            last_error = $3,
+            status = CASE WHEN $4::TIMESTAMPTZ IS NULL THEN 'failed' ELSE 'pending' END,
+            next_attempt_at = COALESCE($4, next_attempt_at)
+        WHERE id = $1
+        "#,
+        id,
+        status_code,
+        error,
+        retry_at
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
 
-    Ok(row.try_get::<i64, _>("count")?)
+pub async fn get_webhook_deliveries(
+    pool: &PgPool,
+    webhook_id: i64,
+    limit: i64,
+) -> Result<Vec<WebhookDelivery>> {
+    let rows = sqlx::query_as!(
+        WebhookDelivery,
+        r#"
+        SELECT
+            id, webhook_id, event_id, event, payload, status, attempts,
+            next_attempt_at, last_status_code, last_error, created_at, delivered_at
+        FROM webhook_deliveries
+        WHERE webhook_id = $1
+        ORDER BY id DESC
+        LIMIT $2
+        "#,
+        webhook_id,
+        limit
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+/// Queues sets for a forced refetch. Ids already queued keep their place.
+pub async fn enqueue_resync(pool: &PgPool, ids: &[i64]) -> Result<u64> {
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO resync_queue (beatmapset_id)
+        SELECT UNNEST($1::BIGINT[])
+        ON CONFLICT (beatmapset_id) DO UPDATE SET attempts = 0, last_error = NULL
+        "#,
+        ids
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+pub async fn enqueue_resync_range(pool: &PgPool, from: i64, to: i64) -> Result<u64> {
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO resync_queue (beatmapset_id)
+        SELECT generate_series($1::BIGINT, $2::BIGINT)
+        ON CONFLICT (beatmapset_id) DO UPDATE SET attempts = 0, last_error = NULL
+        "#,
+        from,
+        to
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+pub async fn get_resync_queue_batch(
+    pool: &PgPool,
+    limit: i64,
+    max_attempts: i32,
+) -> Result<Vec<i64>> {
+    let rows = sqlx::query_scalar!(
+        r#"
+        SELECT beatmapset_id FROM resync_queue
+        WHERE attempts < $2
+        ORDER BY requested_at ASC, beatmapset_id ASC
+        LIMIT $1
+        "#,
+        limit,
+        max_attempts
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+pub async fn remove_from_resync_queue(pool: &PgPool, id: i64) -> Result<()> {
+    sqlx::query!("DELETE FROM resync_queue WHERE beatmapset_id = $1", id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub async fn record_resync_failure(pool: &PgPool, id: i64, error: &str) -> Result<()> {
+    sqlx::query!(
+        r#"
+        UPDATE resync_queue SET attempts = attempts + 1, last_error = $2
+        WHERE beatmapset_id = $1
+        "#,
+        id,
+        error
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Returns `(pending, failed)` counts of the resync queue.
+pub async fn count_resync_queue(pool: &PgPool, max_attempts: i32) -> Result<(i64, i64)> {
+    let row = sqlx::query!(
+        r#"
+        SELECT
+            COUNT(*) FILTER (WHERE attempts < $1) AS "pending!",
+            COUNT(*) FILTER (WHERE attempts >= $1) AS "failed!"
+        FROM resync_queue
+        "#,
+        max_attempts
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok((row.pending, row.failed))
+}
+
+pub async fn insert_sync_run(pool: &PgPool, run: &NewSyncRun) -> Result<i64> {
+    let id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO sync_runs (
+            worker_id, started_at, finished_at,
+            fetched, inserted, updated, unchanged, deleted, errors,
+            cursor_before, cursor_after, error
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+        RETURNING id
+        "#,
+        run.worker_id,
+        run.started_at,
+        run.finished_at,
+        run.stats.fetched as i32,
+        run.stats.inserted as i32,
+        run.stats.updated as i32,
+        run.stats.unchanged as i32,
+        run.stats.deleted as i32,
+        run.stats.errors as i32,
+        run.cursor_before,
+        run.cursor_after,
+        run.error
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(id)
+}
+
+/// Records an idle cycle, folding it into the worker's latest row when that
+/// one is idle too so a quiet worker doesn't add a row every few seconds.
+pub async fn record_idle_sync_run(
+    pool: &PgPool,
+    worker_id: &str,
+    started_at: DateTime<Utc>,
+    finished_at: DateTime<Utc>,
+    cursor: Option<&str>,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+        WITH latest AS (
+            SELECT id, idle FROM sync_runs
+            WHERE worker_id = $1
+            ORDER BY id DESC
+            LIMIT 1
+        ),
+        extended AS (
+            UPDATE sync_runs SET runs = runs + 1, finished_at = $3
+            WHERE id = (SELECT id FROM latest WHERE idle)
+            RETURNING id
+        )
+        INSERT INTO sync_runs (worker_id, started_at, finished_at, cursor_before, cursor_after, idle)
+        SELECT $1, $2, $3, $4, $4, TRUE
+        WHERE NOT EXISTS (SELECT 1 FROM extended)
+        "#,
+        worker_id,
+        started_at,
+        finished_at,
+        cursor
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Newest runs first, optionally for one worker and only failed ones.
+/// `before` is the id of the last run of the previous page.
+pub async fn get_sync_runs(
+    pool: &PgPool,
+    worker_id: Option<&str>,
+    failed_only: bool,
+    before: Option<i64>,
+    limit: i64,
+) -> Result<Vec<SyncRun>> {
+    let rows = sqlx::query_as!(
+        SyncRun,
+        r#"
+        SELECT
+            id, worker_id, started_at, finished_at,
+            fetched, inserted, updated, unchanged, deleted, errors,
+            cursor_before, cursor_after, error, idle, runs
+        FROM sync_runs
+        WHERE ($1::TEXT IS NULL OR worker_id = $1)
+          AND (NOT $2 OR error IS NOT NULL OR errors > 0)
+          AND ($3::BIGINT IS NULL OR id < $3)
+        ORDER BY id DESC
+        LIMIT $4
+        "#,
+        worker_id,
+        failed_only,
+        before,
+        limit
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+/// Latest successful run per worker, which is how fresh each part of the mirror is.
+pub async fn get_latest_successful_sync_runs(pool: &PgPool) -> Result<Vec<SyncRun>> {
+    let rows = sqlx::query_as!(
+        SyncRun,
+        r#"
+        SELECT DISTINCT ON (worker_id)
+            id, worker_id, started_at, finished_at,
+            fetched, inserted, updated, unchanged, deleted, errors,
+            cursor_before, cursor_after, error, idle, runs
+        FROM sync_runs
+        WHERE error IS NULL
+        ORDER BY worker_id, id DESC
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+pub async fn delete_sync_runs_older_than(pool: &PgPool, days: i64) -> Result<u64> {
+    let result = sqlx::query!(
+        r#"
+        DELETE FROM sync_runs
+        WHERE finished_at < NOW() - make_interval(days => $1::INT)
+        "#,
+        days as i32
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+pub async fn get_user(pool: &PgPool, id: i64) -> Result<Option<User>> {
+    let row = sqlx::query_as!(
+        User,
+        r#"
+        SELECT
+            id, username, previous_usernames, country_code, country_name, avatar_url,
+            ranked_beatmapset_count, loved_beatmapset_count, pending_beatmapset_count,
+            graveyard_beatmapset_count, guest_beatmapset_count,
+            available, fetched_at, created_at, updated_at
+        FROM users WHERE id = $1
+        "#,
+        id
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(row)
+}
+
+pub async fn get_users(pool: &PgPool, ids: &[i64]) -> Result<Vec<User>> {
+    let rows = sqlx::query_as!(
+        User,
+        r#"
+        SELECT
+            id, username, previous_usernames, country_code, country_name, avatar_url,
+            ranked_beatmapset_count, loved_beatmapset_count, pending_beatmapset_count,
+            graveyard_beatmapset_count, guest_beatmapset_count,
+            available, fetched_at, created_at, updated_at
+        FROM users WHERE id = ANY($1)
+        "#,
+        ids
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+/// Stores a fetched profile. A rename we witness ourselves is added to
+/// `previous_usernames` even if osu! doesn't list it. Returns whether the user is new.
+pub async fn upsert_user(pool: &PgPool, user: &User) -> Result<bool> {
+    let inserted = sqlx::query_scalar!(
+        r#"
+        INSERT INTO users (
+            id, username, previous_usernames, country_code, country_name, avatar_url,
+            ranked_beatmapset_count, loved_beatmapset_count, pending_beatmapset_count,
+            graveyard_beatmapset_count, guest_beatmapset_count, available, fetched_at
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, NOW())
+        ON CONFLICT (id) DO UPDATE SET
+            username = EXCLUDED.username,
+            previous_usernames = ARRAY(
+                SELECT DISTINCT name FROM UNNEST(
+                    users.previous_usernames
+                    || EXCLUDED.previous_usernames
+                    || CASE WHEN users.username <> EXCLUDED.username
+                            THEN ARRAY[users.username::TEXT] ELSE '{}'::TEXT[] END
+                ) AS name
+                WHERE name <> EXCLUDED.username
+            ),
+            country_code = COALESCE(EXCLUDED.country_code, users.country_code),
+            country_name = COALESCE(EXCLUDED.country_name, users.country_name),
+            avatar_url = COALESCE(EXCLUDED.avatar_url, users.avatar_url),
+            ranked_beatmapset_count = EXCLUDED.ranked_beatmapset_count,
+            loved_beatmapset_count = EXCLUDED.loved_beatmapset_count,
+            pending_beatmapset_count = EXCLUDED.pending_beatmapset_count,
+            graveyard_beatmapset_count = EXCLUDED.graveyard_beatmapset_count,
+            guest_beatmapset_count = EXCLUDED.guest_beatmapset_count,
+            available = EXCLUDED.available,
+            fetched_at = NOW(),
+            updated_at = NOW()
+        RETURNING (xmax = 0) AS "inserted!"
+        "#,
+        user.id,
+        user.username,
+        &user.previous_usernames,
+        user.country_code,
+        user.country_name,
+        user.avatar_url,
+        user.ranked_beatmapset_count,
+        user.loved_beatmapset_count,
+        user.pending_beatmapset_count,
+        user.graveyard_beatmapset_count,
+        user.guest_beatmapset_count,
+        user.available
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(inserted)
+}
+
+/// Keeps what we know about a user osu! no longer serves, so the id isn't retried
+/// on every cycle. Only mappers of stored sets get a new row, named after their
+/// newest set.
+pub async fn mark_user_unavailable(pool: &PgPool, id: i64) -> Result<()> {
+    sqlx::query!(
+        r#"
+        WITH updated AS (
+            UPDATE users SET available = FALSE, fetched_at = NOW(), updated_at = NOW()
+            WHERE id = $1
+            RETURNING id
+        )
+        INSERT INTO users (id, username, available, fetched_at)
+        SELECT $1, creator, FALSE, NOW()
+        FROM beatmapsets
+        WHERE creator_id = $1 AND NOT EXISTS (SELECT 1 FROM updated)
+        ORDER BY submitted_date DESC NULLS LAST
+        LIMIT 1
+        ON CONFLICT (id) DO NOTHING
+        "#,
+        id
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Mappers we have never fetched, then the profiles fetched longest ago.
+pub async fn get_user_ids_to_sync(pool: &PgPool, limit: i64, stale_days: i32) -> Result<Vec<i64>> {
+    let rows = sqlx::query_scalar!(
+        r#"
+        SELECT id AS "id!" FROM (
+            (SELECT DISTINCT b.creator_id AS id, 0 AS rank, NULL::TIMESTAMPTZ AS fetched_at
+             FROM beatmapsets b
+             LEFT JOIN users u ON u.id = b.creator_id
+             WHERE b.creator_id > 0 AND u.id IS NULL
+             LIMIT $1)
+            UNION ALL
+            (SELECT id, 1 AS rank, fetched_at
+             FROM users
+             WHERE fetched_at < NOW() - make_interval(days => $2)
+             ORDER BY fetched_at ASC
+             LIMIT $1)
+        ) due
+        ORDER BY rank, fetched_at NULLS FIRST
+        LIMIT $1
+        "#,
+        limit,
+        stale_days
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+pub async fn get_user_beatmapsets(
+    pool: &PgPool,
+    user_id: i64,
+    status: Option<RankStatus>,
+    include_deleted: bool,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<i64>> {
+    let rows = sqlx::query_scalar!(
+        r#"
+        SELECT id FROM beatmapsets
+        WHERE creator_id = $1
+          AND ($2::TEXT IS NULL OR status = $2)
+          AND ($3 OR NOT deleted)
+        ORDER BY COALESCE(ranked_date, last_updated, submitted_date) DESC NULLS LAST, id DESC
+        LIMIT $4 OFFSET $5
+        "#,
+        user_id,
+        status.map(|s| s.as_str()),
+        include_deleted,
+        limit,
+        offset
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+/// Returns `true` when the pack was new.
+pub async fn upsert_beatmap_pack(pool: &PgPool, pack: &BeatmapPack) -> Result<bool> {
+    let inserted = sqlx::query_scalar!(
+        r#"
+        INSERT INTO beatmap_packs (
+            tag, name, author, pack_type, date, url, ruleset_id, no_diff_reduction
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        ON CONFLICT (tag) DO UPDATE SET
+            name = EXCLUDED.name,
+            author = EXCLUDED.author,
+            pack_type = EXCLUDED.pack_type,
+            date = EXCLUDED.date,
+            url = EXCLUDED.url,
+            ruleset_id = EXCLUDED.ruleset_id,
+            no_diff_reduction = EXCLUDED.no_diff_reduction,
+            updated_at = NOW()
+        RETURNING (xmax = 0) AS "inserted!"
+        "#,
+        pack.tag,
+        pack.name,
+        pack.author,
+        pack.pack_type,
+        pack.date,
+        pack.url,
+        pack.ruleset_id,
+        pack.no_diff_reduction
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(inserted)
+}
+
+/// Replaces the set list of a pack and marks it as synced.
+pub async fn replace_beatmap_pack_sets(pool: &PgPool, tag: &str, set_ids: &[i64]) -> Result<()> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!("DELETE FROM beatmap_pack_sets WHERE pack_tag = $1", tag)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO beatmap_pack_sets (pack_tag, beatmapset_id)
+        SELECT $1, UNNEST($2::BIGINT[])
+        ON CONFLICT DO NOTHING
+        "#,
+        tag,
+        set_ids
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        "UPDATE beatmap_packs SET sets_synced_at = NOW() WHERE tag = $1",
+        tag
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(())
+}
+
+pub async fn mark_beatmap_pack_sets_synced(pool: &PgPool, tag: &str) -> Result<()> {
+    sqlx::query!(
+        "UPDATE beatmap_packs SET sets_synced_at = NOW() WHERE tag = $1",
+        tag
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn get_beatmap_pack(pool: &PgPool, tag: &str) -> Result<Option<BeatmapPack>> {
+    let row = sqlx::query_as!(
+        BeatmapPack,
+        r#"
+        SELECT tag, name, author, pack_type, date, url, ruleset_id, no_diff_reduction, sets_synced_at
+        FROM beatmap_packs WHERE tag = $1
+        "#,
+        tag
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(row)
+}
+
+/// Packs newest first. `after` is the `(date, tag)` of the last pack of the previous page.
+pub async fn list_beatmap_packs(
+    pool: &PgPool,
+    pack_type: Option<&str>,
+    after: Option<(Option<DateTime<Utc>>, String)>,
+    limit: i64,
+) -> Result<Vec<BeatmapPack>> {
+    let (after_date, after_tag) = match after {
+        Some((date, tag)) => (date, Some(tag)),
+        None => (None, None),
+    };
+
+    let rows = sqlx::query_as!(
+        BeatmapPack,
+        r#"
+        SELECT tag, name, author, pack_type, date, url, ruleset_id, no_diff_reduction, sets_synced_at
+        FROM beatmap_packs
+        WHERE ($1::TEXT IS NULL OR pack_type = $1)
+          AND (
+            $3::TEXT IS NULL
+            OR (COALESCE(date, 'epoch'), tag) < (COALESCE($2::TIMESTAMPTZ, 'epoch'), $3)
+          )
+        ORDER BY COALESCE(date, 'epoch') DESC, tag DESC
+        LIMIT $4
+        "#,
+        pack_type,
+        after_date,
+        after_tag,
+        limit
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+/// Packs whose set list hasn't been fetched yet, newest first.
+pub async fn get_unsynced_beatmap_pack_tags(pool: &PgPool, limit: i64) -> Result<Vec<String>> {
+    let rows = sqlx::query_scalar!(
+        r#"
+        SELECT tag FROM beatmap_packs
+        WHERE sets_synced_at IS NULL
+        ORDER BY date DESC NULLS LAST, tag DESC
+        LIMIT $1
+        "#,
+        limit
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+pub async fn get_beatmap_pack_set_ids(pool: &PgPool, tag: &str) -> Result<Vec<i64>> {
+    let rows = sqlx::query_scalar!(
+        r#"
+        SELECT beatmapset_id FROM beatmap_pack_sets
+        WHERE pack_tag = $1
+        ORDER BY beatmapset_id ASC
+        "#,
+        tag
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+pub async fn get_beatmap_pack_archive_entries(
+    pool: &PgPool,
+    tag: &str,
+) -> Result<Vec<PackArchiveEntry>> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT ps.beatmapset_id, s.artist AS "artist?", s.title AS "title?"
+        FROM beatmap_pack_sets ps
+        LEFT JOIN beatmapsets s ON s.id = ps.beatmapset_id
+        WHERE ps.pack_tag = $1
+        ORDER BY ps.beatmapset_id ASC
+        "#,
+        tag
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| PackArchiveEntry {
+            beatmapset_id: r.beatmapset_id,
+            artist: r.artist,
+            title: r.title,
+        })
+        .collect())
+}
+
+/// `(beatmapset_id, pack_tag)` for every pack containing one of `beatmapset_ids`.
+pub async fn get_beatmapsets_pack_tags(
+    pool: &PgPool,
+    beatmapset_ids: &[i64],
+) -> Result<Vec<(i64, String)>> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT beatmapset_id, pack_tag FROM beatmap_pack_sets
+        WHERE beatmapset_id = ANY($1)
+        ORDER BY pack_tag ASC
+        "#,
+        beatmapset_ids
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(|r| (r.beatmapset_id, r.pack_tag))
+        .collect())
+}
+
+/// The subset of `ids` we have no row for.
+pub async fn get_missing_beatmapset_ids(pool: &PgPool, ids: &[i64]) -> Result<Vec<i64>> {
+    let rows = sqlx::query_scalar!(
+        r#"
+        SELECT u.id AS "id!" FROM UNNEST($1::BIGINT[]) AS u(id)
+        WHERE NOT EXISTS (SELECT 1 FROM beatmapsets s WHERE s.id = u.id)
+        "#,
+        ids
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+/// Pools ordered by tournament, then round and name as created.
+pub async fn list_mappools(
+    pool: &PgPool,
+    tournament: Option<&str>,
+    round: Option<&str>,
+) -> Result<Vec<Mappool>> {
+    let rows = sqlx::query_as!(
+        Mappool,
+        r#"
+        SELECT id, tournament, round, name, created_at, updated_at
+        FROM mappools
+        WHERE ($1::TEXT IS NULL OR tournament = $1)
+          AND ($2::TEXT IS NULL OR round = $2)
+        ORDER BY tournament, id
+        "#,
+        tournament,
+        round
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+pub async fn get_mappool(pool: &PgPool, id: i64) -> Result<Option<Mappool>> {
+    let row = sqlx::query_as!(
+        Mappool,
+        r#"
+        SELECT id, tournament, round, name, created_at, updated_at
+        FROM mappools WHERE id = $1
+        "#,
+        id
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(row)
+}
+
+pub async fn get_mappool_slots(pool: &PgPool, id: i64) -> Result<Vec<MappoolSlot>> {
+    let rows = sqlx::query_as!(
+        MappoolSlot,
+        r#"
+        SELECT slot, beatmap_id, mods, star_rating
+        FROM mappool_slots WHERE mappool_id = $1
+        ORDER BY position
+        "#,
+        id
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+/// Creates a pool, or with `id` overwrites one, replacing its slots. Returns
+/// `None` when `id` doesn't exist.
+pub async fn save_mappool(
+    pool: &PgPool,
+    id: Option<i64>,
+    tournament: &str,
+    round: &str,
+    name: &str,
+    slots: &[MappoolSlot],
+) -> Result<Option<i64>> {
+    let mut tx = pool.begin().await?;
+
+    let id = match id {
+        Some(id) => {
+            sqlx::query_scalar!(
+                r#"
+            UPDATE mappools
+            SET tournament = $2, round = $3, name = $4, updated_at = NOW()
+            WHERE id = $1
+            RETURNING id
+            "#,
+                id,
+                tournament,
+                round,
+                name
+            )
+            .fetch_optional(&mut *tx)
+            .await?
+        }
+        None => Some(
+            sqlx::query_scalar!(
+                r#"
+                INSERT INTO mappools (tournament, round, name)
+                VALUES ($1, $2, $3)
+                RETURNING id
+                "#,
+                tournament,
+                round,
+                name
+            )
+            .fetch_one(&mut *tx)
+            .await?,
+        ),
+    };
+    let Some(id) = id else {
+        return Ok(None);
+    };
+
+    sqlx::query!("DELETE FROM mappool_slots WHERE mappool_id = $1", id)
+        .execute(&mut *tx)
+        .await?;
+
+    let names: Vec<String> = slots.iter().map(|s| s.slot.clone()).collect();
+    let beatmap_ids: Vec<i64> = slots.iter().map(|s| s.beatmap_id).collect();
+    let mods: Vec<i32> = slots.iter().map(|s| s.mods).collect();
+    let star_ratings: Vec<Option<f64>> = slots.iter().map(|s| s.star_rating).collect();
+
+    sqlx::query!(
+        r#"
+        INSERT INTO mappool_slots (mappool_id, slot, position, beatmap_id, mods, star_rating)
+        SELECT $1, slot, position::INTEGER, beatmap_id, mods, star_rating
+        FROM UNNEST($2::VARCHAR[], $3::BIGINT[], $4::INTEGER[], $5::DOUBLE PRECISION[])
+            WITH ORDINALITY AS s(slot, beatmap_id, mods, star_rating, position)
+        "#,
+        id,
+        &names,
+        &beatmap_ids,
+        &mods,
+        &star_ratings as &[Option<f64>]
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(Some(id))
+}
+
+pub async fn delete_mappool(pool: &PgPool, id: i64) -> Result<bool> {
+    let result = sqlx::query!("DELETE FROM mappools WHERE id = $1", id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Cached ranked, approved and loved difficulties that lack current attributes
+/// for any of `mods` or current parsed details. `skip` holds ids that failed
+/// earlier.
+pub async fn get_beatmaps_to_precompute(
+    pool: &PgPool,
+    mods: &[i32],
+    skip: &[i64],
+    limit: i64,
+) -> Result<Vec<OsuFileRef>> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT b.id, b.beatmapset_id, b.version, b.checksum
+        FROM beatmaps b
+        JOIN beatmapsets s ON s.id = b.beatmapset_id
+        JOIN cache_metadata c ON c.beatmapset_id = b.beatmapset_id
+        WHERE s.status IN ('ranked', 'approved', 'loved')
+          AND NOT s.deleted
+          AND NOT b.deleted
+          AND b.id <> ALL($2)
+          AND ((
+              SELECT COUNT(*) FROM beatmap_difficulty_attributes a
+              WHERE a.beatmap_id = b.id
+                AND a.mods = ANY($1)
+                AND a.checksum IS NOT DISTINCT FROM b.checksum
+          ) < CARDINALITY($1)
+          OR NOT EXISTS (
+              SELECT 1 FROM beatmap_details d
+              WHERE d.beatmap_id = b.id AND d.checksum IS NOT DISTINCT FROM b.checksum
+          ))
+        ORDER BY b.id
+        LIMIT $3
+        "#,
+        mods,
+        skip,
+        limit
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(|r| OsuFileRef {
+            beatmap_id: r.id,
+            beatmapset_id: r.beatmapset_id,
+            version: r.version,
+            checksum: r.checksum,
+        })
+        .collect())
+}
+
+/// Replaces the stored attributes of a beatmap for the given mods.
+pub async fn save_difficulty_attributes(
+    pool: &PgPool,
+    checksum: Option<&str>,
+    attrs: &[BeatmapDifficultyAttributes],
+) -> Result<()> {
+    let beatmap_ids: Vec<i64> = attrs.iter().map(|a| a.beatmap_id).collect();
+    let mods: Vec<i32> = attrs.iter().map(|a| a.mods).collect();
+    let star_ratings: Vec<f64> = attrs.iter().map(|a| a.star_rating).collect();
+    let aims: Vec<Option<f64>> = attrs.iter().map(|a| a.aim).collect();
+    let speeds: Vec<Option<f64>> = attrs.iter().map(|a| a.speed).collect();
+    let max_combos: Vec<i32> = attrs.iter().map(|a| a.max_combo).collect();
+
+    sqlx::query!(
+        r#"
+        INSERT INTO beatmap_difficulty_attributes
+            (beatmap_id, mods, checksum, star_rating, aim, speed, max_combo)
+        SELECT beatmap_id, mods, $1, star_rating, aim, speed, max_combo
+        FROM UNNEST(
+            $2::BIGINT[], $3::INTEGER[], $4::DOUBLE PRECISION[],
+            $5::DOUBLE PRECISION[], $6::DOUBLE PRECISION[], $7::INTEGER[]
+        ) AS t(beatmap_id, mods, star_rating, aim, speed, max_combo)
+        ON CONFLICT (beatmap_id, mods) DO UPDATE SET
+            checksum = EXCLUDED.checksum,
+            star_rating = EXCLUDED.star_rating,
+            aim = EXCLUDED.aim,
+            speed = EXCLUDED.speed,
+            max_combo = EXCLUDED.max_combo,
+            computed_at = NOW()
+        "#,
+        checksum,
+        &beatmap_ids,
+        &mods,
+        &star_ratings,
+        &aims as &[Option<f64>],
+        &speeds as &[Option<f64>],
+        &max_combos
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Parsed .osu details of a beatmap, unless they're from an older version of it.
+pub async fn get_beatmap_details(pool: &PgPool, beatmap_id: i64) -> Result<Option<BeatmapDetails>> {
+    let row = sqlx::query!(
+        r#"
+        SELECT
+            d.beatmap_id, d.checksum, d.audio_filename, d.audio_lead_in, d.preview_time,
+            d.keys, d.bpm_min, d.bpm_max, d.bpm_main, d.slider_multiplier, d.slider_tick_rate,
+            d.circles, d.sliders, d.spinners, d.holds,
+            d.slider_ticks as "slider_ticks: Json<Vec<i32>>",
+            d.first_object_time, d.last_object_time, d.drain_time,
+            d.drain_segments as "drain_segments: Json<Vec<DrainSegment>>",
+            d.breaks as "breaks: Json<Vec<BreakPeriod>>",
+            d.density as "density: Json<Vec<i32>>"
+        FROM beatmap_details d
+        JOIN beatmaps b ON b.id = d.beatmap_id
+        WHERE d.beatmap_id = $1 AND d.checksum IS NOT DISTINCT FROM b.checksum
+        "#,
+        beatmap_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|r| BeatmapDetails {
+        beatmap_id: r.beatmap_id,
+        checksum: r.checksum,
+        audio_filename: r.audio_filename,
+        audio_lead_in: r.audio_lead_in,
+        preview_time: r.preview_time,
+        keys: r.keys,
+        bpm_min: r.bpm_min,
+        bpm_max: r.bpm_max,
+        bpm_main: r.bpm_main,
+        slider_multiplier: r.slider_multiplier,
+        slider_tick_rate: r.slider_tick_rate,
+        circles: r.circles,
+        sliders: r.sliders,
+        spinners: r.spinners,
+        holds: r.holds,
+        slider_ticks: r.slider_ticks.0,
+        first_object_time: r.first_object_time,
+        last_object_time: r.last_object_time,
+        drain_time: r.drain_time,
+        drain_segments: r.drain_segments.0,
+        breaks: r.breaks.0,
+        density: r.density.0,
+    }))
+}
+
+pub async fn save_beatmap_details(pool: &PgPool, d: &BeatmapDetails) -> Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO beatmap_details (
+            beatmap_id, checksum, audio_filename, audio_lead_in, preview_time,
+            keys, bpm_min, bpm_max, bpm_main, slider_multiplier, slider_tick_rate,
+            circles, sliders, spinners, holds, slider_ticks,
+            first_object_time, last_object_time, drain_time, drain_segments, breaks, density
+        )
+        VALUES (
+            $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11,
+            $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22
+        )
+        ON CONFLICT (beatmap_id) DO UPDATE SET
+            checksum = EXCLUDED.checksum,
+            audio_filename = EXCLUDED.audio_filename,
+            audio_lead_in = EXCLUDED.audio_lead_in,
+            preview_time = EXCLUDED.preview_time,
+            keys = EXCLUDED.keys,
+            bpm_min = EXCLUDED.bpm_min,
+            bpm_max = EXCLUDED.bpm_max,
+            bpm_main = EXCLUDED.bpm_main,
+            slider_multiplier = EXCLUDED.slider_multiplier,
+            slider_tick_rate = EXCLUDED.slider_tick_rate,
+            circles = EXCLUDED.circles,
+            sliders = EXCLUDED.sliders,
+            spinners = EXCLUDED.spinners,
+            holds = EXCLUDED.holds,
+            slider_ticks = EXCLUDED.slider_ticks,
+            first_object_time = EXCLUDED.first_object_time,
+            last_object_time = EXCLUDED.last_object_time,
+            drain_time = EXCLUDED.drain_time,
+            drain_segments = EXCLUDED.drain_segments,
+            breaks = EXCLUDED.breaks,
+            density = EXCLUDED.density,
+            parsed_at = NOW()
+        "#,
+        d.beatmap_id,
+        d.checksum,
+        d.audio_filename,
+        d.audio_lead_in,
+        d.preview_time,
+        d.keys,
+        d.bpm_min,
+        d.bpm_max,
+        d.bpm_main,
+        d.slider_multiplier,
+        d.slider_tick_rate,
+        d.circles,
+        d.sliders,
+        d.spinners,
+        d.holds,
+        Json(&d.slider_ticks) as _,
+        d.first_object_time,
+        d.last_object_time,
+        d.drain_time,
+        Json(&d.drain_segments) as _,
+        Json(&d.breaks) as _,
+        Json(&d.density) as _
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
 }
@@ -0,0 +1,946 @@
+use chrono::{DateTime, Months, NaiveDate, Utc};
+use sqlx::{Postgres, QueryBuilder};
+
+use super::models::{Beatmapset, RankStatus};
+use crate::error::{AppError, Result};
+
+/// A beatmapset search: free-text keywords plus the filters of the osu! website's
+/// query language (`ar>9 stars=5-6 status=r creator=foo`) and its separate
+/// `m`/`s`/`g`/`l`/`nsfw`/`e` parameters.
+///
+/// Difficulty filters must all hold for the same difficulty, like on osu!.
+#[derive(Debug, Default)]
+pub struct SearchFilter {
+    pub keywords: String,
+    /// Each group is a set of accepted statuses; all groups must match.
+    statuses: Vec<Vec<RankStatus>>,
+    pub mode: Option<i32>,
+    pub genre: Option<i32>,
+    pub language: Option<i32>,
+    pub exclude_nsfw: bool,
+    pub video: bool,
+    pub storyboard: bool,
+    pub include_deleted: bool,
+    /// Mod bitmask whose precomputed star rating `stars` filters on instead of
+    /// the nomod one. Difficulties without attributes for it don't match.
+    pub star_mods: Option<i32>,
+    numbers: Vec<NumberFilter>,
+    dates: Vec<DateFilter>,
+    texts: Vec<TextFilter>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum NumberField {
+    Ar,
+    Cs,
+    Od,
+    Hp,
+    Stars,
+    Bpm,
+    Length,
+    Keys,
+}
+
+impl NumberField {
+    fn column(self) -> &'static str {
+        match self {
+            Self::Ar => "b.ar",
+            Self::Cs | Self::Keys => "b.cs",
+            Self::Od => "b.accuracy",
+            Self::Hp => "b.drain",
+            Self::Stars => "b.difficulty_rating",
+            Self::Bpm => "COALESCE(b.bpm, s.bpm)",
+            Self::Length => "b.total_length",
+        }
+    }
+
+    /// How far off a value may be and still count as equal, as osu! rounds
+    /// what it displays.
+    fn tolerance(self) -> f64 {
+        match self {
+            Self::Stars => 0.005,
+            Self::Ar | Self::Cs | Self::Od | Self::Hp => 0.05,
+            Self::Bpm | Self::Length | Self::Keys => 0.5,
+        }
+    }
+}
+
+/// `lower <= value < upper`, or `<=` for an inclusive upper bound.
+#[derive(Debug)]
+struct NumberFilter {
+    field: NumberField,
+    lower: Option<f64>,
+    upper: Option<(f64, bool)>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum DateField {
+    Created,
+    Updated,
+    Ranked,
+}
+
+impl DateField {
+    fn column(self) -> &'static str {
+        match self {
+            Self::Created => "s.submitted_date",
+            Self::Updated => "s.last_updated",
+            Self::Ranked => "s.ranked_date",
+        }
+    }
+}
+
+/// `lower <= date < upper`.
+#[derive(Debug)]
+struct DateFilter {
+    field: DateField,
+    lower: Option<DateTime<Utc>>,
+    upper: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum TextField {
+    Artist,
+    Title,
+    Creator,
+    Source,
+    Tag,
+    Difficulty,
+}
+
+/// Quoted values must match the whole field, others match anywhere in it.
+#[derive(Debug)]
+struct TextFilter {
+    field: TextField,
+    value: String,
+    exact: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Eq,
+    Lt,
+    Lte,
+    Gt,
+    Gte,
+}
+
+impl SearchFilter {
+    /// Splits `q` into filters and keywords. Words that don't look like a known
+    /// filter stay keywords; known filters with a bad value are an error.
+    pub fn parse(q: &str) -> Result<Self> {
+        let mut filter = Self::default();
+        let mut keywords = Vec::new();
+
+        for token in tokenize(q) {
+            if !filter.apply_token(&token)? {
+                keywords.push(token);
+            }
+        }
+
+        filter.keywords = keywords.join(" ");
+        Ok(filter)
+    }
+
+    /// Website `s` parameter.
+    pub fn add_status_category(&mut self, category: &str) -> Result<()> {
+        use RankStatus::*;
+        let statuses: &[RankStatus] = match category {
+            "any" | "" => return Ok(()),
+            "leaderboard" => &[Ranked, Approved, Qualified, Loved],
+            "ranked" => &[Ranked, Approved],
+            "qualified" => &[Qualified],
+            "loved" => &[Loved],
+            "pending" => &[Pending, Wip],
+            "wip" => &[Wip],
+            "graveyard" => &[Graveyard],
+            "favourites" | "mine" | "played" => {
+                return Err(AppError::BadRequest(format!(
+                    "s={} needs an osu! account and isn't supported",
+                    category
+                )));
+            }
+            other => {
+                return Err(AppError::BadRequest(format!("unknown s value '{}'", other)));
+            }
+        };
+        self.statuses.push(statuses.to_vec());
+        Ok(())
+    }
+
+    /// `status=` in the query, or the plain `status` parameter.
+    pub fn add_status(&mut self, status: &str) -> Result<()> {
+        let status = RankStatus::parse(status)
+            .ok_or_else(|| AppError::BadRequest(format!("unknown status '{}'", status)))?;
+        // Like on osu!, ranked also covers the approved sets of old.
+        let group = if status == RankStatus::Ranked {
+            vec![RankStatus::Ranked, RankStatus::Approved]
+        } else {
+            vec![status]
+        };
+        self.statuses.push(group);
+        Ok(())
+    }
+
+    /// Comma separated statuses of which any may match, as other mirrors take
+    /// them (`ranked,loved` or `1,4`). `all` or `any` matches everything.
+    pub fn add_status_list(&mut self, list: &str) -> Result<()> {
+        let mut group = Vec::new();
+        for value in list.split(',').map(str::trim).filter(|v| !v.is_empty()) {
+            if matches!(value, "all" | "any") {
+                return Ok(());
+            }
+            let status = RankStatus::parse(value)
+                .ok_or_else(|| AppError::BadRequest(format!("unknown status '{}'", value)))?;
+            group.push(status);
+            if status == RankStatus::Ranked {
+                group.push(RankStatus::Approved);
+            }
+        }
+        if !group.is_empty() {
+            self.statuses.push(group);
+        }
+        Ok(())
+    }
+
+    /// Website `e` parameter, e.g. `video.storyboard`.
+    pub fn set_extra(&mut self, extra: &str) -> Result<()> {
+        for part in extra.split('.').filter(|p| !p.is_empty()) {
+            match part {
+                "video" => self.video = true,
+                "storyboard" => self.storyboard = true,
+                other => {
+                    return Err(AppError::BadRequest(format!("unknown e value '{}'", other)));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns `false` when the token isn't a filter.
+    fn apply_token(&mut self, token: &str) -> Result<bool> {
+        let Some((key, op, raw)) = split_filter(token) else {
+            return Ok(false);
+        };
+        let (value, quoted) = unquote(raw);
+        let invalid = || AppError::BadRequest(format!("invalid value in '{}'", token));
+
+        let number_field = match key.as_str() {
+            "ar" => Some(NumberField::Ar),
+            "cs" => Some(NumberField::Cs),
+            "od" => Some(NumberField::Od),
+            "hp" => Some(NumberField::Hp),
+            "stars" | "star" | "sr" => Some(NumberField::Stars),
+            "bpm" => Some(NumberField::Bpm),
+            "length" => Some(NumberField::Length),
+            "keys" => Some(NumberField::Keys),
+            _ => None,
+        };
+        if let Some(field) = number_field {
+            let f = number_filter(field, op, value).ok_or_else(invalid)?;
+            if matches!(field, NumberField::Keys) {
+                self.mode = Some(3);
+            }
+            self.numbers.push(f);
+            return Ok(true);
+        }
+
+        let date_field = match key.as_str() {
+            "created" | "submitted" => Some(DateField::Created),
+            "updated" => Some(DateField::Updated),
+            "ranked" => Some(DateField::Ranked),
+            _ => None,
+        };
+        if let Some(field) = date_field {
+            let f = date_filter(field, op, value).ok_or_else(invalid)?;
+            self.dates.push(f);
+            return Ok(true);
+        }
+
+        let text_field = match key.as_str() {
+            "artist" => Some(TextField::Artist),
+            "title" => Some(TextField::Title),
+            "creator" | "mapper" => Some(TextField::Creator),
+            "source" => Some(TextField::Source),
+            "tag" | "tags" => Some(TextField::Tag),
+            "difficulty" | "diff" | "version" => Some(TextField::Difficulty),
+            _ => None,
+        };
+        if let Some(field) = text_field {
+            if op != Op::Eq || value.is_empty() {
+                return Err(invalid());
+            }
+            self.texts.push(TextFilter {
+                field,
+                value: value.to_string(),
+                exact: quoted,
+            });
+            return Ok(true);
+        }
+
+        if key == "status" {
+            if op != Op::Eq {
+                return Err(invalid());
+            }
+            self.add_status(value)?;
+            return Ok(true);
+        }
+
+        Ok(false)
+    }
+
+    /// The keywords as a `simple` tsquery where every word is a prefix match, so
+    /// results show up while a word is still being typed. `None` when no word
+    /// is left after dropping punctuation.
+    fn ts_query(&self) -> Option<String> {
+        let cleaned: String = self
+            .keywords
+            .chars()
+            .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+            .collect();
+        let words: Vec<String> = cleaned
+            .split_whitespace()
+            .map(|w| format!("{}:*", w.to_lowercase()))
+            .collect();
+        (!words.is_empty()).then(|| words.join(" & "))
+    }
+
+    /// Appends ` WHERE ...` for a query over `beatmapsets s`.
+    pub fn push_where(&self, qb: &mut QueryBuilder<'_, Postgres>) {
+        qb.push(" WHERE TRUE");
+        self.push_set_conditions(qb);
+
+        let has_difficulty_texts = self
+            .texts
+            .iter()
+            .any(|t| matches!(t.field, TextField::Difficulty));
+        if self.mode.is_none() && self.numbers.is_empty() && !has_difficulty_texts {
+            return;
+        }
+
+        qb.push(" AND EXISTS (SELECT 1 FROM beatmaps b");
+        self.push_difficulty_join(qb);
+        qb.push(" WHERE b.beatmapset_id = s.id");
+        self.push_difficulty_conditions(qb);
+        qb.push(")");
+    }
+
+    /// Appends ` FROM ... WHERE ...` for a query over single difficulties,
+    /// `beatmaps b` with their set as `s`.
+    pub fn push_beatmap_from(&self, qb: &mut QueryBuilder<'_, Postgres>) {
+        qb.push(" FROM beatmaps b JOIN beatmapsets s ON s.id = b.beatmapset_id");
+        self.push_difficulty_join(qb);
+        qb.push(" WHERE TRUE");
+        self.push_set_conditions(qb);
+        self.push_difficulty_conditions(qb);
+    }
+
+    fn push_set_conditions(&self, qb: &mut QueryBuilder<'_, Postgres>) {
+        if !self.include_deleted {
+            qb.push(" AND NOT s.deleted");
+        }
+
+        if !self.keywords.is_empty() {
+            // Full-text match on the weighted vector, or a close trigram match on
+            // the title and artist for typos the prefix query can't catch.
+            qb.push(" AND (FALSE");
+            if let Some(query) = self.ts_query() {
+                qb.push(" OR s.search_vector @@ to_tsquery('simple', ")
+                    .push_bind(query)
+                    .push(")");
+            }
+            for column in TRGM_COLUMNS {
+                qb.push(" OR ")
+                    .push_bind(self.keywords.clone())
+                    .push(" <% ")
+                    .push(column);
+            }
+            let pattern = format!("%{}%", like_escape(&self.keywords));
+            push_user_match(qb, &pattern);
+            qb.push(")");
+        }
+
+        for group in &self.statuses {
+            qb.push(" AND s.status = ANY(")
+                .push_bind(group.iter().map(|s| s.as_str()).collect::<Vec<_>>())
+                .push(")");
+        }
+
+        if let Some(genre) = self.genre {
+            qb.push(" AND s.genre_id = ").push_bind(genre);
+        }
+        if let Some(language) = self.language {
+            qb.push(" AND s.language_id = ").push_bind(language);
+        }
+        if self.exclude_nsfw {
+            qb.push(" AND NOT s.nsfw");
+        }
+        if self.video {
+            qb.push(" AND s.video");
+        }
+        if self.storyboard {
+            qb.push(" AND s.storyboard");
+        }
+
+        for d in &self.dates {
+            if let Some(lower) = d.lower {
+                qb.push(" AND ")
+                    .push(d.field.column())
+                    .push(" >= ")
+                    .push_bind(lower);
+            }
+            if let Some(upper) = d.upper {
+                qb.push(" AND ")
+                    .push(d.field.column())
+                    .push(" < ")
+                    .push_bind(upper);
+            }
+        }
+
+        for t in &self.texts {
+            let pattern = t.pattern();
+            match t.field {
+                TextField::Artist => {
+                    qb.push(" AND (s.artist ILIKE ")
+                        .push_bind(pattern.clone())
+                        .push(" OR s.artist_unicode ILIKE ")
+                        .push_bind(pattern)
+                        .push(")");
+                }
+                TextField::Title => {
+                    qb.push(" AND (s.title ILIKE ")
+                        .push_bind(pattern.clone())
+                        .push(" OR s.title_unicode ILIKE ")
+                        .push_bind(pattern)
+                        .push(")");
+                }
+                TextField::Creator => {
+                    qb.push(" AND (s.creator ILIKE ").push_bind(pattern.clone());
+                    push_user_match(qb, &pattern);
+                    qb.push(")");
+                }
+                TextField::Source => {
+                    qb.push(" AND s.source ILIKE ").push_bind(pattern);
+                }
+                // Tags are space separated, so an exact tag is a whole word.
+                TextField::Tag if t.exact => {
+                    qb.push(" AND ' ' || s.tags || ' ' ILIKE ")
+                        .push_bind(format!("% {} %", like_escape(&t.value)));
+                }
+                TextField::Tag => {
+                    qb.push(" AND s.tags ILIKE ").push_bind(pattern);
+                }
+                // Checked per difficulty.
+                TextField::Difficulty => {}
+            }
+        }
+    }
+
+    /// The precomputed mods `stars` filters on, when there is a `stars` filter.
+    fn modded_stars(&self) -> Option<i32> {
+        self.star_mods.filter(|_| {
+            self.numbers
+                .iter()
+                .any(|n| matches!(n.field, NumberField::Stars))
+        })
+    }
+
+    fn push_difficulty_join(&self, qb: &mut QueryBuilder<'_, Postgres>) {
+        if let Some(mods) = self.modded_stars() {
+            qb.push(" JOIN beatmap_difficulty_attributes a ON a.beatmap_id = b.id AND a.mods = ")
+                .push_bind(mods)
+                .push(" AND a.checksum IS NOT DISTINCT FROM b.checksum");
+        }
+    }
+
+    fn push_difficulty_conditions(&self, qb: &mut QueryBuilder<'_, Postgres>) {
+        if !self.include_deleted {
+            qb.push(" AND NOT b.deleted");
+        }
+        if let Some(mode) = self.mode {
+            qb.push(" AND b.mode_int = ").push_bind(mode);
+        }
+        for n in &self.numbers {
+            let column = match n.field {
+                NumberField::Stars if self.modded_stars().is_some() => "a.star_rating",
+                field => field.column(),
+            };
+            if let Some(lower) = n.lower {
+                qb.push(" AND ").push(column).push(" >= ").push_bind(lower);
+            }
+            if let Some((upper, inclusive)) = n.upper {
+                qb.push(" AND ")
+                    .push(column)
+                    .push(if inclusive { " <= " } else { " < " })
+                    .push_bind(upper);
+            }
+        }
+        for t in &self.texts {
+            if matches!(t.field, TextField::Difficulty) {
+                qb.push(" AND b.version ILIKE ").push_bind(t.pattern());
+            }
+        }
+    }
+}
+
+impl TextFilter {
+    fn pattern(&self) -> String {
+        if self.exact {
+            like_escape(&self.value)
+        } else {
+            format!("%{}%", like_escape(&self.value))
+        }
+    }
+}
+
+/// Columns matched by trigram word similarity (`<%`), all backed by a
+/// `gin_trgm_ops` index.
+const TRGM_COLUMNS: [&str; 4] = ["s.title", "s.artist", "s.title_unicode", "s.artist_unicode"];
+
+/// Also matches sets by the mapper's current or previous usernames.
+fn push_user_match(qb: &mut QueryBuilder<'_, Postgres>, pattern: &str) {
+    qb.push(" OR s.creator_id IN (SELECT id FROM users WHERE username ILIKE ")
+        .push_bind(pattern.to_string())
+        .push(" OR array_to_string(previous_usernames, ' ') ILIKE ")
+        .push_bind(pattern.to_string())
+        .push(")");
+}
+
+/// Whitespace-separated words; double quotes keep spaces inside a word.
+fn tokenize(q: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+
+    for c in q.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                current.push(c);
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+
+    tokens
+}
+
+/// `key<op>value` with a purely alphabetic key.
+fn split_filter(token: &str) -> Option<(String, Op, &str)> {
+    let key_len = token
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .filter(|&n| n > 0)?;
+    let (key, rest) = token.split_at(key_len);
+
+    let (op, value) = if let Some(v) = rest.strip_prefix(">=") {
+        (Op::Gte, v)
+    } else if let Some(v) = rest.strip_prefix("<=") {
+        (Op::Lte, v)
+    } else if let Some(v) = rest.strip_prefix('>') {
+        (Op::Gt, v)
+    } else if let Some(v) = rest.strip_prefix('<') {
+        (Op::Lt, v)
+    } else if let Some(v) = rest.strip_prefix('=').or_else(|| rest.strip_prefix(':')) {
+        (Op::Eq, v)
+    } else {
+        return None;
+    };
+
+    if value.is_empty() {
+        return None;
+    }
+    Some((key.to_ascii_lowercase(), op, value))
+}
+
+fn unquote(value: &str) -> (&str, bool) {
+    match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+        Some(inner) => (inner, true),
+        None => (value, false),
+    }
+}
+
+fn number_filter(field: NumberField, op: Op, value: &str) -> Option<NumberFilter> {
+    let parse = |v: &str| match field {
+        NumberField::Length => parse_length(v),
+        _ => v.parse::<f64>().ok().filter(|n| n.is_finite()),
+    };
+
+    // `stars=5-6` is an inclusive range.
+    if op == Op::Eq
+        && let Some((from, to)) = value.split_once('-')
+        && !from.is_empty()
+    {
+        let (from, to) = (parse(from)?, parse(to)?);
+        return Some(NumberFilter {
+            field,
+            lower: Some(from.min(to)),
+            upper: Some((from.max(to), true)),
+        });
+    }
+
+    let n = parse(value)?;
+    let tol = field.tolerance();
+    let (lower, upper) = match op {
+        Op::Eq => (Some(n - tol), Some((n + tol, false))),
+        Op::Lt => (None, Some((n - tol, false))),
+        Op::Lte => (None, Some((n + tol, false))),
+        Op::Gt => (Some(n + tol), None),
+        Op::Gte => (Some(n - tol), None),
+    };
+    Some(NumberFilter {
+        field,
+        lower,
+        upper,
+    })
+}
+
+/// Seconds, optionally with an `s`, `m` or `h` suffix.
+fn parse_length(value: &str) -> Option<f64> {
+    let (number, scale) = match value.char_indices().last()? {
+        (i, 's') => (&value[..i], 1.0),
+        (i, 'm') => (&value[..i], 60.0),
+        (i, 'h') => (&value[..i], 3600.0),
+        _ => (value, 1.0),
+    };
+    number
+        .parse::<f64>()
+        .ok()
+        .filter(|n| n.is_finite())
+        .map(|n| n * scale)
+}
+
+fn date_filter(field: DateField, op: Op, value: &str) -> Option<DateFilter> {
+    let (start, end) = date_range(value)?;
+    let (lower, upper) = match op {
+        Op::Eq => (Some(start), Some(end)),
+        Op::Lt => (None, Some(start)),
+        Op::Lte => (None, Some(end)),
+        Op::Gt => (Some(end), None),
+        Op::Gte => (Some(start), None),
+    };
+    Some(DateFilter {
+        field,
+        lower,
+        upper,
+    })
+}
+
+/// `2020`, `2020-05` or `2020-05-17` as a half-open range covering that period.
+fn date_range(value: &str) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    let parts: Vec<&str> = value.split(['-', '/', '.']).collect();
+    let num = |i: usize| parts.get(i).and_then(|p| p.parse::<u32>().ok());
+
+    let year = parts.first()?.parse::<i32>().ok()?;
+    let (start, end) = match parts.len() {
+        1 => {
+            let start = NaiveDate::from_ymd_opt(year, 1, 1)?;
+            (start, start.checked_add_months(Months::new(12))?)
+        }
+        2 => {
+            let start = NaiveDate::from_ymd_opt(year, num(1)?, 1)?;
+            (start, start.checked_add_months(Months::new(1))?)
+        }
+        3 => {
+            let start = NaiveDate::from_ymd_opt(year, num(1)?, num(2)?)?;
+            (start, start.succ_opt()?)
+        }
+        _ => return None,
+    };
+
+    Some((
+        start.and_hms_opt(0, 0, 0)?.and_utc(),
+        end.and_hms_opt(0, 0, 0)?.and_utc(),
+    ))
+}
+
+fn like_escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+pub struct SearchPage {
+    /// Sets with their beatmaps, mapper and pack tags loaded.
+    pub sets: Vec<Beatmapset>,
+    /// Position of the last set, present when the page was full.
+    pub next_cursor: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortField {
+    Title,
+    Artist,
+    Difficulty,
+    Ranked,
+    Rating,
+    Plays,
+    Favourites,
+    Updated,
+    Relevance,
+}
+
+/// Result order. Ties are broken by set id in the same direction, which makes
+/// `(sort key, id)` a stable keyset position.
+#[derive(Debug, Clone, Copy)]
+pub struct SearchSort {
+    pub field: SortField,
+    pub desc: bool,
+}
+
+impl SearchSort {
+    pub const RANKED_DESC: Self = Self {
+        field: SortField::Ranked,
+        desc: true,
+    };
+
+    /// `{field}_{asc|desc}` as osu! spells it; a bare field sorts descending.
+    pub fn parse(value: &str) -> Result<Self> {
+        let (name, desc) = match value.rsplit_once('_') {
+            Some((name, "asc")) => (name, false),
+            Some((name, "desc")) => (name, true),
+            _ => (value, true),
+        };
+        let field = match name {
+            "title" => SortField::Title,
+            "artist" => SortField::Artist,
+            "difficulty" => SortField::Difficulty,
+            "ranked" => SortField::Ranked,
+            "rating" => SortField::Rating,
+            "plays" => SortField::Plays,
+            "favourites" => SortField::Favourites,
+            "updated" => SortField::Updated,
+            "relevance" => SortField::Relevance,
+            _ => {
+                return Err(AppError::BadRequest(format!("unknown sort '{}'", value)));
+            }
+        };
+        Ok(Self { field, desc })
+    }
+
+    /// Relevance needs keywords to rank by; without them it falls back to the
+    /// ranked date, like osu!.
+    pub fn resolve(sort: Option<Self>, filter: &SearchFilter) -> Self {
+        match sort {
+            Some(s) if s.field == SortField::Relevance && filter.keywords.is_empty() => {
+                Self::RANKED_DESC
+            }
+            Some(s) => s,
+            None if filter.keywords.is_empty() => Self::RANKED_DESC,
+            None => Self {
+                field: SortField::Relevance,
+                desc: true,
+            },
+        }
+    }
+
+    pub fn name(&self) -> String {
+        let field = match self.field {
+            SortField::Title => "title",
+            SortField::Artist => "artist",
+            SortField::Difficulty => "difficulty",
+            SortField::Ranked => "ranked",
+            SortField::Rating => "rating",
+            SortField::Plays => "plays",
+            SortField::Favourites => "favourites",
+            SortField::Updated => "updated",
+            SortField::Relevance => "relevance",
+        };
+        format!("{}_{}", field, if self.desc { "desc" } else { "asc" })
+    }
+
+    /// Key of the sort value in the cursor, named after osu!'s own cursors.
+    pub fn cursor_key(&self) -> &'static str {
+        match self.field {
+            SortField::Title => "title.raw",
+            SortField::Artist => "artist.raw",
+            SortField::Difficulty => "difficultyrating",
+            SortField::Ranked => "approved_date",
+            SortField::Rating => "rating",
+            SortField::Plays => "play_count",
+            SortField::Favourites => "favourite_count",
+            SortField::Updated => "last_update",
+            SortField::Relevance => "_score",
+        }
+    }
+
+    fn sql_type(&self) -> &'static str {
+        match self.field {
+            SortField::Title | SortField::Artist => "TEXT",
+            SortField::Difficulty | SortField::Rating | SortField::Relevance => "DOUBLE PRECISION",
+            SortField::Plays | SortField::Favourites => "BIGINT",
+            SortField::Ranked | SortField::Updated => "TIMESTAMPTZ",
+        }
+    }
+
+    /// The sort key as a non-null expression over `beatmapsets s`, so it can be
+    /// compared in a keyset condition.
+    pub fn push_expr(&self, qb: &mut QueryBuilder<'_, Postgres>, filter: &SearchFilter) {
+        match self.field {
+            SortField::Title => {
+                qb.push("s.title::TEXT");
+            }
+            SortField::Artist => {
+                qb.push("s.artist::TEXT");
+            }
+            SortField::Difficulty => {
+                // Hardest difficulty when sorting down, easiest when sorting up.
+                qb.push(if self.desc {
+                    "COALESCE((SELECT MAX(b.difficulty_rating)"
+                } else {
+                    "COALESCE((SELECT MIN(b.difficulty_rating)"
+                });
+                qb.push(" FROM beatmaps b WHERE b.beatmapset_id = s.id AND NOT b.deleted");
+                if let Some(mode) = filter.mode {
+                    qb.push(" AND b.mode_int = ").push_bind(mode);
+                }
+                qb.push("), 0)");
+            }
+            SortField::Ranked => {
+                qb.push("COALESCE(s.ranked_date, 'epoch'::TIMESTAMPTZ)");
+            }
+            SortField::Rating => {
+                qb.push("COALESCE(s.rating, 0)");
+            }
+            SortField::Plays => {
+                qb.push("COALESCE(s.play_count, 0)::BIGINT");
+            }
+            SortField::Favourites => {
+                qb.push("COALESCE(s.favourite_count, 0)::BIGINT");
+            }
+            SortField::Updated => {
+                qb.push("COALESCE(s.last_updated, 'epoch'::TIMESTAMPTZ)");
+            }
+            SortField::Relevance => {
+                // Full-text rank, plus the best trigram similarity so typo matches
+                // that the tsquery misses still order sensibly.
+                qb.push("(");
+                if let Some(query) = filter.ts_query() {
+                    qb.push("ts_rank(s.search_vector, to_tsquery('simple', ")
+                        .push_bind(query)
+                        .push(")) + ");
+                }
+                qb.push("GREATEST(");
+                for (i, column) in TRGM_COLUMNS.iter().enumerate() {
+                    if i > 0 {
+                        qb.push(", ");
+                    }
+                    qb.push("similarity(COALESCE(")
+                        .push(column)
+                        .push(", ''), ")
+                        .push_bind(filter.keywords.clone())
+                        .push(")");
+                }
+                qb.push("))::DOUBLE PRECISION");
+            }
+        }
+    }
+
+    /// ` AND (key, id) </> (cursor key, cursor id)` for a cursor produced by
+    /// this sort.
+    pub fn push_after(
+        &self,
+        qb: &mut QueryBuilder<'_, Postgres>,
+        filter: &SearchFilter,
+        cursor: &serde_json::Value,
+    ) -> Result<()> {
+        let invalid = || AppError::BadRequest("invalid cursor_string".to_string());
+        let key = cursor.get(self.cursor_key()).ok_or_else(invalid)?.clone();
+        let id = cursor
+            .get("id")
+            .and_then(|v| v.as_i64())
+            .ok_or_else(invalid)?;
+
+        qb.push(" AND (");
+        self.push_expr(qb, filter);
+        qb.push(if self.desc {
+            ", s.id) < (("
+        } else {
+            ", s.id) > (("
+        })
+        .push_bind(sqlx::types::Json(key))
+        .push("::JSONB #>> '{}')::")
+        .push(self.sql_type())
+        .push(", ")
+        .push_bind(id)
+        .push(")");
+        Ok(())
+    }
+
+    pub fn push_order_by(&self, qb: &mut QueryBuilder<'_, Postgres>, filter: &SearchFilter) {
+        let dir = if self.desc { " DESC" } else { " ASC" };
+        qb.push(" ORDER BY ");
+        self.push_expr(qb, filter);
+        qb.push(dir).push(", s.id").push(dir);
+    }
+}
+
+/// Filters of osu! API v1's `/api/get_beatmaps`, over
+/// `beatmaps b JOIN beatmapsets s`.
+#[derive(Debug, Default)]
+pub struct LegacyBeatmapFilter {
+    pub beatmapset_id: Option<i64>,
+    pub beatmap_id: Option<i64>,
+    pub creator: Option<CreatorRef>,
+    pub checksum: Option<String>,
+    /// Only sets ranked, approved, qualified or loved after this date.
+    pub since: Option<DateTime<Utc>>,
+    pub mode: Option<i32>,
+    /// With a non-osu! `mode`, also return osu! beatmaps as converts.
+    pub converts: bool,
+}
+
+#[derive(Debug)]
+pub enum CreatorRef {
+    Id(i64),
+    Name(String),
+}
+
+impl LegacyBeatmapFilter {
+    pub fn push_where(&self, qb: &mut QueryBuilder<'_, Postgres>) {
+        qb.push(" WHERE NOT b.deleted AND NOT s.deleted");
+
+        if let Some(id) = self.beatmapset_id {
+            qb.push(" AND b.beatmapset_id = ").push_bind(id);
+        }
+        if let Some(id) = self.beatmap_id {
+            qb.push(" AND b.id = ").push_bind(id);
+        }
+        match &self.creator {
+            Some(CreatorRef::Id(id)) => {
+                qb.push(" AND s.creator_id = ").push_bind(*id);
+            }
+            Some(CreatorRef::Name(name)) => {
+                qb.push(" AND (LOWER(s.creator) = LOWER(")
+                    .push_bind(name.clone())
+                    .push(
+                        ") OR s.creator_id IN (SELECT id FROM users WHERE LOWER(username) = LOWER(",
+                    )
+                    .push_bind(name.clone())
+                    .push(")))");
+            }
+            None => {}
+        }
+        if let Some(checksum) = &self.checksum {
+            qb.push(" AND b.checksum = ").push_bind(checksum.clone());
+        }
+        if let Some(since) = self.since {
+            qb.push(" AND s.ranked_date > ").push_bind(since);
+        }
+        if let Some(mode) = self.mode {
+            if self.converts && mode != 0 {
+                qb.push(" AND b.mode_int IN (0, ").push_bind(mode).push(")");
+            } else {
+                qb.push(" AND b.mode_int = ").push_bind(mode);
+            }
+        }
+    }
+}
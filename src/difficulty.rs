@@ -0,0 +1,323 @@
+use once_cell::sync::Lazy;
+use reqwest::Client;
+use rosu_pp::{
+    Difficulty, Performance,
+    any::{DifficultyAttributes, PerformanceAttributes},
+};
+use sqlx::PgPool;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::{
+    AppState,
+    config::DifficultyConfig,
+    db::{
+        models::{Beatmap, BeatmapDetails, BeatmapDifficultyAttributes, OsuFileRef},
+        queries,
+    },
+    error::{AppError, Result},
+    mods::Mods,
+    osu_file,
+    storage::{BeatmapStorage, archive::ZipReader},
+};
+
+static HTTP_CLIENT: Lazy<Client> = Lazy::new(|| {
+    Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .expect("failed to build reqwest client")
+});
+
+/// Mod combinations whose attributes are stored for every cached ranked and
+/// loved difficulty, and which `/v2/search?mods=` can filter on.
+pub const PRECOMPUTED_MODS: [Mods; 6] = [
+    Mods::NONE,
+    Mods::HARD_ROCK,
+    Mods::DOUBLE_TIME,
+    Mods::HARD_ROCK.with(Mods::DOUBLE_TIME),
+    Mods::EASY,
+    Mods::HALF_TIME,
+];
+
+/// Results are keyed by checksum too so an updated difficulty is recalculated.
+type CacheKey = (i64, Option<String>, i32);
+
+/// Oldest entries are dropped first once this many are cached.
+const CACHE_CAPACITY: usize = 10_000;
+
+struct AttributesCache {
+    entries: HashMap<CacheKey, DifficultyAttributes>,
+    order: VecDeque<CacheKey>,
+}
+
+static CACHE: Lazy<Mutex<AttributesCache>> = Lazy::new(|| {
+    Mutex::new(AttributesCache {
+        entries: HashMap::new(),
+        order: VecDeque::new(),
+    })
+});
+
+/// Difficulty attributes of a beatmap under `mods`, calculated from its .osu
+/// file and cached per (beatmap, mods).
+pub async fn attributes(
+    state: &AppState,
+    map: &Beatmap,
+    mods: Mods,
+) -> Result<DifficultyAttributes> {
+    let key = (map.id, map.checksum.clone(), mods.bits());
+    if let Some(attrs) = CACHE.lock().unwrap().entries.get(&key) {
+        return Ok(attrs.clone());
+    }
+
+    let osu_file = load_osu_file(state, &OsuFileRef::from(map)).await?;
+    let beatmap_id = map.id;
+    let attrs = tokio::task::spawn_blocking(move || {
+        let parsed = parse(&osu_file, beatmap_id)?;
+        calculate(&parsed, beatmap_id, mods)
+    })
+    .await
+    .map_err(|e| AppError::Internal(e.to_string()))??;
+
+    let mut cache = CACHE.lock().unwrap();
+    if cache.entries.insert(key.clone(), attrs.clone()).is_none() {
+        cache.order.push_back(key);
+        if cache.order.len() > CACHE_CAPACITY
+            && let Some(oldest) = cache.order.pop_front()
+        {
+            cache.entries.remove(&oldest);
+        }
+    }
+
+    Ok(attrs)
+}
+
+fn parse(osu_file: &[u8], beatmap_id: i64) -> Result<rosu_pp::Beatmap> {
+    rosu_pp::Beatmap::from_bytes(osu_file)
+        .map_err(|e| AppError::Internal(format!("failed to parse beatmap {}: {}", beatmap_id, e)))
+}
+
+fn calculate(map: &rosu_pp::Beatmap, beatmap_id: i64, mods: Mods) -> Result<DifficultyAttributes> {
+    Difficulty::new()
+        .mods(mods.bits() as u32)
+        .checked_calculate(map)
+        .map_err(|_| {
+            AppError::BadRequest(format!(
+                "beatmap {} is too unusual to calculate",
+                beatmap_id
+            ))
+        })
+}
+
+/// Score to calculate pp for. Unset values mean a full combo with no misses.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ScoreInput {
+    pub accuracy: Option<f64>,
+    pub combo: Option<u32>,
+    pub misses: Option<u32>,
+}
+
+pub fn performance(
+    attrs: DifficultyAttributes,
+    mods: Mods,
+    score: ScoreInput,
+) -> PerformanceAttributes {
+    let mut calc = Performance::new(attrs).mods(mods.bits() as u32);
+    if let Some(acc) = score.accuracy {
+        calc = calc.accuracy(acc);
+    }
+    if let Some(combo) = score.combo {
+        calc = calc.combo(combo);
+    }
+    if let Some(misses) = score.misses {
+        calc = calc.misses(misses);
+    }
+    calc.calculate()
+}
+
+/// The .osu file from whichever variant of the set is cached, else straight
+/// from osu!.
+pub async fn load_osu_file(state: &AppState, map: &OsuFileRef) -> Result<Vec<u8>> {
+    if let Some(file) = cached_osu_file(&state.storage, map).await {
+        return Ok(file);
+    }
+
+    tracing::info!(
+        "beatmap {} not cached → fetching .osu from osu!",
+        map.beatmap_id
+    );
+    let resp = HTTP_CLIENT
+        .get(format!("https://osu.ppy.sh/osu/{}", map.beatmap_id))
+        .send()
+        .await
+        .map_err(|e| AppError::Internal(format!("failed to fetch .osu file: {}", e)))?;
+    let body = match resp.status() {
+        s if s.is_success() => resp
+            .bytes()
+            .await
+            .map_err(|e| AppError::Internal(format!("failed to fetch .osu file: {}", e)))?,
+        _ => Default::default(),
+    };
+    if body.is_empty() {
+        return Err(AppError::NotFound(format!(
+            "No .osu file available for beatmap {}",
+            map.beatmap_id
+        )));
+    }
+    Ok(body.to_vec())
+}
+
+/// [`osu_file::details`] on the blocking pool, as long maps take a while to parse.
+pub async fn parse_details(
+    osu_file: Arc<[u8]>,
+    beatmap_id: i64,
+    checksum: Option<String>,
+) -> Result<BeatmapDetails> {
+    tokio::task::spawn_blocking(move || osu_file::details(&osu_file, beatmap_id, checksum))
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))
+}
+
+async fn cached_osu_file(storage: &BeatmapStorage, map: &OsuFileRef) -> Option<Vec<u8>> {
+    for no_video in [true, false] {
+        let Ok(Some(data)) = storage.get(map.beatmapset_id, no_video).await else {
+            continue;
+        };
+        match find_osu_file(data, map) {
+            Ok(Some(file)) => return Some(file),
+            Ok(None) => tracing::warn!(
+                "beatmap {} is missing from the cached archive of set {}",
+                map.beatmap_id,
+                map.beatmapset_id
+            ),
+            Err(e) => tracing::warn!(
+                "failed to read cached archive of set {}: {}",
+                map.beatmapset_id,
+                e
+            ),
+        }
+    }
+    None
+}
+
+/// Picks the difficulty by its `BeatmapID`, or by version name for old
+/// files that don't carry one.
+fn find_osu_file(archive: bytes::Bytes, map: &OsuFileRef) -> std::io::Result<Option<Vec<u8>>> {
+    let zip = ZipReader::new(archive)?;
+    let mut by_version = None;
+
+    for entry in zip.entries() {
+        if !entry.name.to_ascii_lowercase().ends_with(".osu") {
+            continue;
+        }
+        let file = zip.read(entry)?;
+        let text = String::from_utf8_lossy(&file);
+        let field = |name: &str| {
+            text.lines()
+                .find_map(|l| l.strip_prefix(name))
+                .map(|v| v.trim().to_string())
+        };
+
+        if field("BeatmapID:").and_then(|id| id.parse().ok()) == Some(map.beatmap_id) {
+            return Ok(Some(file));
+        }
+        if by_version.is_none() && field("Version:").as_deref() == Some(map.version.as_str()) {
+            by_version = Some(file);
+        }
+    }
+
+    Ok(by_version)
+}
+
+/// Starts precomputing [`PRECOMPUTED_MODS`] attributes and parsing the details
+/// of cached difficulties.
+pub async fn start(pool: PgPool, storage: BeatmapStorage, config: DifficultyConfig) {
+    if config.batch_size <= 0 {
+        tracing::info!("Difficulty precomputing disabled");
+        return;
+    }
+    tokio::spawn(precompute_loop(pool, storage, config));
+}
+
+async fn precompute_loop(pool: PgPool, storage: BeatmapStorage, config: DifficultyConfig) {
+    let mods: Vec<i32> = PRECOMPUTED_MODS.iter().map(|m| m.bits()).collect();
+    // Maps that can't be calculated are skipped until the next restart
+    // instead of being retried every cycle.
+    let mut failed: HashSet<i64> = HashSet::new();
+    let interval = Duration::from_secs(config.interval_seconds.max(1));
+
+    loop {
+        let skip: Vec<i64> = failed.iter().copied().collect();
+        let maps = match queries::get_beatmaps_to_precompute(&pool, &mods, &skip, config.batch_size)
+            .await
+        {
+            Ok(maps) => maps,
+            Err(e) => {
+                tracing::error!("Failed to load beatmaps for difficulty precomputing: {}", e);
+                tokio::time::sleep(interval).await;
+                continue;
+            }
+        };
+
+        let full_batch = maps.len() as i64 == config.batch_size;
+        let mut computed = 0;
+        for map in maps {
+            match precompute(&pool, &storage, &map).await {
+                Ok(()) => computed += 1,
+                Err(e) => {
+                    tracing::warn!(
+                        "Failed to precompute difficulty of beatmap {}: {}",
+                        map.beatmap_id,
+                        e
+                    );
+                    failed.insert(map.beatmap_id);
+                }
+            }
+        }
+        if computed > 0 {
+            tracing::info!("Precomputed difficulty attributes of {} beatmaps", computed);
+        }
+
+        // Keep going while there's a backlog.
+        if !full_batch {
+            tokio::time::sleep(interval).await;
+        }
+    }
+}
+
+async fn precompute(pool: &PgPool, storage: &BeatmapStorage, map: &OsuFileRef) -> Result<()> {
+    let osu_file: Arc<[u8]> = cached_osu_file(storage, map)
+        .await
+        .ok_or_else(|| AppError::NotFound("no cached .osu file".to_string()))?
+        .into();
+
+    let details = parse_details(osu_file.clone(), map.beatmap_id, map.checksum.clone()).await?;
+    queries::save_beatmap_details(pool, &details).await?;
+
+    let beatmap_id = map.beatmap_id;
+    let rows = tokio::task::spawn_blocking(move || {
+        let parsed = parse(&osu_file, beatmap_id)?;
+        PRECOMPUTED_MODS
+            .iter()
+            .map(|mods| {
+                let attrs = calculate(&parsed, beatmap_id, *mods)?;
+                let (aim, speed) = match &attrs {
+                    DifficultyAttributes::Osu(a) => (Some(a.aim), Some(a.speed)),
+                    _ => (None, None),
+                };
+                Ok(BeatmapDifficultyAttributes {
+                    beatmap_id,
+                    mods: mods.bits(),
+                    star_rating: attrs.stars(),
+                    aim,
+                    speed,
+                    max_combo: attrs.max_combo() as i32,
+                })
+            })
+            .collect::<Result<Vec<_>>>()
+    })
+    .await
+    .map_err(|e| AppError::Internal(e.to_string()))??;
+
+    queries::save_difficulty_attributes(pool, map.checksum.as_deref(), &rows).await
+}
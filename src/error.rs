@@ -16,6 +16,10 @@ pub enum AppError {
     Http(#[from] reqwest::Error),
     #[error("Not found: {0}")]
     NotFound(String),
+    #[error("Bad request: {0}")]
+    BadRequest(String),
+    #[error("Unauthorized")]
+    Unauthorized,
     #[error("Rate limit exceeded")]
     RateLimitExceeded,
     #[error("Internal error: {0}")]
@@ -26,6 +30,8 @@ impl IntoResponse for AppError {
     fn into_response(self) -> Response {
         let (status, message) = match self {
             AppError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
+            AppError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg),
+            AppError::Unauthorized => (StatusCode::UNAUTHORIZED, "Unauthorized".to_string()),
             AppError::RateLimitExceeded => (
                 StatusCode::TOO_MANY_REQUESTS,
                 "Rate limit exceeded".to_string(),
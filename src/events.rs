@@ -0,0 +1,296 @@
+use chrono::{DateTime, Utc};
+use futures::Stream;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use sqlx::PgPool;
+use std::collections::{HashSet, VecDeque};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::db::{
+    models::{Beatmap, Beatmapset, NewBeatmapsetHistory},
+    queries,
+};
+use crate::error::{AppError, Result};
+
+const BACKLOG_PAGE_SIZE: i64 = 200;
+/// History ids come from a sequence shared by concurrent workers, so a lower
+/// id can commit after a higher one. Replays start this far before the last
+/// event seen to pick such late commits up.
+const REPLAY_OVERLAP_SECS: f64 = 300.0;
+/// Delivered ids remembered per subscriber to skip repeats from the overlap.
+const DELIVERED_CAPACITY: usize = 10_000;
+
+static EVENTS: Lazy<broadcast::Sender<BeatmapEvent>> = Lazy::new(|| broadcast::channel(1024).0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    New,
+    StatusChange,
+    Update,
+    Deleted,
+}
+
+impl EventKind {
+    pub fn from_changes(changes: &[String]) -> Self {
+        let has = |c: &str| changes.iter().any(|x| x == c);
+        if has("created") {
+            Self::New
+        } else if has("deleted") {
+            Self::Deleted
+        } else if has("status") {
+            Self::StatusChange
+        } else {
+            Self::Update
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::New => "new",
+            Self::StatusChange => "status_change",
+            Self::Update => "update",
+            Self::Deleted => "deleted",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "new" => Some(Self::New),
+            "status_change" => Some(Self::StatusChange),
+            "update" => Some(Self::Update),
+            "deleted" => Some(Self::Deleted),
+            _ => None,
+        }
+    }
+}
+
+/// A change to a beatmapset, as seen by the crawler. `id` is the id of the
+/// `beatmapset_history` row, so clients can resume from the last id they saw.
+#[derive(Debug, Clone, Serialize)]
+pub struct BeatmapEvent {
+    pub id: i64,
+    #[serde(rename = "type")]
+    pub kind: EventKind,
+    pub beatmapset_id: i64,
+    pub status: String,
+    pub previous_status: Option<String>,
+    pub changes: Vec<String>,
+    pub modes: Vec<String>,
+    pub artist: String,
+    pub title: String,
+    pub creator: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl BeatmapEvent {
+    pub fn from_history(
+        id: i64,
+        entry: &NewBeatmapsetHistory,
+        set: &Beatmapset,
+        beatmaps: &[Beatmap],
+    ) -> Self {
+        let mut modes: Vec<String> = beatmaps.iter().map(|m| m.mode.clone()).collect();
+        modes.sort();
+        modes.dedup();
+
+        Self {
+            id,
+            kind: EventKind::from_changes(&entry.changes),
+            beatmapset_id: set.id,
+            status: entry.status.clone(),
+            previous_status: entry.previous_status.clone(),
+            changes: entry.changes.clone(),
+            modes,
+            artist: set.artist.clone(),
+            title: set.title.clone(),
+            creator: set.creator.clone(),
+            created_at: Utc::now(),
+        }
+    }
+}
+
+/// Subscriber-side filter. Empty lists match everything.
+#[derive(Debug, Clone, Default)]
+pub struct EventFilter {
+    pub kinds: Vec<EventKind>,
+    pub statuses: Vec<String>,
+    pub modes: Vec<String>,
+}
+
+impl EventFilter {
+    /// Builds a filter from comma separated lists, e.g. `status=ranked,loved&mode=osu,3`.
+    /// Unknown types and modes are rejected rather than ignored, as ignoring
+    /// them would widen the filter to everything.
+    pub fn from_params(
+        kinds: Option<&str>,
+        statuses: Option<&str>,
+        modes: Option<&str>,
+    ) -> Result<Self> {
+        Ok(Self {
+            kinds: split_list(kinds)
+                .map(|k| {
+                    EventKind::parse(&k).ok_or_else(|| {
+                        AppError::BadRequest(format!(
+                            "unknown event type '{}', expected one of new, status_change, update, deleted",
+                            k
+                        ))
+                    })
+                })
+                .collect::<Result<_>>()?,
+            statuses: split_list(statuses).collect(),
+            modes: split_list(modes)
+                .map(|m| {
+                    parse_mode(&m).map(str::to_string).ok_or_else(|| {
+                        AppError::BadRequest(format!(
+                            "unknown mode '{}', expected one of osu, taiko, fruits, mania",
+                            m
+                        ))
+                    })
+                })
+                .collect::<Result<_>>()?,
+        })
+    }
+
+    pub fn matches(&self, event: &BeatmapEvent) -> bool {
+        (self.kinds.is_empty() || self.kinds.contains(&event.kind))
+            && (self.statuses.is_empty() || self.statuses.contains(&event.status))
+            && (self.modes.is_empty() || event.modes.iter().any(|m| self.modes.contains(m)))
+    }
+}
+
+fn split_list(s: Option<&str>) -> impl Iterator<Item = String> + '_ {
+    s.unwrap_or_default()
+        .split(',')
+        .map(|v| v.trim().to_lowercase())
+        .filter(|v| !v.is_empty())
+}
+
+/// osu!'s name for a mode given by name, number or one of the usual aliases.
+pub fn parse_mode(m: &str) -> Option<&'static str> {
+    match m.trim().to_lowercase().as_str() {
+        "0" | "osu" | "std" | "standard" => Some("osu"),
+        "1" | "taiko" => Some("taiko"),
+        "2" | "fruits" | "catch" | "ctb" => Some("fruits"),
+        "3" | "mania" => Some("mania"),
+        _ => None,
+    }
+}
+
+pub fn publish(event: BeatmapEvent) {
+    // No receivers just means nobody is listening right now.
+    let _ = EVENTS.send(event);
+}
+
+pub fn subscribe() -> broadcast::Receiver<BeatmapEvent> {
+    EVENTS.subscribe()
+}
+
+struct Feed {
+    pool: PgPool,
+    filter: EventFilter,
+    rx: broadcast::Receiver<BeatmapEvent>,
+    /// Highest id delivered; replays start shortly before it.
+    last_id: i64,
+    catching_up: bool,
+    /// Paging position in history while catching up.
+    replay_after: Option<i64>,
+    pending: VecDeque<BeatmapEvent>,
+    delivered: HashSet<i64>,
+    delivered_order: VecDeque<i64>,
+}
+
+impl Feed {
+    /// `false` when the event went out already.
+    fn mark_delivered(&mut self, id: i64) -> bool {
+        if !self.delivered.insert(id) {
+            return false;
+        }
+        self.delivered_order.push_back(id);
+        if self.delivered_order.len() > DELIVERED_CAPACITY
+            && let Some(oldest) = self.delivered_order.pop_front()
+        {
+            self.delivered.remove(&oldest);
+        }
+        self.last_id = self.last_id.max(id);
+        true
+    }
+}
+
+/// Replays history after `resume` (if given), then follows the live channel.
+/// When the live channel lags behind, the gap is filled from history again.
+///
+/// Events are deduplicated by id rather than dropped for being older than the
+/// newest one seen, so a late commit still gets through. Replays overlap the
+/// resume point a little, which can repeat events from just before it.
+pub fn stream(
+    pool: PgPool,
+    filter: EventFilter,
+    resume: Option<i64>,
+) -> impl Stream<Item = BeatmapEvent> {
+    // Subscribe before reading the backlog so nothing falls between the two.
+    let state = Feed {
+        pool,
+        filter,
+        rx: subscribe(),
+        last_id: resume.unwrap_or(0),
+        catching_up: resume.is_some(),
+        replay_after: None,
+        pending: VecDeque::new(),
+        delivered: HashSet::new(),
+        delivered_order: VecDeque::new(),
+    };
+
+    futures::stream::unfold(state, |mut f| async move {
+        loop {
+            if let Some(ev) = f.pending.pop_front() {
+                if f.mark_delivered(ev.id) && f.filter.matches(&ev) {
+                    return Some((ev, f));
+                }
+                continue;
+            }
+
+            if f.catching_up {
+                let after = match f.replay_after {
+                    Some(after) => Ok(after),
+                    None => {
+                        queries::get_event_replay_start(&f.pool, f.last_id, REPLAY_OVERLAP_SECS)
+                            .await
+                    }
+                };
+                let page = match after {
+                    Ok(after) => {
+                        queries::get_beatmap_events_since(&f.pool, after, BACKLOG_PAGE_SIZE).await
+                    }
+                    Err(e) => Err(e),
+                };
+                match page {
+                    Ok(page) => {
+                        f.catching_up = page.len() as i64 == BACKLOG_PAGE_SIZE;
+                        f.replay_after = page.last().map(|ev| ev.id).filter(|_| f.catching_up);
+                        f.pending.extend(page);
+                    }
+                    Err(e) => {
+                        tracing::warn!("event backlog query failed: {}", e);
+                        f.catching_up = false;
+                        f.replay_after = None;
+                    }
+                }
+                continue;
+            }
+
+            match f.rx.recv().await {
+                Ok(ev) => {
+                    if f.mark_delivered(ev.id) && f.filter.matches(&ev) {
+                        return Some((ev, f));
+                    }
+                }
+                Err(RecvError::Lagged(n)) => {
+                    tracing::warn!("event subscriber lagged by {} events, replaying", n);
+                    f.catching_up = true;
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    })
+}
@@ -2,9 +2,14 @@ mod api;
 mod config;
 mod crawler;
 mod db;
+mod difficulty;
 mod error;
+mod events;
 mod middleware;
+mod mods;
+mod osu_file;
 mod storage;
+mod webhooks;
 
 use anyhow::Result;
 use sqlx::PgPool;
@@ -17,6 +22,7 @@ pub struct AppState {
     pub db: PgPool,
     pub storage: storage::BeatmapStorage,
     pub osu_client: Arc<crawler::OsuClient>,
+    pub crawler: Arc<crawler::WorkerRegistry>,
 }
 
 #[tokio::main]
@@ -68,22 +74,28 @@ async fn main() -> Result<()> {
         config.osu.client_secret.clone(),
     ));
 
+    let registry = Arc::new(crawler::WorkerRegistry::new());
+
     let state = AppState {
         config: config.clone(),
         db: db.clone(),
         storage,
         osu_client: osu_client.clone(),
+        crawler: registry.clone(),
     };
 
     if config.crawler.enabled {
         let db_clone = db.clone();
         let client_clone = osu_client.clone();
-        let interval = config.crawler.sync_interval_seconds;
+        let crawler_config = config.crawler.clone();
         tokio::spawn(async move {
-            crawler::start_scheduler(db_clone, client_clone, interval).await;
+            crawler::start_scheduler(db_clone, client_clone, crawler_config, registry).await;
         });
     }
 
+    webhooks::start(db.clone(), config.webhooks.clone()).await?;
+    difficulty::start(db.clone(), state.storage.clone(), config.difficulty.clone()).await;
+
     let app = api::routes::create_router(state)
         .layer(CompressionLayer::new())
         .layer(CorsLayer::permissive())
@@ -74,7 +74,10 @@ pub struct RateLimitMiddleware<S> {
 
 impl<S, B> Service<Request<B>> for RateLimitMiddleware<S>
 where
-    S: Service<Request<B>, Response = Response, Error = std::convert::Infallible> + Clone + Send + 'static,
+    S: Service<Request<B>, Response = Response, Error = std::convert::Infallible>
+        + Clone
+        + Send
+        + 'static,
     S::Future: Send + 'static,
     B: Send + 'static,
 {
@@ -108,16 +111,16 @@ where
 }
 
 fn extract_ip<B>(req: &Request<B>) -> String {
-    if let Some(ip) = req.headers().get("cf-connecting-ip") {
-        if let Ok(ip) = ip.to_str() {
-            return ip.to_string();
-        }
+    if let Some(ip) = req.headers().get("cf-connecting-ip")
+        && let Ok(ip) = ip.to_str()
+    {
+        return ip.to_string();
     }
 
-    if let Some(ip) = req.headers().get("x-forwarded-for") {
-        if let Ok(ip) = ip.to_str() {
-            return ip.split(',').next().unwrap_or(ip).trim().to_string();
-        }
+    if let Some(ip) = req.headers().get("x-forwarded-for")
+        && let Ok(ip) = ip.to_str()
+    {
+        return ip.split(',').next().unwrap_or(ip).trim().to_string();
     }
 
     if let Some(addr) = req.extensions().get::<std::net::SocketAddr>() {
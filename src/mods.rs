@@ -0,0 +1,188 @@
+use serde::{Serialize, Serializer};
+
+/// osu!'s legacy mod bitmask, limited to the mods tournaments play with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Mods(u32);
+
+impl Mods {
+    pub const NONE: Mods = Mods(0);
+    pub const NO_FAIL: Mods = Mods(1);
+    pub const EASY: Mods = Mods(1 << 1);
+    pub const HIDDEN: Mods = Mods(1 << 3);
+    pub const HARD_ROCK: Mods = Mods(1 << 4);
+    pub const DOUBLE_TIME: Mods = Mods(1 << 6);
+    pub const HALF_TIME: Mods = Mods(1 << 8);
+    /// Always sent together with double time, as osu! does.
+    pub const NIGHTCORE: Mods = Mods(1 << 9);
+    pub const FLASHLIGHT: Mods = Mods(1 << 10);
+
+    const ACRONYMS: [(&'static str, Mods); 8] = [
+        ("NF", Mods::NO_FAIL),
+        ("EZ", Mods::EASY),
+        ("HD", Mods::HIDDEN),
+        ("HR", Mods::HARD_ROCK),
+        ("DT", Mods::DOUBLE_TIME),
+        ("HT", Mods::HALF_TIME),
+        ("NC", Mods(Mods::NIGHTCORE.0 | Mods::DOUBLE_TIME.0)),
+        ("FL", Mods::FLASHLIGHT),
+    ];
+
+    pub fn from_bits(bits: i32) -> Mods {
+        let known = Self::ACRONYMS.iter().fold(0, |acc, (_, m)| acc | m.0);
+        Mods(bits as u32 & known)
+    }
+
+    pub fn bits(self) -> i32 {
+        self.0 as i32
+    }
+
+    pub const fn with(self, other: Mods) -> Mods {
+        Mods(self.0 | other.0)
+    }
+
+    pub fn contains(self, other: Mods) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// `HDDT`, `hd,dt` or `NM`. `None` on unknown acronyms or mods that
+    /// exclude each other.
+    pub fn parse(s: &str) -> Option<Mods> {
+        let letters: String = s
+            .chars()
+            .filter(|c| !matches!(c, ',' | ' ' | '+'))
+            .collect::<String>()
+            .to_ascii_uppercase();
+        if !letters.is_ascii() || !letters.len().is_multiple_of(2) {
+            return None;
+        }
+
+        let mut mods = Mods::NONE;
+        for i in (0..letters.len()).step_by(2) {
+            match &letters[i..i + 2] {
+                "NM" => {}
+                acronym => {
+                    let (_, m) = Self::ACRONYMS.iter().find(|(a, _)| *a == acronym)?;
+                    mods.0 |= m.0;
+                }
+            }
+        }
+
+        let conflicts = [
+            (Mods::EASY, Mods::HARD_ROCK),
+            (Mods::DOUBLE_TIME, Mods::HALF_TIME),
+        ];
+        if conflicts
+            .iter()
+            .any(|(a, b)| mods.contains(*a) && mods.contains(*b))
+        {
+            return None;
+        }
+        Some(mods)
+    }
+
+    /// Mods a tournament slot name like `HD2`, `DT1` or `HDHR3` implies.
+    /// Freemod (`FM`) and tiebreaker (`TB`) slots have none fixed.
+    pub fn from_slot(slot: &str) -> Option<Mods> {
+        let prefix = slot.trim_end_matches(|c: char| c.is_ascii_digit());
+        match prefix.to_ascii_uppercase().as_str() {
+            "FM" | "TB" | "NM" => Some(Mods::NONE),
+            p => Mods::parse(p),
+        }
+    }
+
+    /// `HDDT`, or `NM` for none.
+    pub fn acronyms(self) -> String {
+        if self == Mods::NONE {
+            return "NM".to_string();
+        }
+        Self::ACRONYMS
+            .iter()
+            .filter(|(a, m)| self.contains(*m) && !(*a == "DT" && self.contains(Mods::NIGHTCORE)))
+            .map(|(a, _)| *a)
+            .collect()
+    }
+
+    pub fn clock_rate(self) -> f64 {
+        if self.contains(Mods::DOUBLE_TIME) {
+            1.5
+        } else if self.contains(Mods::HALF_TIME) {
+            0.75
+        } else {
+            1.0
+        }
+    }
+
+    /// Whether star rating differs from the nomod one.
+    pub fn changes_star_rating(self) -> bool {
+        self.difficulty_mods() != Mods::NONE
+    }
+
+    /// Only the mods that change star rating, so `HDNC` becomes `DT`.
+    pub fn difficulty_mods(self) -> Mods {
+        let relevant = Mods::EASY
+            .with(Mods::HARD_ROCK)
+            .with(Mods::DOUBLE_TIME)
+            .with(Mods::HALF_TIME)
+            .with(Mods::FLASHLIGHT);
+        Mods(self.0 & relevant.0)
+    }
+
+    /// Difficulty settings as the player experiences them, with HR/EZ scaling
+    /// and the clock rate folded into AR and OD.
+    pub fn apply(self, d: Difficulty) -> Difficulty {
+        let scale = if self.contains(Mods::HARD_ROCK) {
+            1.4
+        } else if self.contains(Mods::EASY) {
+            0.5
+        } else {
+            1.0
+        };
+        let cs_scale = if self.contains(Mods::HARD_ROCK) {
+            1.3
+        } else {
+            scale
+        };
+
+        let rate = self.clock_rate();
+        let ar = (d.ar * scale).min(10.0);
+        let od = (d.od * scale).min(10.0);
+
+        Difficulty {
+            cs: (d.cs * cs_scale).min(10.0),
+            ar: preempt_to_ar(ar_to_preempt(ar) / rate),
+            od: (80.0 - (80.0 - 6.0 * od) / rate) / 6.0,
+            hp: (d.hp * scale).min(10.0),
+        }
+    }
+}
+
+impl Serialize for Mods {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.acronyms())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Difficulty {
+    pub cs: f64,
+    pub ar: f64,
+    pub od: f64,
+    pub hp: f64,
+}
+
+/// Milliseconds an object is shown before it has to be hit.
+fn ar_to_preempt(ar: f64) -> f64 {
+    if ar < 5.0 {
+        1800.0 - 120.0 * ar
+    } else {
+        1950.0 - 150.0 * ar
+    }
+}
+
+fn preempt_to_ar(ms: f64) -> f64 {
+    if ms > 1200.0 {
+        (1800.0 - ms) / 120.0
+    } else {
+        (1950.0 - ms) / 150.0
+    }
+}
@@ -0,0 +1,426 @@
+use crate::db::models::{BeatmapDetails, BreakPeriod, DrainSegment};
+
+/// Buckets of the object density graph, spread evenly from the first object
+/// to the end of the last one.
+const DENSITY_BUCKETS: usize = 100;
+
+struct TimingPoint {
+    time: f64,
+    beat_length: f64,
+    uninherited: bool,
+}
+
+struct HitObject {
+    start: f64,
+    end: f64,
+}
+
+/// Details of a difficulty from its .osu file. Times are in milliseconds.
+pub fn details(osu_file: &[u8], beatmap_id: i64, checksum: Option<String>) -> BeatmapDetails {
+    let text = String::from_utf8_lossy(osu_file);
+    let mut section = "";
+
+    let mut audio_filename = None;
+    let mut audio_lead_in = 0;
+    let mut preview_time = None;
+    let mut mode = 0;
+    let mut circle_size = None;
+    let mut slider_multiplier = 1.4;
+    let mut slider_tick_rate = 1.0;
+    let mut breaks = Vec::new();
+    let mut timing_points = Vec::new();
+    // Kept raw until all timing points are known, as slider ends depend on them.
+    let mut object_lines = Vec::new();
+
+    for line in text.lines() {
+        let line = line.trim_start_matches('\u{feff}').trim();
+        if line.is_empty() || line.starts_with("//") {
+            continue;
+        }
+        if line.starts_with('[') && line.ends_with(']') {
+            section = &line[1..line.len() - 1];
+            continue;
+        }
+
+        match section {
+            "General" | "Difficulty" => {
+                let Some((key, value)) = line.split_once(':') else {
+                    continue;
+                };
+                let value = value.trim();
+                match key.trim() {
+                    "AudioFilename" if !value.is_empty() => {
+                        audio_filename = Some(value.to_string())
+                    }
+                    "AudioLeadIn" => audio_lead_in = value.parse().unwrap_or(0),
+                    "PreviewTime" => preview_time = value.parse().ok().filter(|t| *t >= 0),
+                    "Mode" => mode = value.parse().unwrap_or(0),
+                    "CircleSize" => circle_size = value.parse::<f64>().ok(),
+                    "SliderMultiplier" => {
+                        slider_multiplier = value.parse().unwrap_or(slider_multiplier)
+                    }
+                    "SliderTickRate" => {
+                        slider_tick_rate = value.parse().unwrap_or(slider_tick_rate)
+                    }
+                    _ => {}
+                }
+            }
+            "Events" => {
+                let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+                if let [kind, start, end, ..] = fields[..]
+                    && matches!(kind, "2" | "Break")
+                    && let (Ok(start), Ok(end)) = (start.parse::<f64>(), end.parse::<f64>())
+                {
+                    breaks.push(BreakPeriod {
+                        start: start as i32,
+                        end: end as i32,
+                    });
+                }
+            }
+            "TimingPoints" => {
+                let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+                let (Some(Ok(time)), Some(Ok(beat_length))) = (
+                    fields.first().map(|t| t.parse::<f64>()),
+                    fields.get(1).map(|b| b.parse::<f64>()),
+                ) else {
+                    continue;
+                };
+                // Files before v6 have no uninherited flag.
+                let uninherited = match fields.get(6) {
+                    Some(flag) => *flag == "1",
+                    None => beat_length > 0.0,
+                };
+                timing_points.push(TimingPoint {
+                    time,
+                    beat_length,
+                    uninherited,
+                });
+            }
+            "HitObjects" => object_lines.push(line),
+            _ => {}
+        }
+    }
+
+    timing_points.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+    let mut circles = 0;
+    let mut sliders = 0;
+    let mut spinners = 0;
+    let mut holds = 0;
+    let mut slider_ticks = Vec::new();
+    let mut objects = Vec::with_capacity(object_lines.len());
+    for line in object_lines {
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let (Some(Ok(start)), Some(Ok(kind))) = (
+            fields.get(2).map(|t| t.parse::<f64>()),
+            fields.get(3).map(|t| t.parse::<u32>()),
+        ) else {
+            continue;
+        };
+
+        let end = if kind & 1 != 0 {
+            circles += 1;
+            start
+        } else if kind & 2 != 0 {
+            sliders += 1;
+            let slides: i32 = fields
+                .get(6)
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(1)
+                .max(1);
+            let length: f64 = fields.get(7).and_then(|s| s.parse().ok()).unwrap_or(0.0);
+            let (beat_length, velocity) = timing_at(&timing_points, start);
+            let pixels_per_beat = slider_multiplier * 100.0 * velocity;
+            slider_ticks.push(
+                ticks_per_slide(length, pixels_per_beat, beat_length, slider_tick_rate)
+                    .saturating_mul(slides),
+            );
+            if pixels_per_beat > 0.0 {
+                start + length * f64::from(slides) / pixels_per_beat * beat_length
+            } else {
+                start
+            }
+        } else if kind & 8 != 0 {
+            spinners += 1;
+            fields.get(5).and_then(|e| e.parse().ok()).unwrap_or(start)
+        } else if kind & 128 != 0 {
+            holds += 1;
+            fields
+                .get(5)
+                .and_then(|e| e.split(':').next())
+                .and_then(|e| e.parse().ok())
+                .unwrap_or(start)
+        } else {
+            continue;
+        };
+        objects.push(HitObject {
+            start,
+            end: end.max(start),
+        });
+    }
+
+    let first = objects.iter().map(|o| o.start).reduce(f64::min);
+    let last = objects.iter().map(|o| o.end).reduce(f64::max);
+
+    let (bpm_min, bpm_max, bpm_main) = bpms(&timing_points, last);
+
+    let (drain_time, drain_segments, density) = match (first, last) {
+        (Some(first), Some(last)) => {
+            let segments = drain_segments(&breaks, first, last);
+            let drain: f64 = segments.iter().map(|(start, end)| end - start).sum();
+            let segments = segments
+                .into_iter()
+                .map(|(start, end)| DrainSegment {
+                    start: start.round() as i32,
+                    end: end.round() as i32,
+                })
+                .collect();
+            (
+                Some(drain.round() as i32),
+                segments,
+                density_graph(&objects, first, last),
+            )
+        }
+        _ => (None, Vec::new(), Vec::new()),
+    };
+
+    BeatmapDetails {
+        beatmap_id,
+        checksum,
+        audio_filename,
+        audio_lead_in,
+        preview_time,
+        keys: (mode == 3)
+            .then_some(circle_size)
+            .flatten()
+            .map(|cs| cs.round() as i32),
+        bpm_min,
+        bpm_max,
+        bpm_main,
+        slider_multiplier,
+        slider_tick_rate,
+        circles,
+        sliders,
+        spinners,
+        holds,
+        slider_ticks,
+        first_object_time: first.map(|t| t.round() as i32),
+        last_object_time: last.map(|t| t.round() as i32),
+        drain_time,
+        drain_segments,
+        breaks,
+        density,
+    }
+}
+
+/// Beat length and slider velocity multiplier in effect at `time`.
+fn timing_at(points: &[TimingPoint], time: f64) -> (f64, f64) {
+    let mut beat_length = points
+        .iter()
+        .find(|p| p.uninherited)
+        .map(|p| p.beat_length)
+        .unwrap_or(500.0);
+    let mut velocity = 1.0;
+
+    for p in points.iter().take_while(|p| p.time <= time) {
+        if p.uninherited {
+            beat_length = p.beat_length;
+            velocity = 1.0;
+        } else if p.beat_length < 0.0 {
+            velocity = (-100.0 / p.beat_length).clamp(0.1, 10.0);
+        }
+    }
+
+    (beat_length, velocity)
+}
+
+/// Ticks on one slide of a slider, placed like osu! does: every `1 / tick_rate`
+/// beats from its head, leaving out any within 10ms of its end.
+fn ticks_per_slide(length: f64, pixels_per_beat: f64, beat_length: f64, tick_rate: f64) -> i32 {
+    if pixels_per_beat <= 0.0 || beat_length <= 0.0 || tick_rate <= 0.0 {
+        return 0;
+    }
+    let tick_distance = pixels_per_beat / tick_rate;
+    let min_distance_from_end = pixels_per_beat / beat_length * 10.0;
+    let span = length - min_distance_from_end;
+    if span <= 0.0 {
+        return 0;
+    }
+    ((span / tick_distance).ceil() - 1.0).max(0.0) as i32
+}
+
+/// The stretches from `first` to `last` that aren't covered by a break.
+fn drain_segments(breaks: &[BreakPeriod], first: f64, last: f64) -> Vec<(f64, f64)> {
+    let mut breaks: Vec<(f64, f64)> = breaks
+        .iter()
+        .map(|b| (f64::from(b.start).max(first), f64::from(b.end).min(last)))
+        .filter(|(start, end)| end > start)
+        .collect();
+    breaks.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut segments = Vec::new();
+    let mut from = first;
+    for (start, end) in breaks {
+        if start > from {
+            segments.push((from, start));
+        }
+        from = from.max(end);
+    }
+    if last > from {
+        segments.push((from, last));
+    }
+    segments
+}
+
+/// Lowest, highest and longest-lasting BPM, ignoring timing points past the
+/// last object like osu! does.
+fn bpms(points: &[TimingPoint], last: Option<f64>) -> (Option<f64>, Option<f64>, Option<f64>) {
+    let last = last.unwrap_or(f64::MAX);
+    let relevant: Vec<&TimingPoint> = points
+        .iter()
+        .filter(|p| p.uninherited && p.beat_length > 0.0)
+        .enumerate()
+        .filter(|(i, p)| *i == 0 || p.time <= last)
+        .map(|(_, p)| p)
+        .collect();
+
+    let bpm = |p: &TimingPoint| (6_000_000.0 / p.beat_length).round() / 100.0;
+    let min = relevant.iter().map(|p| bpm(p)).reduce(f64::min);
+    let max = relevant.iter().map(|p| bpm(p)).reduce(f64::max);
+
+    let mut durations: Vec<(f64, f64)> = Vec::new();
+    for (i, p) in relevant.iter().enumerate() {
+        let until = relevant.get(i + 1).map(|n| n.time).unwrap_or(last);
+        let duration = (until - p.time).max(0.0);
+        let value = bpm(p);
+        match durations
+            .iter_mut()
+            .find(|(b, _)| (*b - value).abs() < 1e-6)
+        {
+            Some((_, d)) => *d += duration,
+            None => durations.push((value, duration)),
+        }
+    }
+    let main = durations
+        .into_iter()
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(b, _)| b);
+
+    (min, max, main)
+}
+
+/// Objects starting in each of [`DENSITY_BUCKETS`] equal slices of the map.
+fn density_graph(objects: &[HitObject], first: f64, last: f64) -> Vec<i32> {
+    let mut buckets = vec![0; DENSITY_BUCKETS];
+    let span = (last - first).max(1.0);
+    for o in objects {
+        let i = ((o.start - first) / span * DENSITY_BUCKETS as f64) as usize;
+        buckets[i.min(DENSITY_BUCKETS - 1)] += 1;
+    }
+    buckets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STANDARD: &str = "osu file format v14
+
+[General]
+AudioFilename: audio.mp3
+AudioLeadIn: 500
+PreviewTime: 1000
+Mode: 0
+
+[Difficulty]
+CircleSize:4
+SliderMultiplier:1.4
+SliderTickRate:2
+
+[Events]
+//Break Periods
+2,3000,6000
+
+[TimingPoints]
+0,500,4,2,0,100,1,0
+2000,-50,4,2,0,100,0,0
+8000,250,4,2,0,100,1,0
+
+[HitObjects]
+256,192,0,1,0,0:0:0:0:
+256,192,1000,2,0,B|456:192,1,280
+256,192,2000,2,0,B|456:192,2,280
+256,192,7000,12,0,8000,0:0:0:0:
+256,192,9000,1,0,0:0:0:0:
+";
+
+    const MANIA: &str = "osu file format v14
+
+[General]
+Mode: 3
+
+[Difficulty]
+CircleSize:7
+
+[TimingPoints]
+0,400,4,2,0,100,1,0
+
+[HitObjects]
+36,192,100,1,0,0:0:0:0:
+109,192,200,128,0,700:0:0:0:0:
+";
+
+    #[test]
+    fn counts_each_object_type() {
+        let d = details(STANDARD.as_bytes(), 1, None);
+        assert_eq!((d.circles, d.sliders, d.spinners, d.holds), (2, 2, 1, 0));
+        assert_eq!(d.audio_filename.as_deref(), Some("audio.mp3"));
+        assert_eq!(d.audio_lead_in, 500);
+        assert_eq!(d.preview_time, Some(1000));
+        assert_eq!(d.keys, None);
+        assert_eq!(d.density.len(), DENSITY_BUCKETS);
+        assert_eq!(d.density.iter().sum::<i32>(), 5);
+    }
+
+    #[test]
+    fn inherited_point_speeds_up_sliders() {
+        let d = details(STANDARD.as_bytes(), 1, None);
+        // The second slider runs at 2x, so both slides of it take as long
+        // as the single slide of the first one.
+        assert_eq!(d.slider_ticks, vec![3, 2]);
+        assert_eq!(
+            (d.bpm_min, d.bpm_max, d.bpm_main),
+            (Some(120.0), Some(240.0), Some(120.0))
+        );
+        assert_eq!(d.first_object_time, Some(0));
+        assert_eq!(d.last_object_time, Some(9000));
+    }
+
+    #[test]
+    fn breaks_split_drain_time() {
+        let d = details(STANDARD.as_bytes(), 1, None);
+        assert_eq!(d.breaks.len(), 1);
+        assert_eq!((d.breaks[0].start, d.breaks[0].end), (3000, 6000));
+        let segments: Vec<_> = d.drain_segments.iter().map(|s| (s.start, s.end)).collect();
+        assert_eq!(segments, vec![(0, 3000), (6000, 9000)]);
+        assert_eq!(d.drain_time, Some(6000));
+    }
+
+    #[test]
+    fn mania_keys_and_holds() {
+        let d = details(MANIA.as_bytes(), 1, None);
+        assert_eq!(d.keys, Some(7));
+        assert_eq!((d.circles, d.holds), (1, 1));
+        assert_eq!(d.last_object_time, Some(700));
+        assert_eq!(d.drain_time, Some(600));
+        assert_eq!(d.bpm_main, Some(150.0));
+    }
+
+    #[test]
+    fn garbage_has_no_objects() {
+        let d = details(b"\xff\x00 not a beatmap", 1, None);
+        assert_eq!((d.circles, d.sliders, d.spinners, d.holds), (0, 0, 0, 0));
+        assert_eq!(d.drain_time, None);
+        assert!(d.density.is_empty());
+    }
+}
@@ -0,0 +1,462 @@
+use bytes::{BufMut, Bytes, BytesMut};
+use flate2::read::DeflateDecoder;
+use std::io::{self, Read};
+
+const LOCAL_HEADER_SIG: u32 = 0x0403_4b50;
+const CENTRAL_HEADER_SIG: u32 = 0x0201_4b50;
+const EOCD_SIG: u32 = 0x0605_4b50;
+const ZIP64_EOCD_SIG: u32 = 0x0606_4b50;
+const ZIP64_LOCATOR_SIG: u32 = 0x0706_4b50;
+const ZIP64_EXTRA_ID: u16 = 0x0001;
+
+const VERSION_DEFAULT: u16 = 20;
+const VERSION_ZIP64: u16 = 45;
+/// Bit 11: file names are UTF-8.
+const FLAG_UTF8: u16 = 1 << 11;
+
+struct CentralEntry {
+    name: Vec<u8>,
+    crc: u32,
+    size: u64,
+    offset: u64,
+    dos_time: u16,
+    dos_date: u16,
+}
+
+/// Minimal streaming zip writer for bundling cached `.osz` files.
+///
+/// `.osz` files are already deflated, so entries are stored as-is. Each entry is
+/// written as soon as its data is known; the central directory goes out last.
+/// Archives past 4 GiB or 65535 entries switch to ZIP64 records.
+pub struct ZipWriter {
+    entries: Vec<CentralEntry>,
+    offset: u64,
+    dos_time: u16,
+    dos_date: u16,
+    /// Sizes and offsets from here on need ZIP64 records. Tests lower it.
+    zip64_threshold: u64,
+}
+
+impl Default for ZipWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ZipWriter {
+    pub fn new() -> Self {
+        let (dos_time, dos_date) = dos_datetime(chrono::Utc::now());
+        Self {
+            entries: Vec::new(),
+            offset: 0,
+            dos_time,
+            dos_date,
+            zip64_threshold: u32::MAX as u64,
+        }
+    }
+
+    /// Returns the local header followed by the data, ready to be sent.
+    pub fn add_entry(&mut self, name: &str, data: Bytes) -> [Bytes; 2] {
+        let size = data.len() as u64;
+        let crc = crc32fast::hash(&data);
+        let name = name.as_bytes().to_vec();
+        let zip64 = size >= self.zip64_threshold;
+
+        let mut header = BytesMut::with_capacity(30 + name.len() + 20);
+        header.put_u32_le(LOCAL_HEADER_SIG);
+        header.put_u16_le(if zip64 {
+            VERSION_ZIP64
+        } else {
+            VERSION_DEFAULT
+        });
+        header.put_u16_le(FLAG_UTF8);
+        header.put_u16_le(0); // stored
+        header.put_u16_le(self.dos_time);
+        header.put_u16_le(self.dos_date);
+        header.put_u32_le(crc);
+        if zip64 {
+            header.put_u32_le(u32::MAX);
+            header.put_u32_le(u32::MAX);
+        } else {
+            header.put_u32_le(size as u32);
+            header.put_u32_le(size as u32);
+        }
+        header.put_u16_le(name.len() as u16);
+        header.put_u16_le(if zip64 { 20 } else { 0 });
+        header.put_slice(&name);
+        if zip64 {
+            header.put_u16_le(ZIP64_EXTRA_ID);
+            header.put_u16_le(16);
+            header.put_u64_le(size);
+            header.put_u64_le(size);
+        }
+
+        let entry_offset = self.offset;
+        self.offset += header.len() as u64 + size;
+        self.entries.push(CentralEntry {
+            name,
+            crc,
+            size,
+            offset: entry_offset,
+            dos_time: self.dos_time,
+            dos_date: self.dos_date,
+        });
+
+        [header.freeze(), data]
+    }
+
+    /// Central directory and end records. Consumes the writer.
+    pub fn finish(self) -> Bytes {
+        let mut out = BytesMut::new();
+        let cd_start = self.offset;
+
+        for e in &self.entries {
+            let big_size = e.size >= self.zip64_threshold;
+            let big_offset = e.offset >= self.zip64_threshold;
+            let extra_len = (if big_size { 16 } else { 0 }) + (if big_offset { 8 } else { 0 });
+            let version = if extra_len > 0 {
+                VERSION_ZIP64
+            } else {
+                VERSION_DEFAULT
+            };
+
+            out.put_u32_le(CENTRAL_HEADER_SIG);
+            out.put_u16_le(version); // made by
+            out.put_u16_le(version); // needed
+            out.put_u16_le(FLAG_UTF8);
+            out.put_u16_le(0);
+            out.put_u16_le(e.dos_time);
+            out.put_u16_le(e.dos_date);
+            out.put_u32_le(e.crc);
+            let size32 = if big_size { u32::MAX } else { e.size as u32 };
+            out.put_u32_le(size32);
+            out.put_u32_le(size32);
+            out.put_u16_le(e.name.len() as u16);
+            out.put_u16_le(if extra_len > 0 { extra_len + 4 } else { 0 });
+            out.put_u16_le(0); // comment
+            out.put_u16_le(0); // disk
+            out.put_u16_le(0); // internal attrs
+            out.put_u32_le(0); // external attrs
+            out.put_u32_le(if big_offset {
+                u32::MAX
+            } else {
+                e.offset as u32
+            });
+            out.put_slice(&e.name);
+            if extra_len > 0 {
+                out.put_u16_le(ZIP64_EXTRA_ID);
+                out.put_u16_le(extra_len);
+                if big_size {
+                    out.put_u64_le(e.size);
+                    out.put_u64_le(e.size);
+                }
+                if big_offset {
+                    out.put_u64_le(e.offset);
+                }
+            }
+        }
+
+        let cd_size = out.len() as u64;
+        let count = self.entries.len() as u64;
+        let zip64 = count >= u16::MAX as u64
+            || cd_start >= self.zip64_threshold
+            || cd_size >= self.zip64_threshold;
+
+        if zip64 {
+            let zip64_eocd_offset = cd_start + cd_size;
+            out.put_u32_le(ZIP64_EOCD_SIG);
+            out.put_u64_le(44); // size of the rest of this record
+            out.put_u16_le(VERSION_ZIP64);
+            out.put_u16_le(VERSION_ZIP64);
+            out.put_u32_le(0);
+            out.put_u32_le(0);
+            out.put_u64_le(count);
+            out.put_u64_le(count);
+            out.put_u64_le(cd_size);
+            out.put_u64_le(cd_start);
+
+            out.put_u32_le(ZIP64_LOCATOR_SIG);
+            out.put_u32_le(0);
+            out.put_u64_le(zip64_eocd_offset);
+            out.put_u32_le(1);
+        }
+
+        out.put_u32_le(EOCD_SIG);
+        out.put_u16_le(0);
+        out.put_u16_le(0);
+        let count16 = if zip64 { u16::MAX } else { count as u16 };
+        out.put_u16_le(count16);
+        out.put_u16_le(count16);
+        out.put_u32_le(if zip64 { u32::MAX } else { cd_size as u32 });
+        out.put_u32_le(if zip64 { u32::MAX } else { cd_start as u32 });
+        out.put_u16_le(0);
+
+        out.freeze()
+    }
+}
+
+/// Entry of a zip read back with [`ZipReader`].
+pub struct ZipEntry {
+    pub name: String,
+    method: u16,
+    compressed_size: u64,
+    size: u64,
+    header_offset: u64,
+}
+
+/// Reads entries out of an in-memory zip such as a cached `.osz`. Handles
+/// stored and deflated entries and ZIP64 records.
+pub struct ZipReader {
+    data: Bytes,
+    entries: Vec<ZipEntry>,
+}
+
+/// Refuse to inflate entries past this, whatever their header claims.
+const MAX_ENTRY_SIZE: u64 = 256 * 1024 * 1024;
+
+impl ZipReader {
+    pub fn new(data: Bytes) -> io::Result<Self> {
+        let eocd = find_eocd(&data)?;
+        let mut count = u64::from(read_u16(&data, eocd + 10)?);
+        let mut cd_offset = u64::from(read_u32(&data, eocd + 16)?);
+
+        if (count == u64::from(u16::MAX) || cd_offset == u64::from(u32::MAX))
+            && eocd >= 20
+            && read_u32(&data, eocd - 20)? == ZIP64_LOCATOR_SIG
+        {
+            let record = to_usize(read_u64(&data, eocd - 20 + 8)?)?;
+            if read_u32(&data, record)? != ZIP64_EOCD_SIG {
+                return Err(invalid("bad ZIP64 end record"));
+            }
+            count = read_u64(&data, record + 32)?;
+            cd_offset = read_u64(&data, record + 48)?;
+        }
+
+        let mut entries = Vec::new();
+        let mut pos = to_usize(cd_offset)?;
+        for _ in 0..count {
+            if read_u32(&data, pos)? != CENTRAL_HEADER_SIG {
+                return Err(invalid("bad central directory header"));
+            }
+            let method = read_u16(&data, pos + 10)?;
+            let mut compressed_size = u64::from(read_u32(&data, pos + 20)?);
+            let mut size = u64::from(read_u32(&data, pos + 24)?);
+            let name_len = usize::from(read_u16(&data, pos + 28)?);
+            let extra_len = usize::from(read_u16(&data, pos + 30)?);
+            let comment_len = usize::from(read_u16(&data, pos + 32)?);
+            let mut header_offset = u64::from(read_u32(&data, pos + 42)?);
+
+            let name = slice(&data, pos + 46, name_len)?;
+            let name = String::from_utf8_lossy(name).into_owned();
+
+            // ZIP64 extra fields only carry the values that overflowed, in
+            // this order.
+            let extra = slice(&data, pos + 46 + name_len, extra_len)?;
+            let mut i = 0;
+            while i + 4 <= extra.len() {
+                let id = read_u16(extra, i)?;
+                let len = usize::from(read_u16(extra, i + 2)?);
+                if id == ZIP64_EXTRA_ID {
+                    let mut field = i + 4;
+                    for value in [&mut size, &mut compressed_size, &mut header_offset] {
+                        if *value == u64::from(u32::MAX) && field + 8 <= i + 4 + len {
+                            *value = read_u64(extra, field)?;
+                            field += 8;
+                        }
+                    }
+                }
+                i += 4 + len;
+            }
+
+            entries.push(ZipEntry {
+                name,
+                method,
+                compressed_size,
+                size,
+                header_offset,
+            });
+            pos += 46 + name_len + extra_len + comment_len;
+        }
+
+        Ok(Self { data, entries })
+    }
+
+    pub fn entries(&self) -> &[ZipEntry] {
+        &self.entries
+    }
+
+    pub fn read(&self, entry: &ZipEntry) -> io::Result<Vec<u8>> {
+        if entry.size > MAX_ENTRY_SIZE {
+            return Err(invalid("entry too large"));
+        }
+
+        let header = to_usize(entry.header_offset)?;
+        if read_u32(&self.data, header)? != LOCAL_HEADER_SIG {
+            return Err(invalid("bad local header"));
+        }
+        let name_len = usize::from(read_u16(&self.data, header + 26)?);
+        let extra_len = usize::from(read_u16(&self.data, header + 28)?);
+        let raw = slice(
+            &self.data,
+            header + 30 + name_len + extra_len,
+            to_usize(entry.compressed_size)?,
+        )?;
+
+        match entry.method {
+            0 => Ok(raw.to_vec()),
+            8 => {
+                let mut out = Vec::with_capacity(entry.size as usize);
+                DeflateDecoder::new(raw)
+                    .take(entry.size)
+                    .read_to_end(&mut out)?;
+                Ok(out)
+            }
+            m => Err(invalid(&format!("unsupported compression method {}", m))),
+        }
+    }
+}
+
+fn find_eocd(data: &[u8]) -> io::Result<usize> {
+    // The record is 22 bytes plus a comment of up to 64 KiB.
+    let last = data
+        .len()
+        .checked_sub(22)
+        .ok_or_else(|| invalid("too short"))?;
+    let first = last.saturating_sub(u16::MAX as usize);
+    (first..=last)
+        .rev()
+        .find(|&pos| read_u32(data, pos).is_ok_and(|sig| sig == EOCD_SIG))
+        .ok_or_else(|| invalid("no end of central directory record"))
+}
+
+fn slice(data: &[u8], pos: usize, len: usize) -> io::Result<&[u8]> {
+    pos.checked_add(len)
+        .and_then(|end| data.get(pos..end))
+        .ok_or_else(|| invalid("truncated archive"))
+}
+
+fn read_u16(data: &[u8], pos: usize) -> io::Result<u16> {
+    Ok(u16::from_le_bytes(slice(data, pos, 2)?.try_into().unwrap()))
+}
+
+fn read_u32(data: &[u8], pos: usize) -> io::Result<u32> {
+    Ok(u32::from_le_bytes(slice(data, pos, 4)?.try_into().unwrap()))
+}
+
+fn read_u64(data: &[u8], pos: usize) -> io::Result<u64> {
+    Ok(u64::from_le_bytes(slice(data, pos, 8)?.try_into().unwrap()))
+}
+
+fn to_usize(value: u64) -> io::Result<usize> {
+    usize::try_from(value).map_err(|_| invalid("offset out of range"))
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+fn dos_datetime(now: chrono::DateTime<chrono::Utc>) -> (u16, u16) {
+    use chrono::{Datelike, Timelike};
+    let time = ((now.hour() << 11) | (now.minute() << 5) | (now.second() / 2)) as u16;
+    let year = now.year().clamp(1980, 2107) as u32 - 1980;
+    let date = ((year << 9) | (now.month() << 5) | now.day()) as u16;
+    (time, date)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(mut writer: ZipWriter, entries: &[(&str, &[u8])]) -> Bytes {
+        let mut out = BytesMut::new();
+        for (name, data) in entries {
+            for part in writer.add_entry(name, Bytes::copy_from_slice(data)) {
+                out.put_slice(&part);
+            }
+        }
+        out.put_slice(&writer.finish());
+        out.freeze()
+    }
+
+    fn read_all(data: Bytes) -> io::Result<Vec<(String, Vec<u8>)>> {
+        let reader = ZipReader::new(data)?;
+        reader
+            .entries()
+            .iter()
+            .map(|e| Ok((e.name.clone(), reader.read(e)?)))
+            .collect()
+    }
+
+    const ENTRIES: &[(&str, &[u8])] = &[
+        ("1 Artist - Title.osz", b"first set"),
+        ("2 Künstler - Titel.osz", b""),
+        ("3 Artist - Title.osz", &[0xff; 1000]),
+    ];
+
+    fn expected() -> Vec<(String, Vec<u8>)> {
+        ENTRIES
+            .iter()
+            .map(|(name, data)| (name.to_string(), data.to_vec()))
+            .collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let zip = write(ZipWriter::new(), ENTRIES);
+        assert_eq!(read_all(zip).unwrap(), expected());
+    }
+
+    #[test]
+    fn round_trip_zip64_sizes_and_offsets() {
+        let mut writer = ZipWriter::new();
+        writer.zip64_threshold = 0;
+        let zip = write(writer, ENTRIES);
+        assert_eq!(
+            read_u32(&zip, zip.len() - 22 - 20).unwrap(),
+            ZIP64_LOCATOR_SIG
+        );
+        assert_eq!(read_all(zip).unwrap(), expected());
+    }
+
+    #[test]
+    fn round_trip_zip64_entry_count() {
+        let names: Vec<String> = (0..u16::MAX as usize + 1)
+            .map(|i| format!("{}.osz", i))
+            .collect();
+        let entries: Vec<(&str, &[u8])> = names.iter().map(|n| (n.as_str(), &b"x"[..])).collect();
+        let zip = write(ZipWriter::new(), &entries);
+
+        let reader = ZipReader::new(zip).unwrap();
+        assert_eq!(reader.entries().len(), entries.len());
+        let last = reader.entries().last().unwrap();
+        assert_eq!(last.name, "65535.osz");
+        assert_eq!(reader.read(last).unwrap(), b"x");
+    }
+
+    #[test]
+    fn rejects_truncated_archive() {
+        let zip = write(ZipWriter::new(), ENTRIES);
+        for len in [0, 10, zip.len() / 2, zip.len() - 1] {
+            assert!(ZipReader::new(zip.slice(..len)).is_err(), "len {}", len);
+        }
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        assert!(ZipReader::new(Bytes::from_static(&[0x42; 4096])).is_err());
+
+        // A valid end record pointing past the end of the data.
+        let mut zip = BytesMut::from(&write(ZipWriter::new(), ENTRIES)[..]);
+        let eocd = zip.len() - 22;
+        zip[eocd + 16..eocd + 20].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(ZipReader::new(zip.freeze()).is_err());
+    }
+
+    #[test]
+    fn rejects_entry_with_bad_local_header() {
+        let mut zip = BytesMut::from(&write(ZipWriter::new(), ENTRIES)[..]);
+        zip[0] = 0;
+        let reader = ZipReader::new(zip.freeze()).unwrap();
+        assert!(reader.read(&reader.entries()[0]).is_err());
+    }
+}
@@ -11,7 +11,7 @@ pub struct S3Storage {
 
 impl S3Storage {
     pub async fn new(endpoint: &str, bucket: String, region: &str, prefix: String) -> Self {
-        let config = aws_config::defaults(aws_config::BehaviorVersion::v2025_08_07())
+        let config = aws_config::defaults(aws_config::BehaviorVersion::v2026_01_12())
             .endpoint_url(endpoint)
             .region(aws_config::Region::new(region.to_string()))
             .load()
@@ -0,0 +1,296 @@
+use chrono::{Duration as ChronoDuration, Utc};
+use futures::StreamExt;
+use hmac::{Hmac, Mac};
+use once_cell::sync::Lazy;
+use reqwest::Client;
+use serde_json::{Value, json};
+use sha2::Sha256;
+use sqlx::PgPool;
+use std::time::Duration;
+
+use crate::config::WebhookConfig;
+use crate::crawler::sync::{load_cursor, save_cursor};
+use crate::db::{
+    models::{DueDelivery, Webhook},
+    queries,
+};
+use crate::events::{self, BeatmapEvent, EventFilter, EventKind};
+
+const CURSOR_ID: &str = "webhooks";
+const DELIVERY_BATCH_SIZE: i64 = 50;
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+const ENQUEUE_RETRY_MIN: Duration = Duration::from_secs(1);
+const ENQUEUE_RETRY_MAX: Duration = Duration::from_secs(5 * 60);
+const MAX_ATTEMPTS: i32 = 10;
+const BASE_BACKOFF_SECS: i64 = 30;
+const MAX_BACKOFF_SECS: i64 = 6 * 60 * 60;
+
+/// Triggers a webhook can subscribe to.
+pub const TRIGGERS: &[&str] = &[
+    "new",
+    "ranked",
+    "loved",
+    "qualified",
+    "disqualified",
+    "status_change",
+    "updated",
+    "deleted",
+];
+
+pub const FORMATS: &[&str] = &["json", "discord"];
+
+static HTTP_CLIENT: Lazy<Client> = Lazy::new(|| {
+    Client::builder()
+        .timeout(Duration::from_secs(10))
+        .user_agent("osu-mirror-rs/1.0")
+        .build()
+        .expect("failed to build reqwest client")
+});
+
+/// Modes as osu! names them, which is what events carry, so `0` or `std`
+/// match like `osu` does. Errors on the first unknown mode.
+pub fn normalize_modes(modes: &[String]) -> Result<Vec<String>, String> {
+    let mut normalized = Vec::with_capacity(modes.len());
+    for m in modes {
+        let mode = events::parse_mode(m).ok_or_else(|| {
+            format!(
+                "unknown mode '{}', expected one of osu, taiko, fruits, mania",
+                m
+            )
+        })?;
+        if !normalized.iter().any(|n| n == mode) {
+            normalized.push(mode.to_string());
+        }
+    }
+    Ok(normalized)
+}
+
+/// Registers the webhooks from the config file and starts the queueing and
+/// delivery tasks. Fails on a configured webhook with an unknown mode.
+pub async fn start(pool: PgPool, mut configs: Vec<WebhookConfig>) -> anyhow::Result<()> {
+    for config in &mut configs {
+        config.modes = normalize_modes(&config.modes)
+            .map_err(|e| anyhow::anyhow!("webhook {}: {}", config.url, e))?;
+    }
+
+    if let Err(e) = queries::sync_config_webhooks(&pool, &configs).await {
+        tracing::error!("Failed to register configured webhooks: {}", e);
+    }
+
+    tokio::spawn(enqueue_loop(pool.clone()));
+    tokio::spawn(delivery_loop(pool));
+    Ok(())
+}
+
+/// Turns beatmap events into queued deliveries. The last handled event id is
+/// kept in `sync_cursors`, so events published while we were down are replayed
+/// from history on the next start.
+async fn enqueue_loop(pool: PgPool) {
+    let resume = match load_cursor(&pool, CURSOR_ID).await {
+        Ok(cursor) => cursor.and_then(|c| c.parse::<i64>().ok()),
+        Err(e) => {
+            tracing::error!("Failed to load webhook cursor: {}", e);
+            None
+        }
+    };
+
+    let mut stream = Box::pin(events::stream(pool.clone(), EventFilter::default(), resume));
+
+    while let Some(event) = stream.next().await {
+        // The cursor only moves past an event once it's queued, so keep
+        // retrying it rather than skipping ahead.
+        let mut backoff = ENQUEUE_RETRY_MIN;
+        while let Err(e) = enqueue_event(&pool, &event).await {
+            tracing::error!(
+                "Failed to queue webhooks for event {}, retrying in {:?}: {}",
+                event.id,
+                backoff,
+                e
+            );
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(ENQUEUE_RETRY_MAX);
+        }
+        if let Err(e) = save_cursor(&pool, CURSOR_ID, Some(event.id.to_string())).await {
+            tracing::error!("Failed to save webhook cursor: {}", e);
+        }
+    }
+}
+
+async fn enqueue_event(pool: &PgPool, event: &BeatmapEvent) -> anyhow::Result<()> {
+    let triggers = event_triggers(event);
+    if triggers.is_empty() {
+        return Ok(());
+    }
+
+    for webhook in queries::list_webhooks(pool).await? {
+        if !webhook.enabled {
+            continue;
+        }
+        let Some(trigger) = triggers
+            .iter()
+            .find(|t| webhook.events.iter().any(|e| e == *t))
+        else {
+            continue;
+        };
+        if !webhook.modes.is_empty() && !event.modes.iter().any(|m| webhook.modes.contains(m)) {
+            continue;
+        }
+
+        let payload = build_payload(&webhook, trigger, event);
+        queries::enqueue_webhook_delivery(pool, webhook.id, event.id, trigger, &payload).await?;
+    }
+
+    Ok(())
+}
+
+/// Maps an event onto webhook triggers, most specific first.
+fn event_triggers(event: &BeatmapEvent) -> Vec<&'static str> {
+    let mut triggers = Vec::new();
+    let has = |c: &str| event.changes.iter().any(|x| x == c);
+
+    match event.kind {
+        EventKind::New => triggers.push("new"),
+        EventKind::Deleted => triggers.push("deleted"),
+        EventKind::StatusChange => {
+            if has("disqualified") {
+                triggers.push("disqualified");
+            }
+            match event.status.as_str() {
+                "ranked" | "approved" => triggers.push("ranked"),
+                "loved" => triggers.push("loved"),
+                "qualified" => triggers.push("qualified"),
+                _ => {}
+            }
+            triggers.push("status_change");
+        }
+        EventKind::Update => triggers.push("updated"),
+    }
+
+    triggers
+}
+
+fn build_payload(webhook: &Webhook, trigger: &str, event: &BeatmapEvent) -> Value {
+    match webhook.format.as_str() {
+        "discord" => discord_payload(trigger, event),
+        _ => json!({
+            "event": trigger,
+            "data": event,
+        }),
+    }
+}
+
+fn discord_payload(trigger: &str, event: &BeatmapEvent) -> Value {
+    let color = match trigger {
+        "ranked" => 0x66ccff,
+        "loved" => 0xff66aa,
+        "qualified" => 0xb3ff66,
+        "disqualified" | "deleted" => 0xff6666,
+        _ => 0xcccccc,
+    };
+
+    let status = match &event.previous_status {
+        Some(prev) if prev != &event.status => format!("{} → {}", prev, event.status),
+        _ => event.status.clone(),
+    };
+
+    json!({
+        "username": "osu-mirror-rs",
+        "embeds": [{
+            "title": format!("{} - {}", event.artist, event.title),
+            "url": format!("https://osu.ppy.sh/beatmapsets/{}", event.beatmapset_id),
+            "description": format!("Mapped by {}\n**{}**: {}", event.creator, trigger, status),
+            "color": color,
+            "thumbnail": {
+                "url": format!(
+                    "https://assets.ppy.sh/beatmaps/{}/covers/list@2x.jpg",
+                    event.beatmapset_id
+                ),
+            },
+            "timestamp": event.created_at,
+        }],
+    })
+}
+
+async fn delivery_loop(pool: PgPool) {
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let due = match queries::claim_due_webhook_deliveries(&pool, DELIVERY_BATCH_SIZE).await {
+            Ok(due) => due,
+            Err(e) => {
+                tracing::error!("Failed to load webhook deliveries: {}", e);
+                continue;
+            }
+        };
+
+        for delivery in due {
+            if let Err(e) = deliver(&pool, &delivery).await {
+                tracing::error!("Failed to record webhook delivery {}: {}", delivery.id, e);
+            }
+        }
+    }
+}
+
+async fn deliver(pool: &PgPool, delivery: &DueDelivery) -> anyhow::Result<()> {
+    let body = serde_json::to_vec(&delivery.payload)?;
+    let timestamp = Utc::now().timestamp().to_string();
+
+    let mut request = HTTP_CLIENT
+        .post(&delivery.url)
+        .header("Content-Type", "application/json")
+        .header("X-Mirror-Event", &delivery.event)
+        .header("X-Mirror-Delivery", delivery.id.to_string())
+        .header("X-Mirror-Timestamp", &timestamp);
+
+    if let Some(secret) = delivery.secret.as_deref().filter(|s| !s.is_empty()) {
+        request = request.header("X-Mirror-Signature", sign(secret, &timestamp, &body));
+    }
+
+    let (status_code, error) = match request.body(body).send().await {
+        Ok(resp) if resp.status().is_success() => {
+            queries::mark_webhook_delivered(pool, delivery.id, resp.status().as_u16() as i32)
+                .await?;
+            return Ok(());
+        }
+        Ok(resp) => (
+            Some(resp.status().as_u16() as i32),
+            format!("HTTP {}", resp.status()),
+        ),
+        Err(e) => (None, e.to_string()),
+    };
+
+    let attempts = delivery.attempts + 1;
+    let retry_at = (attempts < MAX_ATTEMPTS).then(|| Utc::now() + backoff(attempts));
+
+    if retry_at.is_none() {
+        tracing::warn!(
+            "Webhook delivery {} to {} gave up after {} attempts: {}",
+            delivery.id,
+            delivery.url,
+            attempts,
+            error
+        );
+    }
+
+    queries::mark_webhook_attempt_failed(pool, delivery.id, status_code, &error, retry_at).await?;
+    Ok(())
+}
+
+fn backoff(attempts: i32) -> ChronoDuration {
+    let secs = BASE_BACKOFF_SECS
+        .saturating_mul(1i64 << attempts.clamp(0, 20))
+        .min(MAX_BACKOFF_SECS);
+    ChronoDuration::seconds(secs)
+}
+
+/// `sha256=<hex>` HMAC over `{timestamp}.{body}`, so receivers can reject replays.
+fn sign(secret: &str, timestamp: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(timestamp.as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}